@@ -0,0 +1,181 @@
+/*!
+Derive macros for the `external` crate.
+
+These are re-exported by `external`, depend on that crate instead of using this one directly.
+!*/
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, parse_quote_spanned, Data, DeriveInput, Error, Fields, Member, Meta, Token, Type};
+
+/// Derives `Pod` after validating the layout of the struct.
+///
+/// See the `external::ptr::Pod` trait for more information.
+#[proc_macro_derive(Pod)]
+pub fn derive_pod(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match pod(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+/// Derives `FromRemoteBytes` validating every field or the discriminant.
+///
+/// See the `external::ptr::FromRemoteBytes` trait for more information.
+#[proc_macro_derive(FromRemoteBytes)]
+pub fn derive_from_remote_bytes(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match from_remote_bytes(&input) {
+		Ok(tokens) => tokens.into(),
+		Err(err) => err.to_compile_error().into(),
+	}
+}
+
+// The arguments of the repr attributes.
+fn reprs(input: &DeriveInput) -> syn::Result<Vec<Meta>> {
+	let mut reprs = Vec::new();
+	for attr in &input.attrs {
+		if attr.path().is_ident("repr") {
+			reprs.extend(attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?);
+		}
+	}
+	Ok(reprs)
+}
+
+// Only structs with a defined layout qualify, the default Rust layout may reorder and pad fields.
+fn check_repr(input: &DeriveInput, trait_name: &str) -> syn::Result<()> {
+	if reprs(input)?.iter().any(|meta| meta.path().is_ident("C") || meta.path().is_ident("transparent")) {
+		return Ok(());
+	}
+	Err(Error::new(input.ident.span(), format!("{} can only be derived for structs with #[repr(C)] or #[repr(transparent)]", trait_name)))
+}
+
+fn pod(input: &DeriveInput) -> syn::Result<TokenStream2> {
+	let fields = match &input.data {
+		Data::Struct(data) => &data.fields,
+		Data::Enum(data) => return Err(Error::new(data.enum_token.span(), "Pod cannot be derived for enums, not every bit pattern is a valid discriminant")),
+		Data::Union(data) => return Err(Error::new(data.union_token.span(), "Pod cannot be derived for unions")),
+	};
+	check_repr(input, "Pod")?;
+
+	let name = &input.ident;
+	let types: Vec<&Type> = fields.iter().map(|field| &field.ty).collect();
+
+	// Every field must be Pod, the bounds are checked where the impl applies
+	let mut generics = input.generics.clone();
+	let where_clause = generics.make_where_clause();
+	for ty in &types {
+		where_clause.predicates.push(parse_quote_spanned!(ty.span()=> #ty: ::external::ptr::Pod));
+	}
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+	// Without padding the size of the struct is the sum of the sizes of its fields, the fields are checked first as they may be generic structs themselves
+	let message = format!("Pod cannot be derived for `{}`, it contains padding", name);
+	let mut tokens = quote! {
+		unsafe impl #impl_generics ::external::ptr::Pod for #name #ty_generics #where_clause {
+			#[doc(hidden)]
+			const __ASSERT_LAYOUT: () = {
+				#(<#types as ::external::ptr::Pod>::__ASSERT_LAYOUT;)*
+				::core::assert!(
+					::core::mem::size_of::<Self>() == 0 #(+ ::core::mem::size_of::<#types>())*,
+					#message
+				)
+			};
+		}
+	};
+	// Generic structs are checked for every instantiation when used, concrete structs are checked right away
+	if input.generics.params.is_empty() {
+		tokens.extend(quote! {
+			const _: () = <#name as ::external::ptr::Pod>::__ASSERT_LAYOUT;
+		});
+	}
+	Ok(tokens)
+}
+
+// Integer types accepted as the repr of an enum.
+const INT_REPRS: [&str; 10] = ["u8", "u16", "u32", "u64", "usize", "i8", "i16", "i32", "i64", "isize"];
+
+fn from_remote_bytes(input: &DeriveInput) -> syn::Result<TokenStream2> {
+	let name = &input.ident;
+	let mut generics = input.generics.clone();
+	let body = match &input.data {
+		Data::Struct(data) => {
+			check_repr(input, "FromRemoteBytes")?;
+			let where_clause = generics.make_where_clause();
+			let mut checks = Vec::new();
+			for (index, field) in data.fields.iter().enumerate() {
+				let member = match &field.ident {
+					Some(ident) => Member::Named(ident.clone()),
+					None => Member::Unnamed(index.into()),
+				};
+				let ty = &field.ty;
+				let check = validate_type(ty, &mut |elem| {
+					where_clause.predicates.push(parse_quote_spanned!(elem.span()=> #elem: ::external::ptr::FromRemoteBytes));
+				});
+				checks.push(quote! {
+					{
+						let bytes = &bytes[::core::mem::offset_of!(Self, #member)..][..::core::mem::size_of::<#ty>()];
+						#check
+					}
+				});
+			}
+			quote!(true #(&& #checks)*)
+		},
+		Data::Enum(data) => {
+			let repr = reprs(input)?.into_iter()
+				.filter_map(|meta| meta.path().get_ident().cloned())
+				.find(|ident| INT_REPRS.iter().any(|int| ident == int))
+				.ok_or_else(|| Error::new(name.span(), "FromRemoteBytes can only be derived for enums with an integer repr such as #[repr(u32)]"))?;
+			let mut variants = Vec::new();
+			for variant in &data.variants {
+				if !matches!(variant.fields, Fields::Unit) {
+					return Err(Error::new(variant.ident.span(), "FromRemoteBytes cannot be derived for enums with fields"));
+				}
+				variants.push(&variant.ident);
+			}
+			quote! {
+				let mut value = [0u8; ::core::mem::size_of::<#repr>()];
+				value.copy_from_slice(bytes);
+				let value = #repr::from_ne_bytes(value);
+				false #(|| value == Self::#variants as #repr)*
+			}
+		},
+		Data::Union(data) => return Err(Error::new(data.union_token.span(), "FromRemoteBytes cannot be derived for unions")),
+	};
+	let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+	Ok(quote! {
+		unsafe impl #impl_generics ::external::ptr::FromRemoteBytes for #name #ty_generics #where_clause {
+			fn validate(bytes: &[u8]) -> bool {
+				#body
+			}
+		}
+	})
+}
+
+// Validates the bytes of a field.
+//
+// Arrays are only FromRemoteBytes when their elements are Pod, other arrays are validated element by element.
+fn validate_type(ty: &Type, bound: &mut dyn FnMut(&Type)) -> TokenStream2 {
+	match ty {
+		Type::Array(array) => {
+			let elem = &array.elem;
+			let check = validate_type(elem, bound);
+			quote! {{
+				let size = ::core::mem::size_of::<#elem>();
+				size == 0 || bytes.chunks_exact(size).all(|bytes| #check)
+			}}
+		},
+		Type::Paren(paren) => validate_type(&paren.elem, bound),
+		Type::Group(group) => validate_type(&group.elem, bound),
+		_ => {
+			bound(ty);
+			quote!(<#ty as ::external::ptr::FromRemoteBytes>::validate(bytes))
+		},
+	}
+}
@@ -2,15 +2,18 @@
 Error codes.
 !*/
 
-use std::{fmt, error};
+use std::{fmt, error, io};
+#[cfg(windows)]
 use crate::winapi::*;
 
 /// Windows error code.
 ///
 /// See [System Error Codes](https://msdn.microsoft.com/en-us/library/windows/desktop/ms681381.aspx) for more information.
+///
+/// On other platforms the OS error numbers are translated to their closest Windows equivalent.
 #[derive(Copy, Clone, Eq, PartialEq)]
-pub struct ErrorCode(DWORD);
-impl_inner!(ErrorCode: DWORD);
+pub struct ErrorCode(u32);
+impl_inner!(ErrorCode: u32);
 impl From<u32> for ErrorCode {
 	fn from(error_code: u32) -> ErrorCode {
 		ErrorCode(error_code)
@@ -31,6 +34,7 @@ impl AsMut<u32> for ErrorCode {
 		&mut self.0
 	}
 }
+#[cfg(windows)]
 impl ErrorCode {
 	/// Get the last error code.
 	///
@@ -39,6 +43,78 @@ impl ErrorCode {
 		ErrorCode(unsafe { GetLastError() })
 	}
 }
+#[cfg(unix)]
+impl ErrorCode {
+	/// Get the last error code.
+	///
+	/// Translates the thread's `errno`, see [`from_errno`](#method.from_errno).
+	pub fn last() -> ErrorCode {
+		ErrorCode::from_errno(io::Error::last_os_error().raw_os_error().unwrap_or(0))
+	}
+	/// Translates an `errno` value to its closest Windows error code.
+	///
+	/// Error numbers without an equivalent are kept with the customer bit set: `0x20000000 | errno`.
+	pub fn from_errno(errno: i32) -> ErrorCode {
+		match errno {
+			0 => ERROR_SUCCESS,
+			libc::ENOENT => ERROR_FILE_NOT_FOUND,
+			libc::EPERM | libc::EACCES => ERROR_ACCESS_DENIED,
+			libc::EBADF => ERROR_INVALID_HANDLE,
+			libc::ENOMEM => ERROR_NOT_ENOUGH_MEMORY,
+			libc::ESRCH | libc::EINVAL => ERROR_INVALID_PARAMETER,
+			libc::EFAULT | libc::EIO => ERROR_PARTIAL_COPY,
+			libc::ENOSYS => ERROR_NOT_SUPPORTED,
+			libc::EBUSY | libc::EAGAIN => ERROR_BUSY,
+			libc::ETIMEDOUT => ERROR_TIMEOUT,
+			libc::ECANCELED => ERROR_CANCELLED,
+			errno => ErrorCode(0x2000_0000 | errno as u32),
+		}
+	}
+}
+impl From<io::Error> for ErrorCode {
+	fn from(err: io::Error) -> ErrorCode {
+		// Error codes converted to io errors are passed back unchanged
+		if let Some(&code) = err.get_ref().and_then(|inner| inner.downcast_ref::<ErrorCode>()) {
+			return code;
+		}
+		match err.raw_os_error() {
+			#[cfg(windows)]
+			Some(code) => ErrorCode(code as u32),
+			#[cfg(unix)]
+			Some(errno) => ErrorCode::from_errno(errno),
+			#[cfg(not(any(windows, unix)))]
+			Some(_) => ERROR_GEN_FAILURE,
+			None => match err.kind() {
+				io::ErrorKind::NotFound => ERROR_FILE_NOT_FOUND,
+				io::ErrorKind::PermissionDenied => ERROR_ACCESS_DENIED,
+				io::ErrorKind::InvalidInput => ERROR_INVALID_PARAMETER,
+				io::ErrorKind::InvalidData => ERROR_INVALID_DATA,
+				io::ErrorKind::UnexpectedEof => ERROR_HANDLE_EOF,
+				io::ErrorKind::TimedOut => ERROR_TIMEOUT,
+				_ => ERROR_GEN_FAILURE,
+			},
+		}
+	}
+}
+impl From<ErrorCode> for io::Error {
+	#[cfg(windows)]
+	fn from(err: ErrorCode) -> io::Error {
+		io::Error::from_raw_os_error(err.0 as i32)
+	}
+	#[cfg(not(windows))]
+	fn from(err: ErrorCode) -> io::Error {
+		let kind = match err {
+			ERROR_FILE_NOT_FOUND => io::ErrorKind::NotFound,
+			ERROR_ACCESS_DENIED => io::ErrorKind::PermissionDenied,
+			ERROR_INVALID_PARAMETER => io::ErrorKind::InvalidInput,
+			ERROR_INVALID_DATA => io::ErrorKind::InvalidData,
+			ERROR_HANDLE_EOF => io::ErrorKind::UnexpectedEof,
+			ERROR_TIMEOUT => io::ErrorKind::TimedOut,
+			_ => io::ErrorKind::Other,
+		};
+		io::Error::new(kind, err)
+	}
+}
 impl fmt::Display for ErrorCode {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "{:#X}", self.0)
@@ -56,3 +132,19 @@ impl error::Error for ErrorCode {
 }
 
 pub const ERROR_SUCCESS: ErrorCode = ErrorCode(0);
+pub const ERROR_FILE_NOT_FOUND: ErrorCode = ErrorCode(2);
+pub const ERROR_ACCESS_DENIED: ErrorCode = ErrorCode(5);
+pub const ERROR_INVALID_HANDLE: ErrorCode = ErrorCode(6);
+pub const ERROR_NOT_ENOUGH_MEMORY: ErrorCode = ErrorCode(8);
+pub const ERROR_INVALID_DATA: ErrorCode = ErrorCode(13);
+pub const ERROR_GEN_FAILURE: ErrorCode = ErrorCode(31);
+pub const ERROR_HANDLE_EOF: ErrorCode = ErrorCode(38);
+pub const ERROR_NOT_SUPPORTED: ErrorCode = ErrorCode(50);
+pub const ERROR_INVALID_PARAMETER: ErrorCode = ErrorCode(87);
+pub const ERROR_INSUFFICIENT_BUFFER: ErrorCode = ErrorCode(122);
+pub const ERROR_BUSY: ErrorCode = ErrorCode(170);
+pub const ERROR_PARTIAL_COPY: ErrorCode = ErrorCode(299);
+pub const ERROR_INVALID_ADDRESS: ErrorCode = ErrorCode(487);
+pub const ERROR_NOACCESS: ErrorCode = ErrorCode(998);
+pub const ERROR_CANCELLED: ErrorCode = ErrorCode(1223);
+pub const ERROR_TIMEOUT: ErrorCode = ErrorCode(1460);
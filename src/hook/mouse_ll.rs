@@ -11,6 +11,7 @@ use super::{Context, Invoke, Hook};
 //----------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MouseData {
 	Move,
 	ButtonDown(VirtualKey),
@@ -8,6 +8,7 @@ use crate::winapi::*;
 ///
 /// See [Virtual-Key Codes](https://msdn.microsoft.com/en-us/library/windows/desktop/dd375731.aspx) for more information.
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct VirtualKey(u8);
 impl From<DWORD> for VirtualKey {
 	fn from(vkey: DWORD) -> VirtualKey {
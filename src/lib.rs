@@ -2,7 +2,8 @@
 Externals.
 !*/
 
-#![cfg(windows)]
+// The derive macros refer to this crate by name.
+extern crate self as external;
 
 mod util;
 pub use self::util::*;
@@ -11,28 +12,45 @@ pub use self::util::*;
 mod inner;
 pub use self::inner::*;
 
+#[cfg(windows)]
 macro_rules! wide_str {
     ($($c:tt)+) => {
         [$($c as u16,)+]
     }
 }
 
+#[cfg(windows)]
 mod winapi;
+#[cfg(target_os = "linux")]
+mod procfs;
+#[cfg(any(windows, target_os = "linux"))]
+mod rights;
 
 pub type Result<T> = std::result::Result<T, error::ErrorCode>;
 
 pub mod ptr;
 pub mod error;
+#[cfg(any(windows, target_os = "linux"))]
 pub mod process;
 pub mod vm;
+pub mod scan;
+#[cfg(any(windows, target_os = "linux"))]
 pub mod module;
+#[cfg(any(windows, target_os = "linux"))]
 pub mod thread;
+#[cfg(windows)]
 pub mod window;
+#[cfg(windows)]
 pub mod wndclass;
+#[cfg(windows)]
 pub mod hook;
+#[cfg(windows)]
 pub mod input;
+#[cfg(windows)]
 pub mod control;
+#[cfg(windows)]
 pub mod snap;
+#[cfg(windows)]
 pub mod system;
 
 pub mod prelude;
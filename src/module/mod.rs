@@ -2,8 +2,16 @@
 Modules.
 !*/
 
+#[cfg(windows)]
 mod module_enum;
+#[cfg(target_os = "linux")]
+mod module_enum_linux;
+#[cfg(windows)]
 mod module_iter;
 
+#[cfg(windows)]
 pub use self::module_enum::*;
+#[cfg(target_os = "linux")]
+pub use self::module_enum_linux::*;
+#[cfg(windows)]
 pub use self::module_iter::*;
@@ -5,6 +5,8 @@ use crate::winapi::*;
 use crate::process::ProcessId;
 use crate::error::ErrorCode;
 use crate::util::from_wchar_buf;
+#[cfg(feature = "serde")]
+use crate::util::to_wchar_buf;
 use crate::{Result, IntoInner, FromInner};
 
 /// Module enumeration.
@@ -28,7 +30,7 @@ impl Iterator for EnumModules {
 	type Item = ModuleEntry;
 	fn next(&mut self) -> Option<ModuleEntry> {
 		unsafe {
-			let mut entry: ModuleEntry = mem::uninitialized();
+			let mut entry: ModuleEntry = mem::zeroed();
 			entry.0.dwSize = mem::size_of::<MODULEENTRY32W>() as DWORD;
 			let result = if self.1 {
 				Module32NextW(self.0, &mut entry.0)
@@ -106,3 +108,40 @@ impl fmt::Debug for ModuleEntry {
 			.finish()
 	}
 }
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "ModuleEntry")]
+struct ModuleEntryData {
+	process_id: ProcessId,
+	base: usize,
+	size: usize,
+	name: String,
+	exe_path: String,
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ModuleEntry {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		ModuleEntryData {
+			process_id: self.process_id(),
+			base: self.base(),
+			size: self.size(),
+			name: self.name().to_string_lossy().into_owned(),
+			exe_path: self.exe_path().to_string_lossy().into_owned(),
+		}.serialize(serializer)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ModuleEntry {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<ModuleEntry, D::Error> {
+		let data = ModuleEntryData::deserialize(deserializer)?;
+		let mut entry: MODULEENTRY32W = unsafe { mem::zeroed() };
+		entry.dwSize = mem::size_of::<MODULEENTRY32W>() as DWORD;
+		entry.th32ProcessID = data.process_id.into_inner();
+		entry.modBaseAddr = data.base as *mut BYTE;
+		entry.modBaseSize = data.size as DWORD;
+		entry.hModule = data.base as HMODULE;
+		to_wchar_buf(&data.name, &mut entry.szModule);
+		to_wchar_buf(&data.exe_path, &mut entry.szExePath);
+		Ok(ModuleEntry(entry))
+	}
+}
@@ -0,0 +1,159 @@
+use std::{fmt, vec};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+use crate::process::ProcessId;
+use crate::vm::ProcMaps;
+use crate::Result;
+
+/// Module enumeration.
+///
+/// Reads the file backed mappings from `/proc/<pid>/maps`, the segments of each ELF object are collapsed into a single module.
+#[derive(Debug)]
+pub struct EnumModules(vec::IntoIter<ModuleEntry>);
+impl EnumModules {
+	/// Creates an iterator over the modules in a process.
+	pub fn create(pid: ProcessId) -> Result<EnumModules> {
+		let maps = ProcMaps::read(pid)?;
+		Ok(EnumModules::from_maps(pid, &maps))
+	}
+	/// Creates an iterator over the modules in previously read maps.
+	///
+	/// Only files with an executable mapping and a mapping of the start of the file are considered modules, this excludes mapped data files.
+	pub fn from_maps(pid: ProcessId, maps: &ProcMaps) -> EnumModules {
+		struct Module<'a> {
+			base: usize,
+			end: usize,
+			path: &'a Path,
+			header: bool,
+			exec: bool,
+		}
+		let mut modules: Vec<Module> = Vec::new();
+		let mut index = HashMap::new();
+		for entry in maps.iter().filter(|entry| entry.is_file_backed()) {
+			let path = match &entry.path {
+				Some(path) => path,
+				None => continue,
+			};
+			let key = (entry.dev_major, entry.dev_minor, entry.inode);
+			let i = *index.entry(key).or_insert(modules.len());
+			if i == modules.len() {
+				modules.push(Module { base: entry.start, end: entry.end, path, header: false, exec: false });
+			}
+			let module = &mut modules[i];
+			module.base = module.base.min(entry.start);
+			module.end = module.end.max(entry.end);
+			module.header |= entry.offset == 0;
+			module.exec |= entry.exec;
+		}
+		let modules: Vec<_> = modules.into_iter()
+			.filter(|module| module.header && module.exec)
+			.map(|module| ModuleEntry {
+				pid,
+				base: module.base,
+				size: module.end - module.base,
+				path: module.path.to_owned(),
+			})
+			.collect();
+		EnumModules(modules.into_iter())
+	}
+}
+impl Iterator for EnumModules {
+	type Item = ModuleEntry;
+	fn next(&mut self) -> Option<ModuleEntry> {
+		self.0.next()
+	}
+}
+
+/// Module entry.
+///
+/// See [proc(5)](http://man7.org/linux/man-pages/man5/proc.5.html) for more information.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleEntry {
+	pid: ProcessId,
+	base: usize,
+	size: usize,
+	path: PathBuf,
+}
+impl ModuleEntry {
+	/// The identifier of the process whose modules are to be examined.
+	pub fn process_id(&self) -> ProcessId {
+		self.pid
+	}
+	/// The base address of the module in the context of the owning process.
+	pub fn base(&self) -> usize {
+		self.base
+	}
+	/// The size of the module, in bytes.
+	///
+	/// Spans from the lowest to the highest mapped segment, including any gaps between the segments.
+	pub fn size(&self) -> usize {
+		self.size
+	}
+	/// The module name.
+	pub fn name(&self) -> OsString {
+		self.path.file_name().unwrap_or(OsStr::new("")).to_owned()
+	}
+	/// The module path.
+	pub fn exe_path(&self) -> OsString {
+		self.path.clone().into_os_string()
+	}
+	/// The module path.
+	pub fn path(&self) -> &Path {
+		&self.path
+	}
+}
+impl fmt::Debug for ModuleEntry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ModuleEntry")
+			.field("process_id", &self.process_id())
+			.field("base", &format_args!("{:#x}", self.base()))
+			.field("size", &format_args!("{:#x}", self.size()))
+			.field("name", &self.name())
+			.field("path", &self.path())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::FromInner;
+	use super::*;
+
+	#[test]
+	fn from_maps() {
+		let maps = ProcMaps::parse("\
+55d0a0000000-55d0a0002000 r--p 00000000 08:01 100 /usr/bin/game
+55d0a0002000-55d0a0008000 r-xp 00002000 08:01 100 /usr/bin/game
+55d0a0008000-55d0a000a000 r--p 00008000 08:01 100 /usr/bin/game
+55d0a000b000-55d0a000c000 rw-p 0000a000 08:01 100 /usr/bin/game
+55d0a000c000-55d0a000d000 rw-p 00000000 00:00 0
+55d0a1000000-55d0a1021000 rw-p 00000000 00:00 0 [heap]
+7f0000000000-7f0000100000 r--p 00000000 08:01 200 /usr/lib/locale/locale-archive
+7f0000200000-7f0000228000 r--p 00000000 08:01 300 /usr/lib/libc.so.6
+7f0000228000-7f00003bd000 r-xp 00028000 08:01 300 /usr/lib/libc.so.6
+7f00003bd000-7f0000415000 r--p 001bd000 08:01 300 /usr/lib/libc.so.6
+7f0000415000-7f0000419000 rw-p 00214000 08:01 300 /usr/lib/libc.so.6
+").unwrap();
+		let pid = unsafe { ProcessId::from_inner(42) };
+		let modules: Vec<_> = EnumModules::from_maps(pid, &maps).collect();
+		assert_eq!(modules.len(), 2);
+		assert_eq!((modules[0].base(), modules[0].size()), (0x55d0a0000000, 0xc000));
+		assert_eq!(modules[0].name(), "game");
+		assert_eq!((modules[1].base(), modules[1].size()), (0x7f0000200000, 0x219000));
+		assert_eq!(modules[1].exe_path(), "/usr/lib/libc.so.6");
+		assert_eq!(modules[1].process_id(), pid);
+	}
+
+	#[test]
+	fn enum_self() {
+		let pid = unsafe { ProcessId::from_inner(std::process::id()) };
+		let exe = std::env::current_exe().unwrap();
+		let code = enum_self as *const () as usize;
+		let module = EnumModules::create(pid).unwrap().find(|module| module.path() == exe).unwrap();
+		assert!(code >= module.base() && code < module.base() + module.size());
+	}
+}
@@ -5,14 +5,24 @@ The prelude contains this library's items in a flat namespace.
 pub use super::Result;
 pub use super::ptr::*;
 pub use super::error::*;
+#[cfg(any(windows, target_os = "linux"))]
 pub use super::process::*;
 pub use super::vm::*;
+pub use super::scan::*;
+#[cfg(any(windows, target_os = "linux"))]
 pub use super::module::*;
+#[cfg(any(windows, target_os = "linux"))]
 pub use super::thread::*;
+#[cfg(windows)]
 pub use super::window::*;
+#[cfg(windows)]
 pub use super::wndclass::*;
+#[cfg(windows)]
 pub use super::hook::*;
+#[cfg(windows)]
 pub use super::input::*;
+#[cfg(windows)]
 pub use super::control::*;
+#[cfg(windows)]
 pub use super::system::*;
 pub use crate::{AsInner, AsInnerMut, FromInner, IntoInner};
@@ -4,12 +4,33 @@ Process handles.
 
 mod process_id;
 mod process_rights;
+#[cfg(windows)]
 mod process;
+#[cfg(target_os = "linux")]
+mod process_linux;
+#[cfg(windows)]
 mod process_enum;
+#[cfg(target_os = "linux")]
+mod process_enum_linux;
+#[cfg(windows)]
 mod process_list;
 
 pub use self::process_id::*;
 pub use self::process_rights::*;
+#[cfg(windows)]
 pub use self::process::*;
+#[cfg(target_os = "linux")]
+pub use self::process_linux::*;
+#[cfg(windows)]
 pub use self::process_enum::*;
+#[cfg(target_os = "linux")]
+pub use self::process_enum_linux::*;
+#[cfg(windows)]
 pub use self::process_list::*;
+
+/// The value returned by `Process::wait` when the process has exited.
+pub const WAIT_OBJECT_0: u32 = 0x00000000;
+/// The value returned by `Process::wait` when the timeout elapsed.
+pub const WAIT_TIMEOUT: u32 = 0x00000102;
+/// Timeout for `Process::wait` to wait indefinitely.
+pub const INFINITE: u32 = 0xFFFFFFFF;
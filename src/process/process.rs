@@ -1,10 +1,12 @@
 use std::{mem, ptr};
+use std::mem::MaybeUninit;
 use std::ffi::OsString;
 use std::os::windows::ffi::{OsStringExt};
 use crate::winapi::*;
 use crate::process::{ProcessId, ProcessRights};
 use crate::thread::Thread;
 use crate::error::ErrorCode;
+use crate::ptr::TargetArch;
 use crate::{Result, IntoInner, FromInner};
 
 /// Process handle.
@@ -40,8 +42,9 @@ impl Process {
 	/// Get the exit code for the process, `None` if the process is still running.
 	pub fn exit_code(&self) -> Result<Option<DWORD>> {
 		unsafe {
-			let mut code: DWORD = mem::uninitialized();
-			if GetExitCodeProcess(self.0, &mut code) != FALSE {
+			let mut code = MaybeUninit::<DWORD>::uninit();
+			if GetExitCodeProcess(self.0, code.as_mut_ptr()) != FALSE {
+				let code = code.assume_init();
 				Ok(if code == 259/*STILL_ACTIVE*/ { None } else { Some(code) })
 			}
 			else {
@@ -87,10 +90,18 @@ impl Process {
 	}
 	/// Get the full name of the executable for this process.
 	pub fn full_image_name(&self) -> Result<OsString> {
-		let mut buffer: [WCHAR; 0x400] = unsafe { mem::uninitialized() };
+		let mut buffer: [WCHAR; 0x400] = [0; 0x400];
 		self.full_image_name_wide(&mut buffer)
 			.map(|path| OsString::from_wide(path))
 	}
+	/// Get the architecture of the process, 32bit processes running under WOW64 are `X86`.
+	pub fn target_arch(&self) -> Result<TargetArch> {
+		let mut wow64 = FALSE;
+		if unsafe { IsWow64Process(self.0, &mut wow64) } == FALSE {
+			return Err(ErrorCode::last());
+		}
+		Ok(if wow64 != FALSE { TargetArch::X86 } else { TargetArch::HOST })
+	}
 	pub fn get_mapped_file_name_wide<'a>(&self, address: usize, buffer: &'a mut [u16]) -> Result<&'a mut [u16]> {
 		unsafe {
 			let size = GetMappedFileNameW(self.0, address as LPVOID, buffer.as_mut_ptr(), buffer.len() as DWORD);
@@ -107,12 +118,12 @@ impl Clone for Process {
 	fn clone(&self) -> Process {
 		Process(unsafe {
 			let current = GetCurrentProcess();
-			let mut new: HANDLE = mem::uninitialized();
+			let mut new = MaybeUninit::<HANDLE>::uninit();
 			// What about all these options? inherit handles?
-			let result = DuplicateHandle(current, self.0, current, &mut new, 0, FALSE, DUPLICATE_SAME_ACCESS);
+			let result = DuplicateHandle(current, self.0, current, new.as_mut_ptr(), 0, FALSE, DUPLICATE_SAME_ACCESS);
 			// Can't report error, should this ever fail?
 			assert!(result != FALSE, "duplicate handle error: {}", ErrorCode::last());
-			new
+			new.assume_init()
 		})
 	}
 }
@@ -121,3 +132,6 @@ impl Drop for Process {
 		unsafe { CloseHandle(self.0); }
 	}
 }
+// Process handles can be used from any thread.
+unsafe impl Send for Process {}
+unsafe impl Sync for Process {}
@@ -3,6 +3,8 @@ use std::ffi::{OsString};
 use std::os::windows::ffi::{OsStringExt};
 use crate::winapi::*;
 use crate::util::from_wchar_buf;
+#[cfg(feature = "serde")]
+use crate::util::to_wchar_buf;
 use crate::error::ErrorCode;
 use crate::Result;
 use super::ProcessId;
@@ -33,7 +35,7 @@ impl Iterator for EnumProcess {
 	type Item = ProcessEntry;
 	fn next(&mut self) -> Option<ProcessEntry> {
 		unsafe {
-			let mut entry: ProcessEntry = mem::uninitialized();
+			let mut entry: ProcessEntry = mem::zeroed();
 			entry.0.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
 			let result = if self.next {
 				Process32NextW(self.handle, &mut entry.0)
@@ -107,3 +109,39 @@ impl fmt::Debug for ProcessEntry {
 			.finish()
 	}
 }
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "ProcessEntry")]
+struct ProcessEntryData {
+	process_id: ProcessId,
+	parent_id: ProcessId,
+	thread_count: u32,
+	thread_base_priority: i32,
+	exe_file: String,
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ProcessEntry {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		ProcessEntryData {
+			process_id: self.process_id(),
+			parent_id: self.parent_id(),
+			thread_count: self.thread_count(),
+			thread_base_priority: self.thread_base_priority(),
+			exe_file: self.exe_file().to_string_lossy().into_owned(),
+		}.serialize(serializer)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ProcessEntry {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<ProcessEntry, D::Error> {
+		let data = ProcessEntryData::deserialize(deserializer)?;
+		let mut entry: PROCESSENTRY32W = unsafe { mem::zeroed() };
+		entry.dwSize = mem::size_of::<PROCESSENTRY32W>() as DWORD;
+		entry.th32ProcessID = data.process_id.0;
+		entry.th32ParentProcessID = data.parent_id.0;
+		entry.cntThreads = data.thread_count;
+		entry.pcPriClassBase = data.thread_base_priority;
+		to_wchar_buf(&data.exe_file, &mut entry.szExeFile);
+		Ok(ProcessEntry(entry))
+	}
+}
@@ -0,0 +1,149 @@
+use std::{fmt, fs};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use crate::{procfs, Result};
+use super::ProcessId;
+
+//----------------------------------------------------------------
+
+/// Process enumeration.
+///
+/// Walks the numbered directories of `/proc`, processes which exit while enumerating are skipped.
+#[derive(Debug)]
+pub struct EnumProcess(fs::ReadDir);
+impl EnumProcess {
+	/// Iterate over the running processes.
+	pub fn create() -> Result<EnumProcess> {
+		Ok(EnumProcess(fs::read_dir("/proc")?))
+	}
+}
+impl Iterator for EnumProcess {
+	type Item = ProcessEntry;
+	fn next(&mut self) -> Option<ProcessEntry> {
+		for entry in &mut self.0 {
+			let pid = match entry.ok().and_then(|entry| entry.file_name().to_str()?.parse().ok()) {
+				Some(pid) => ProcessId(pid),
+				None => continue,
+			};
+			if let Some(entry) = ProcessEntry::read(pid) {
+				return Some(entry);
+			}
+		}
+		None
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Process entry.
+///
+/// See [proc(5)](http://man7.org/linux/man-pages/man5/proc.5.html) for more information.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessEntry {
+	pid: ProcessId,
+	parent_id: ProcessId,
+	state: char,
+	thread_count: u32,
+	priority: i32,
+	#[cfg_attr(feature = "serde", serde(with = "crate::util::serde_os_string"))]
+	comm: OsString,
+	exe_path: Option<PathBuf>,
+}
+impl ProcessEntry {
+	fn read(pid: ProcessId) -> Option<ProcessEntry> {
+		let stat = procfs::read(format!("/proc/{}/stat", pid)).ok()?;
+		let mut entry = ProcessEntry::parse(pid, &stat)?;
+		// Fails for kernel threads and processes of other users
+		entry.exe_path = fs::read_link(format!("/proc/{}/exe", pid)).ok();
+		Some(entry)
+	}
+	fn parse(pid: ProcessId, stat: &str) -> Option<ProcessEntry> {
+		let (comm, fields) = procfs::stat_fields(stat)?;
+		Some(ProcessEntry {
+			pid,
+			state: fields.first()?.chars().next()?,
+			parent_id: ProcessId(fields.get(1)?.parse().ok()?),
+			priority: fields.get(15)?.parse().ok()?,
+			thread_count: fields.get(17)?.parse().ok()?,
+			comm: OsString::from(comm),
+			exe_path: None,
+		})
+	}
+	/// The process identifier.
+	pub fn process_id(&self) -> ProcessId {
+		self.pid
+	}
+	/// The identifier of the process that created this process (its parent process).
+	pub fn parent_id(&self) -> ProcessId {
+		self.parent_id
+	}
+	/// The number of execution threads started by the process.
+	pub fn thread_count(&self) -> u32 {
+		self.thread_count
+	}
+	/// The scheduling priority of the process.
+	///
+	/// This is the kernel's priority value, 20 plus the nice value for normal processes and negative for real-time processes.
+	pub fn thread_base_priority(&self) -> i32 {
+		self.priority
+	}
+	/// The process state, eg. `R` running, `S` sleeping or `Z` zombie.
+	pub fn state(&self) -> char {
+		self.state
+	}
+	/// The name of the executable file for the process.
+	///
+	/// The file name of the executable if it can be read, otherwise the command name which is truncated to 15 bytes.
+	pub fn exe_file(&self) -> OsString {
+		self.exe_path.as_deref()
+			.and_then(Path::file_name)
+			.map_or_else(|| self.comm.clone(), |name| name.to_owned())
+	}
+	/// The full path of the executable file for the process.
+	///
+	/// Not available for kernel threads and processes owned by other users.
+	pub fn exe_path(&self) -> Option<&Path> {
+		self.exe_path.as_deref()
+	}
+}
+impl fmt::Debug for ProcessEntry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProcessEntry")
+			.field("process_id", &self.process_id())
+			.field("parent_id", &self.parent_id())
+			.field("state", &self.state())
+			.field("thread_count", &self.thread_count())
+			.field("thread_base_priority", &self.thread_base_priority())
+			.field("exe_file", &self.exe_file())
+			.field("exe_path", &self.exe_path())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let stat = "4242 (my (game)) S 1 4242 4242 0 -1 4194560 100 0 0 0 5 3 0 0 20 0 7 0 123 4096 10 18446744073709551615";
+		let entry = ProcessEntry::parse(ProcessId(4242), stat).unwrap();
+		assert_eq!(entry.parent_id(), ProcessId(1));
+		assert_eq!(entry.state(), 'S');
+		assert_eq!(entry.thread_base_priority(), 20);
+		assert_eq!(entry.thread_count(), 7);
+		assert_eq!(entry.exe_file(), "my (game)");
+	}
+
+	#[test]
+	fn enum_self() {
+		let pid = ProcessId(std::process::id());
+		let entry = EnumProcess::create().unwrap().find(|entry| entry.process_id() == pid).unwrap();
+		assert_eq!(entry.parent_id(), ProcessId(unsafe { libc::getppid() } as u32));
+		assert!(entry.thread_count() >= 1);
+		assert_eq!(Some(entry.exe_file().as_os_str()), std::env::current_exe().unwrap().file_name());
+	}
+}
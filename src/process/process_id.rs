@@ -1,10 +1,10 @@
 use std::fmt;
-use crate::winapi::*;
 
 /// Wraps a process identifier.
 #[derive(Copy, Clone, Eq, PartialEq)]
-pub struct ProcessId(pub(super) DWORD);
-impl_inner!(ProcessId: DWORD);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessId(pub(super) u32);
+impl_inner!(ProcessId: u32);
 
 // Custom Debug and Display implementation to disable pretty formatting
 impl fmt::Debug for ProcessId {
@@ -0,0 +1,199 @@
+use std::{cmp, fs, io, mem};
+use std::io::Read;
+use std::fs::{File, OpenOptions};
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::time::{Duration, Instant};
+use crate::process::{ProcessId, ProcessRights, WAIT_OBJECT_0, WAIT_TIMEOUT, INFINITE};
+use crate::error::{ErrorCode, ERROR_INVALID_DATA};
+use crate::ptr::TargetArch;
+use crate::{procfs, Result};
+
+/// Process handle.
+///
+/// Refers to the process by a pidfd where the kernel supports it (Linux 5.3), this protects against the pid being reused after the process exits.
+/// The memory of the process is accessed with `process_vm_readv` and `process_vm_writev`, falling back to `/proc/<pid>/mem`.
+#[derive(Debug)]
+pub struct Process {
+	pub(crate) pid: ProcessId,
+	pidfd: Option<File>,
+	pub(crate) mem: Option<File>,
+}
+impl Process {
+	/// Get the current process.
+	pub fn current() -> Process {
+		let pid = ProcessId(unsafe { libc::getpid() } as u32);
+		Process {
+			pid,
+			pidfd: pidfd_open(pid).ok(),
+			mem: open_mem(pid, true).ok(),
+		}
+	}
+	/// Attach to a process by id and given rights.
+	///
+	/// Fails if the process does not exist or if its memory cannot be opened with the requested `vm_read` or `vm_write` rights.
+	pub fn attach(pid: ProcessId, rights: ProcessRights) -> Result<Process> {
+		let pidfd = match pidfd_open(pid) {
+			Ok(pidfd) => Some(pidfd),
+			// Kernels before 5.3 lack pidfds, check the process exists instead
+			Err(err) if err.raw_os_error() == Some(libc::ENOSYS) => {
+				if unsafe { libc::kill(pid.0 as libc::pid_t, 0) } != 0 {
+					let err = io::Error::last_os_error();
+					if err.raw_os_error() != Some(libc::EPERM) {
+						return Err(err.into());
+					}
+				}
+				None
+			},
+			Err(err) => return Err(err.into()),
+		};
+		let write = rights.contains(ProcessRights::new().vm_write());
+		let read = rights.contains(ProcessRights::new().vm_read());
+		let mem = if write {
+			// Writing may be forbidden even if reading is allowed, `process_vm_writev` may still succeed
+			Some(open_mem(pid, true).or_else(|_| open_mem(pid, false))?)
+		}
+		else if read {
+			Some(open_mem(pid, false)?)
+		}
+		else {
+			None
+		};
+		Ok(Process { pid, pidfd, mem })
+	}
+	/// Get the id for this process.
+	pub fn pid(&self) -> Result<ProcessId> {
+		Ok(self.pid)
+	}
+	/// Get the exit code for the process, `None` if the process is still running.
+	///
+	/// The exit code can only be retrieved for children of the current process and for processes which have not yet been reaped by their parent.
+	/// Processes killed by a signal report `0x80` plus the signal number.
+	pub fn exit_code(&self) -> Result<Option<u32>> {
+		if !self.has_exited()? {
+			return Ok(None);
+		}
+		// Children report their status without being reaped
+		unsafe {
+			let mut info: libc::siginfo_t = mem::zeroed();
+			let options = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+			if libc::waitid(libc::P_PID, self.pid.0 as libc::id_t, &mut info, options) == 0 && info.si_pid() != 0 {
+				let status = info.si_status() as u32;
+				return Ok(Some(if info.si_code == libc::CLD_EXITED { status } else { 0x80 + status }));
+			}
+		}
+		// Zombies report their wait status in the stat file
+		let stat = procfs::read(format!("/proc/{}/stat", self.pid))?;
+		let status: u32 = procfs::stat_fields(&stat)
+			.and_then(|(_, fields)| fields.get(49)?.parse().ok())
+			.ok_or(ERROR_INVALID_DATA)?;
+		Ok(Some(if status & 0x7f == 0 { (status >> 8) & 0xff } else { 0x80 + (status & 0x7f) }))
+	}
+	/// Wait for the process to finish.
+	///
+	/// Returns `WAIT_OBJECT_0` if the process finished or `WAIT_TIMEOUT` if the timeout elapsed, pass `INFINITE` to wait indefinitely.
+	pub fn wait(&self, milis: u32) -> Result<u32> {
+		let deadline = if milis == INFINITE { None } else { Some(Instant::now() + Duration::from_millis(milis as u64)) };
+		let pidfd = match &self.pidfd {
+			Some(pidfd) => pidfd,
+			None => {
+				let exited = procfs::poll_until(deadline, || self.has_exited())?;
+				return Ok(if exited { WAIT_OBJECT_0 } else { WAIT_TIMEOUT });
+			},
+		};
+		loop {
+			let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+			let mut pollfd = libc::pollfd { fd: pidfd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+			let timeout = remaining.map_or(-1, |remaining| cmp::min(remaining.as_millis(), i32::MAX as u128) as i32);
+			match unsafe { libc::poll(&mut pollfd, 1, timeout) } {
+				0 => return Ok(WAIT_TIMEOUT),
+				-1 => {
+					let err = io::Error::last_os_error();
+					if err.kind() != io::ErrorKind::Interrupted {
+						return Err(err.into());
+					}
+				},
+				_ => return Ok(WAIT_OBJECT_0),
+			}
+		}
+	}
+	/// Get the architecture of the process from the ELF class of its executable.
+	pub fn target_arch(&self) -> Result<TargetArch> {
+		let mut ident = [0u8; 5];
+		File::open(format!("/proc/{}/exe", self.pid))?.read_exact(&mut ident)?;
+		match ident {
+			[0x7F, b'E', b'L', b'F', 1] => Ok(TargetArch::X86),
+			[0x7F, b'E', b'L', b'F', 2] => Ok(TargetArch::X64),
+			_ => Err(ERROR_INVALID_DATA),
+		}
+	}
+	fn has_exited(&self) -> Result<bool> {
+		match &self.pidfd {
+			Some(pidfd) => {
+				let mut pollfd = libc::pollfd { fd: pidfd.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+				match unsafe { libc::poll(&mut pollfd, 1, 0) } {
+					-1 => Err(ErrorCode::last()),
+					ready => Ok(ready != 0),
+				}
+			},
+			None => match fs::read_to_string(format!("/proc/{}/stat", self.pid)) {
+				Ok(stat) => Ok(procfs::stat_fields(&stat).is_none_or(|(_, fields)| matches!(fields.first(), Some(&"Z") | Some(&"X")))),
+				Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(true),
+				Err(err) => Err(err.into()),
+			},
+		}
+	}
+}
+impl Clone for Process {
+	fn clone(&self) -> Process {
+		// Can't report error, should this ever fail?
+		let try_clone = |file: &Option<File>| file.as_ref().map(|file| file.try_clone().expect("duplicate handle error"));
+		Process {
+			pid: self.pid,
+			pidfd: try_clone(&self.pidfd),
+			mem: try_clone(&self.mem),
+		}
+	}
+}
+
+fn pidfd_open(pid: ProcessId) -> io::Result<File> {
+	let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid.0 as libc::pid_t, 0) };
+	if fd < 0 {
+		Err(io::Error::last_os_error())
+	}
+	else {
+		Ok(unsafe { File::from_raw_fd(fd as i32) })
+	}
+}
+
+fn open_mem(pid: ProcessId, write: bool) -> Result<File> {
+	Ok(OpenOptions::new().read(true).write(write).open(format!("/proc/{}/mem", pid))?)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::process::Command;
+	use super::*;
+
+	#[test]
+	fn wait_exit_code() {
+		let mut child = Command::new("sh").args(["-c", "sleep 0.2; exit 3"]).spawn().unwrap();
+		let process = Process::attach(ProcessId(child.id()), ProcessRights::new().synchronize()).unwrap();
+		assert_eq!(process.exit_code(), Ok(None));
+		assert_eq!(process.wait(0), Ok(WAIT_TIMEOUT));
+		assert_eq!(process.wait(INFINITE), Ok(WAIT_OBJECT_0));
+		assert_eq!(process.exit_code(), Ok(Some(3)));
+		assert_eq!(child.wait().unwrap().code(), Some(3));
+	}
+
+	#[test]
+	fn target_arch() {
+		assert_eq!(Process::current().target_arch(), Ok(TargetArch::HOST));
+	}
+
+	#[test]
+	fn attach_missing() {
+		assert!(Process::attach(ProcessId(u32::MAX >> 2), ProcessRights::new()).is_err());
+	}
+}
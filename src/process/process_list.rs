@@ -141,6 +141,58 @@ impl fmt::Debug for ThreadInformation {
 	}
 }
 
+/// Owned snapshot of a [`ProcessInformation`](struct.ProcessInformation.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedProcessInformation {
+	pub process_id: ProcessId,
+	pub image_name: String,
+	pub threads: Vec<OwnedThreadInformation>,
+}
+impl<'a> From<&'a ProcessInformation> for OwnedProcessInformation {
+	fn from(pi: &'a ProcessInformation) -> OwnedProcessInformation {
+		OwnedProcessInformation {
+			process_id: pi.process_id(),
+			image_name: pi.image_name().to_string_lossy().into_owned(),
+			threads: pi.threads().iter().map(OwnedThreadInformation::from).collect(),
+		}
+	}
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ProcessInformation {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		OwnedProcessInformation::from(self).serialize(serializer)
+	}
+}
+
+/// Owned snapshot of a [`ThreadInformation`](struct.ThreadInformation.html).
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedThreadInformation {
+	pub thread_id: ThreadId,
+	pub process_id: ProcessId,
+	pub start_address: usize,
+	pub thread_state: u32,
+	pub wait_reason: u32,
+}
+impl<'a> From<&'a ThreadInformation> for OwnedThreadInformation {
+	fn from(ti: &'a ThreadInformation) -> OwnedThreadInformation {
+		OwnedThreadInformation {
+			thread_id: ti.thread_id(),
+			process_id: ti.process_id(),
+			start_address: ti.start_address(),
+			thread_state: ti.thread_state(),
+			wait_reason: ti.wait_reason(),
+		}
+	}
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ThreadInformation {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		OwnedThreadInformation::from(self).serialize(serializer)
+	}
+}
+
 //----------------------------------------------------------------
 
 #[cfg(test)]
@@ -1,16 +1,43 @@
-use crate::winapi::*;
+use crate::rights::*;
+
+// Process specific rights of the Windows SDK.
+const PROCESS_TERMINATE: u32 = 0x0001;
+const PROCESS_CREATE_THREAD: u32 = 0x0002;
+const PROCESS_VM_OPERATION: u32 = 0x0008;
+const PROCESS_VM_READ: u32 = 0x0010;
+const PROCESS_VM_WRITE: u32 = 0x0020;
+const PROCESS_DUP_HANDLE: u32 = 0x0040;
+const PROCESS_CREATE_PROCESS: u32 = 0x0080;
+const PROCESS_SET_QUOTA: u32 = 0x0100;
+const PROCESS_SET_INFORMATION: u32 = 0x0200;
+const PROCESS_QUERY_INFORMATION: u32 = 0x0400;
+const PROCESS_SUSPEND_RESUME: u32 = 0x0800;
+const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+const PROCESS_ALL_ACCESS: u32 = 0x001FFFFF;
 
 /// Create process access rights using the builder pattern.
 ///
 /// See [Process Security and Access Rights](https://msdn.microsoft.com/en-us/library/windows/desktop/ms684880.aspx) for more information.
-pub struct ProcessRights(DWORD);
-impl_inner!(ProcessRights: DWORD);
+///
+/// On Linux only the `vm_read` and `vm_write` rights are meaningful, they select how the process memory is opened.
+pub struct ProcessRights(u32);
+impl_inner!(ProcessRights: u32);
+impl Default for ProcessRights {
+	fn default() -> ProcessRights {
+		ProcessRights::new()
+	}
+}
 impl ProcessRights {
 	pub fn new() -> ProcessRights {
 		ProcessRights(0)
 	}
 	pub const ALL_ACCESS: ProcessRights = ProcessRights(PROCESS_ALL_ACCESS);
 
+	/// Returns if all the rights in `other` are requested.
+	pub fn contains(&self, other: ProcessRights) -> bool {
+		self.0 & other.0 == other.0
+	}
+
 	pub fn delete(self) -> ProcessRights {
 		ProcessRights(self.0 | DELETE)
 	}
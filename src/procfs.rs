@@ -0,0 +1,90 @@
+/*!
+Helpers for the Linux `/proc` filesystem.
+!*/
+
+use std::{cmp, fs, thread, path::Path};
+use std::time::{Duration, Instant};
+use crate::Result;
+
+/// Reads a `/proc` file to a string.
+pub fn read<P: AsRef<Path>>(path: P) -> Result<String> {
+	Ok(fs::read_to_string(path)?)
+}
+
+/// Splits the contents of a `stat` file into the command name and the remaining fields.
+///
+/// The command name is enclosed in parentheses and may itself contain spaces and parentheses.
+/// The first remaining field is the state, which is field 3 in the `proc(5)` numbering.
+pub fn stat_fields(stat: &str) -> Option<(&str, Vec<&str>)> {
+	let open = stat.find('(')?;
+	let close = stat.rfind(')')?;
+	if close < open {
+		return None;
+	}
+	let comm = &stat[open + 1..close];
+	let fields = stat[close + 1..].split_whitespace().collect();
+	Some((comm, fields))
+}
+
+/// Finds the value of a `Key:` line in a `status` file.
+pub fn status_field<'a>(status: &'a str, key: &str) -> Option<&'a str> {
+	status.lines()
+		.find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+		.map(str::trim)
+}
+
+/// Converts a time in clock ticks to a duration.
+pub fn clock_ticks(ticks: u64) -> Duration {
+	let hz = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+	let hz = if hz > 0 { hz as u64 } else { 100 };
+	Duration::from_secs(ticks / hz) + Duration::from_nanos(ticks % hz * 1_000_000_000 / hz)
+}
+
+/// Polls until the process or thread has exited, returns false if the deadline passes first.
+///
+/// Waits indefinitely without a deadline. Used where there is no handle to wait on.
+pub fn poll_until<F: FnMut() -> Result<bool>>(deadline: Option<Instant>, mut has_exited: F) -> Result<bool> {
+	loop {
+		if has_exited()? {
+			return Ok(true);
+		}
+		let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+		if remaining == Some(Duration::from_millis(0)) {
+			return Ok(false);
+		}
+		thread::sleep(remaining.map_or(POLL_INTERVAL, |remaining| cmp::min(remaining, POLL_INTERVAL)));
+	}
+}
+
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn stat() {
+		let (comm, fields) = stat_fields("1234 (a (b) c) S 1 1234 1234 0 -1").unwrap();
+		assert_eq!(comm, "a (b) c");
+		assert_eq!(fields, ["S", "1", "1234", "1234", "0", "-1"]);
+		assert_eq!(stat_fields("garbage"), None);
+	}
+
+	#[test]
+	fn status() {
+		let status = "Name:\tcat\nTgid:\t1234\nPid:\t1235\nPPid:\t1\n";
+		assert_eq!(status_field(status, "Tgid"), Some("1234"));
+		assert_eq!(status_field(status, "Pid"), Some("1235"));
+		assert_eq!(status_field(status, "Uid"), None);
+	}
+
+	#[test]
+	fn poll() {
+		let mut polls = 0;
+		assert_eq!(poll_until(None, || { polls += 1; Ok(polls == 3) }), Ok(true));
+		assert_eq!(poll_until(Some(Instant::now() + Duration::from_millis(20)), || Ok(false)), Ok(false));
+		assert_eq!(poll_until(Some(Instant::now()), || Ok(true)), Ok(true));
+	}
+}
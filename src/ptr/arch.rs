@@ -0,0 +1,139 @@
+use std::{fmt, hash};
+use super::{Pod, Ptr32, Ptr64};
+
+/// Pointer types accepted by the memory API.
+///
+/// Implemented by both [`Ptr32`](struct.Ptr32.html) and [`Ptr64`](struct.Ptr64.html) so either can be used regardless of the host pointer width.
+pub trait RemotePtr: Copy {
+	/// The type pointed to.
+	type Target: ?Sized;
+	/// Constructs a pointer from an address, truncating it to the pointer width.
+	fn from_address(address: u64) -> Self;
+	/// Converts to the address pointed to.
+	fn into_address(self) -> u64;
+}
+impl<T: ?Sized> RemotePtr for Ptr32<T> {
+	type Target = T;
+	#[inline]
+	fn from_address(address: u64) -> Ptr32<T> {
+		Ptr32::from(address as u32)
+	}
+	#[inline]
+	fn into_address(self) -> u64 {
+		self.into_raw() as u64
+	}
+}
+impl<T: ?Sized> RemotePtr for Ptr64<T> {
+	type Target = T;
+	#[inline]
+	fn from_address(address: u64) -> Ptr64<T> {
+		Ptr64::from(address)
+	}
+	#[inline]
+	fn into_address(self) -> u64 {
+		self.into_raw()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Target architecture selected at runtime.
+///
+/// Query it once after attaching to a process and dispatch to code generic over [`Arch`](trait.Arch.html).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TargetArch {
+	/// 32bit pointers.
+	X86,
+	/// 64bit pointers.
+	X64,
+}
+impl TargetArch {
+	/// The architecture of the current process.
+	#[cfg(target_pointer_width = "64")]
+	pub const HOST: TargetArch = TargetArch::X64;
+	/// The architecture of the current process.
+	#[cfg(target_pointer_width = "32")]
+	pub const HOST: TargetArch = TargetArch::X86;
+
+	/// The size of a pointer, in bytes.
+	pub fn pointer_size(self) -> usize {
+		match self {
+			TargetArch::X86 => 4,
+			TargetArch::X64 => 8,
+		}
+	}
+}
+
+/// Target architecture selected at compile time.
+///
+/// Structures containing pointers are written once generic over the architecture and instantiated for either pointer width.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::{Arch, Pod, X86, X64};
+///
+/// #[derive(Copy, Clone, Pod)]
+/// #[repr(C)]
+/// struct Entity<A: Arch> {
+///     next: A::Ptr<Entity<A>>,
+///     name: A::Ptr<[u8]>,
+/// }
+///
+/// assert_eq!(std::mem::size_of::<Entity<X86>>(), 8);
+/// assert_eq!(std::mem::size_of::<Entity<X64>>(), 16);
+/// ```
+pub trait Arch: 'static + Copy + Default + Eq + hash::Hash + fmt::Debug {
+	/// The runtime value of this architecture.
+	const TARGET: TargetArch;
+	/// Pointer of this architecture.
+	type Ptr<T: ?Sized>: RemotePtr<Target = T> + Pod + Default + Eq + Ord + hash::Hash + fmt::Debug + fmt::Display;
+	/// Unsigned integer with the size of a pointer.
+	type Usize: Pod + Copy + Default + Eq + Ord + hash::Hash + fmt::Debug + Into<u64>;
+}
+
+/// 32bit architecture.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct X86;
+impl Arch for X86 {
+	const TARGET: TargetArch = TargetArch::X86;
+	type Ptr<T: ?Sized> = Ptr32<T>;
+	type Usize = u32;
+}
+
+/// 64bit architecture.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct X64;
+impl Arch for X64 {
+	const TARGET: TargetArch = TargetArch::X64;
+	type Ptr<T: ?Sized> = Ptr64<T>;
+	type Usize = u64;
+}
+
+/// The architecture of the current process.
+#[cfg(target_pointer_width = "64")]
+pub type HostArch = X64;
+/// The architecture of the current process.
+#[cfg(target_pointer_width = "32")]
+pub type HostArch = X86;
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn roundtrip<P: RemotePtr>(address: u64) -> u64 {
+		P::from_address(address).into_address()
+	}
+
+	#[test]
+	fn remote_ptr() {
+		assert_eq!(roundtrip::<Ptr32<u8>>(0x1_2345_6789), 0x2345_6789);
+		assert_eq!(roundtrip::<Ptr64<u8>>(0x1_2345_6789), 0x1_2345_6789);
+		assert_eq!(<X86 as Arch>::TARGET.pointer_size(), std::mem::size_of::<<X86 as Arch>::Ptr<u8>>());
+		assert_eq!(<X64 as Arch>::TARGET.pointer_size(), std::mem::size_of::<<X64 as Arch>::Ptr<u8>>());
+		assert_eq!(<HostArch as Arch>::TARGET, TargetArch::HOST);
+	}
+}
@@ -0,0 +1,134 @@
+use std::{mem, ptr};
+use std::num::{NonZeroI8, NonZeroI16, NonZeroI32, NonZeroI64, NonZeroU8, NonZeroU16, NonZeroU32, NonZeroU64};
+use super::Pod;
+
+/// Types which can be read from another process after validating the bytes.
+///
+/// Not every bit pattern is a valid `bool`, enum discriminant or `NonZero` integer.
+/// Reading such types with [`vm_read`](../vm/trait.VirtualMemory.html#method.vm_read) checks the bytes and fails with `ERROR_INVALID_DATA` instead of producing an invalid value.
+///
+/// Every `Pod` type is valid for any bit pattern and implements this trait.
+///
+/// # Deriving
+///
+/// `#[derive(FromRemoteBytes)]` is available for `#[repr(C)]` and `#[repr(transparent)]` structs, validating each field in turn,
+/// and for enums without fields with an integer `repr`, validating the discriminant.
+///
+/// ```
+/// use std::num::NonZeroU32;
+/// use external::ptr::FromRemoteBytes;
+///
+/// #[derive(Copy, Clone, Debug, Eq, PartialEq, FromRemoteBytes)]
+/// #[repr(u8)]
+/// enum State {
+///     Alive = 1,
+///     Dead = 2,
+/// }
+///
+/// #[derive(Copy, Clone, Debug, FromRemoteBytes)]
+/// #[repr(C)]
+/// struct Player {
+///     id: NonZeroU32,
+///     state: State,
+///     flags: [bool; 3],
+/// }
+///
+/// let player = Player::from_remote_bytes(&[1, 0, 0, 0, 2, 1, 0, 1]).unwrap();
+/// assert_eq!(player.state, State::Dead);
+/// assert!(Player::from_remote_bytes(&[0, 0, 0, 0, 2, 1, 0, 1]).is_none());
+/// assert!(Player::from_remote_bytes(&[1, 0, 0, 0, 3, 1, 0, 1]).is_none());
+/// assert!(Player::from_remote_bytes(&[1, 0, 0, 0, 2, 1, 0, 2]).is_none());
+/// ```
+///
+/// Enums without an integer `repr` are rejected as the size of the discriminant is not defined:
+///
+/// ```compile_fail
+/// #[derive(external::ptr::FromRemoteBytes)]
+/// enum State {
+///     Alive,
+///     Dead,
+/// }
+/// ```
+///
+/// # Safety
+///
+/// Implementors must only accept bytes which are a valid instance of the type.
+pub unsafe trait FromRemoteBytes: Sized {
+	/// Returns if the bytes are a valid instance of the type.
+	///
+	/// The length of the bytes is the size of the type, they are not necessarily aligned.
+	fn validate(bytes: &[u8]) -> bool;
+
+	/// Copies the bytes into a value if they are valid.
+	fn from_remote_bytes(bytes: &[u8]) -> Option<Self> {
+		if bytes.len() == mem::size_of::<Self>() && Self::validate(bytes) {
+			Some(unsafe { ptr::read_unaligned(bytes.as_ptr() as *const Self) })
+		}
+		else {
+			None
+		}
+	}
+}
+
+unsafe impl<T: Pod> FromRemoteBytes for T {
+	#[inline]
+	fn validate(_bytes: &[u8]) -> bool {
+		true
+	}
+}
+
+unsafe impl FromRemoteBytes for bool {
+	#[inline]
+	fn validate(bytes: &[u8]) -> bool {
+		bytes[0] <= 1
+	}
+}
+unsafe impl FromRemoteBytes for char {
+	#[inline]
+	fn validate(bytes: &[u8]) -> bool {
+		let mut value = [0u8; 4];
+		value.copy_from_slice(bytes);
+		char::from_u32(u32::from_ne_bytes(value)).is_some()
+	}
+}
+
+macro_rules! impl_non_zero {
+	($($ty:ty)*) => {$(
+		unsafe impl FromRemoteBytes for $ty {
+			#[inline]
+			fn validate(bytes: &[u8]) -> bool {
+				bytes.iter().any(|&byte| byte != 0)
+			}
+		}
+		// The null pointer optimization makes any bit pattern valid.
+		unsafe impl FromRemoteBytes for Option<$ty> {
+			#[inline]
+			fn validate(_bytes: &[u8]) -> bool {
+				true
+			}
+		}
+	)*};
+}
+impl_non_zero!(NonZeroU8 NonZeroU16 NonZeroU32 NonZeroU64 NonZeroI8 NonZeroI16 NonZeroI32 NonZeroI64);
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::num::NonZeroU16;
+	use crate::ptr::Ptr32;
+	use super::*;
+
+	#[test]
+	fn validate() {
+		assert_eq!(bool::from_remote_bytes(&[1]), Some(true));
+		assert_eq!(bool::from_remote_bytes(&[2]), None);
+		assert_eq!(char::from_remote_bytes(&0x41u32.to_ne_bytes()), Some('A'));
+		assert_eq!(char::from_remote_bytes(&0xD800u32.to_ne_bytes()), None);
+		assert_eq!(NonZeroU16::from_remote_bytes(&[0, 1]), NonZeroU16::new(u16::from_ne_bytes([0, 1])));
+		assert_eq!(NonZeroU16::from_remote_bytes(&[0, 0]), None);
+		assert_eq!(Option::<NonZeroU16>::from_remote_bytes(&[0, 0]), Some(None));
+		assert_eq!(Ptr32::<u8>::from_remote_bytes(&[0, 0x10, 0, 0]), Some(Ptr32::from(u32::from_ne_bytes([0, 0x10, 0, 0]))));
+		assert_eq!(u32::from_remote_bytes(&[0; 3]), None);
+	}
+}
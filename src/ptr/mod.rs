@@ -30,6 +30,16 @@ All the pointer types implement these interfaces:
 * Adding and subtracting an unsigned integer offset resulting in the same pointer with specified offset. For typed pointers the addition is in number of elements.
 
 * Display and Debug formatting.
+
+* Projection to a field of the pointed to struct, see `Ptr64::field`.
+
+# Pointer width
+
+The memory API accepts any [`RemotePtr`](trait.RemotePtr.html), both `Ptr32` and `Ptr64` can be used regardless of the host's pointer width.
+`Ptr` is an alias for the pointer with the host's width.
+
+Structures containing pointers can be written generic over an [`Arch`](trait.Arch.html) and instantiated for 32bit or 64bit targets, see [`TargetArch`](enum.TargetArch.html) to select one at runtime.
+
 !*/
 
 mod ptr64;
@@ -46,6 +56,25 @@ pub type Ptr<T> = Ptr32<T>;
 
 mod pod;
 pub use self::pod::Pod;
+pub use external_derive::Pod;
+
+mod from_remote_bytes;
+pub use self::from_remote_bytes::FromRemoteBytes;
+pub use external_derive::FromRemoteBytes;
+
+mod arch;
+pub use self::arch::*;
+
+// Byte offset of the field pointed to by the projection.
+//
+// The projection is applied to a pointer to a zeroed `T`, it must not create references unless every bit pattern is a valid `T`.
+fn field_offset<T, U: ?Sized, F: FnOnce(*const T) -> *const U>(f: F) -> usize {
+	let base = std::mem::MaybeUninit::<T>::zeroed();
+	let base_addr = base.as_ptr() as usize;
+	let offset = (f(base.as_ptr()) as *const u8 as usize).wrapping_sub(base_addr);
+	assert!(offset <= std::mem::size_of::<T>(), "projection must return a pointer into the struct");
+	offset
+}
 
 impl<T: ?Sized> From<Ptr32<T>> for Ptr64<T> {
 	fn from(ptr: Ptr32<T>) -> Ptr64<T> {
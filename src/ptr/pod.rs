@@ -3,11 +3,106 @@ use std::{mem, slice};
 /// Assert a type is plain old data.
 ///
 /// This is used to verify that such types are safe to be transferred between processes.
+///
+/// # Safety
+///
+/// Implementors must be valid for any bit pattern and must not contain padding or pointers into the current process.
+///
+/// # Deriving
+///
+/// Prefer `#[derive(Pod)]` over implementing the trait by hand, it validates the layout at compile time:
+///
+/// * The struct must be `#[repr(C)]` or `#[repr(transparent)]`.
+/// * Every field must be `Pod`, this includes arrays of `Pod` and the remote pointers `Ptr32` and `Ptr64`.
+/// * The struct must not contain padding.
+///
+/// Generic structs are validated for each instantiation the first time it is viewed as bytes, e.g. written to a process.
+/// Arrays, slices and structs containing the instantiation are validated along with it.
+///
+/// ```
+/// use external::ptr::{Arch, Pod, Ptr32};
+///
+/// #[derive(Copy, Clone, Pod)]
+/// #[repr(C)]
+/// struct Player {
+///     name: [u8; 20],
+///     health: f32,
+///     target: Ptr32<Player>,
+/// }
+///
+/// #[derive(Copy, Clone, Pod)]
+/// #[repr(C)]
+/// struct Node<A: Arch> {
+///     next: A::Ptr<Node<A>>,
+///     value: A::Usize,
+/// }
+/// ```
+///
+/// Structs without a defined layout are rejected:
+///
+/// ```compile_fail
+/// #[derive(external::ptr::Pod)]
+/// struct Player {
+///     health: f32,
+/// }
+/// ```
+///
+/// Fields which are not `Pod` are rejected:
+///
+/// ```compile_fail
+/// #[derive(external::ptr::Pod)]
+/// #[repr(C)]
+/// struct Player {
+///     name: String,
+/// }
+/// ```
+///
+/// Padding between or after the fields is rejected:
+///
+/// ```compile_fail
+/// #[derive(external::ptr::Pod)]
+/// #[repr(C)]
+/// struct Player {
+///     alive: u8,
+///     health: f32,
+/// }
+/// ```
+///
+/// Generic structs with padding are rejected when viewed as bytes, also inside arrays:
+///
+/// ```compile_fail
+/// use external::ptr::Pod;
+///
+/// #[derive(Copy, Clone, Pod)]
+/// #[repr(C)]
+/// struct Pair<A: Pod, B: Pod> {
+///     a: A,
+///     b: B,
+/// }
+///
+/// [Pair { a: 1u8, b: 2u32 }; 2].as_bytes();
+/// ```
+///
+/// Enums are rejected as not every bit pattern is a valid discriminant:
+///
+/// ```compile_fail
+/// #[derive(external::ptr::Pod)]
+/// #[repr(u32)]
+/// enum State {
+///     Alive,
+///     Dead,
+/// }
+/// ```
 pub unsafe trait Pod {
+	#[doc(hidden)]
+	const __ASSERT_LAYOUT: () = ();
+
 	fn as_bytes(&self) -> &[u8] {
+		let () = Self::__ASSERT_LAYOUT;
 		unsafe { slice::from_raw_parts(self as *const _ as *const _, mem::size_of_val(self)) }
 	}
 	fn as_bytes_mut(&mut self) -> &mut [u8] {
+		let () = Self::__ASSERT_LAYOUT;
 		unsafe { slice::from_raw_parts_mut(self as *mut _ as *mut _, mem::size_of_val(self)) }
 	}
 }
@@ -27,17 +122,72 @@ unsafe impl Pod for i64 {}
 unsafe impl Pod for f32 {}
 unsafe impl Pod for f64 {}
 
-unsafe impl<T: Pod> Pod for [T] {}
+unsafe impl<T: Pod> Pod for [T] {
+	#[doc(hidden)]
+	const __ASSERT_LAYOUT: () = T::__ASSERT_LAYOUT;
+}
+
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {
+	#[doc(hidden)]
+	const __ASSERT_LAYOUT: () = T::__ASSERT_LAYOUT;
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::mem;
+	use crate::ptr::{Arch, Pod, Ptr32, Ptr64, X64, X86};
 
-macro_rules! impl_pod_array {
-	($n:tt $($tail:tt)+) => {
-		unsafe impl<T: Pod> Pod for [T; $n] {}
-		impl_pod_array!($($tail)+);
-	};
-	($n:tt) => {
-		unsafe impl<T: Pod> Pod for [T; $n] {}
-	};
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Named {
+		bytes: [u8; 37],
+		byte: u8,
+		short: u16,
+		ptr32: Ptr32<Named>,
+		int: i32,
+		ptr64: Ptr64<[u8]>,
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(transparent)]
+	struct Tuple(u32);
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C, packed)]
+	struct Packed {
+		byte: u8,
+		long: u64,
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Generic<A: Arch> {
+		next: A::Ptr<Generic<A>>,
+		len: A::Usize,
+		tuples: [Tuple; 4],
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Unit;
+
+	#[test]
+	fn derive() {
+		let mut named: Named = unsafe { mem::zeroed() };
+		named.bytes[36] = 0xAA;
+		assert_eq!(named.as_bytes().len(), 56);
+		assert_eq!(named.as_bytes()[36], 0xAA);
+		assert_eq!(Tuple(0x01020304).as_bytes(), &0x01020304u32.to_ne_bytes());
+		assert_eq!(Packed { byte: 1, long: 2 }.as_bytes().len(), 9);
+		let mut generic: Generic<X86> = unsafe { mem::zeroed() };
+		generic.as_bytes_mut()[4] = 5;
+		assert_eq!(generic.len, 5);
+		assert_eq!(mem::size_of::<Generic<X86>>(), 24);
+		let generic: Generic<X64> = unsafe { mem::zeroed() };
+		assert_eq!(generic.as_bytes().len(), 32);
+		assert_eq!(Unit.as_bytes(), &[]);
+		assert_eq!([0u16; 1000].as_bytes().len(), 2000);
+	}
 }
-impl_pod_array!(0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15);
-impl_pod_array!(16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31);
-impl_pod_array!(32 48 64 80 100 128 160 192 256 512 768 1024 2048 4096);
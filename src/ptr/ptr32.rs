@@ -1,4 +1,4 @@
-use std::{cmp, fmt, ops, mem};
+use std::{cmp, fmt, hash, ops, mem};
 use std::marker::PhantomData;
 use super::Pod;
 
@@ -46,6 +46,73 @@ impl<T: ?Sized> Ptr32<T> {
 		self.0
 	}
 }
+impl<T: Pod> Ptr32<T> {
+	/// Projects the pointer to a field of the pointed to struct.
+	///
+	/// The closure returns a reference to the field, which may be nested or an element of an array.
+	/// It is called with a zeroed instance to find the offset of the field, no memory is read.
+	/// Panics if the returned reference does not point into the struct.
+	///
+	/// Structs which are not valid for every bit pattern are projected with [`field_raw`](#method.field_raw).
+	///
+	/// ```
+	/// use external::ptr::{Pod, Ptr32};
+	///
+	/// #[derive(Copy, Clone, Pod)]
+	/// #[repr(C)]
+	/// struct Vec3 {
+	///     x: f32,
+	///     y: f32,
+	///     z: f32,
+	/// }
+	///
+	/// #[derive(Copy, Clone, Pod)]
+	/// #[repr(C)]
+	/// struct Player {
+	///     position: Vec3,
+	///     health: f32,
+	///     items: [u32; 8],
+	/// }
+	///
+	/// let player = Ptr32::<Player>::from(0x1000);
+	/// assert_eq!(player.field(|p| &p.health), Ptr32::<f32>::from(0x100C));
+	/// assert_eq!(player.field(|p| &p.position.y), Ptr32::<f32>::from(0x1004));
+	/// assert_eq!(player.field(|p| &p.items[..]).at(3), Ptr32::<u32>::from(0x101C));
+	/// ```
+	pub fn field<U: ?Sized, F: FnOnce(&T) -> &U>(self, f: F) -> Ptr32<U> {
+		// Every bit pattern is a valid Pod, the zeroed instance can be borrowed
+		self.field_raw(|base| f(unsafe { &*base }))
+	}
+}
+impl<T> Ptr32<T> {
+	/// Projects the pointer to a field of the pointed to struct, for any struct.
+	///
+	/// The closure returns a pointer to the field from a pointer to the struct, using `addr_of!` to avoid creating references.
+	/// It is called with a pointer to zeroed memory which is not necessarily a valid `T`, no memory is read.
+	/// Panics if the returned pointer does not point into the struct.
+	///
+	/// ```
+	/// use std::num::NonZeroU32;
+	/// use std::ptr::addr_of;
+	/// use external::ptr::{FromRemoteBytes, Ptr32};
+	///
+	/// #[derive(Copy, Clone, FromRemoteBytes)]
+	/// #[repr(C)]
+	/// struct Player {
+	///     id: NonZeroU32,
+	///     alive: bool,
+	///     flags: [bool; 3],
+	/// }
+	///
+	/// let player = Ptr32::<Player>::from(0x1000);
+	/// assert_eq!(player.field_raw(|p| unsafe { addr_of!((*p).alive) }), Ptr32::<bool>::from(0x1004));
+	/// assert_eq!(player.field_raw(|p| unsafe { addr_of!((*p).flags) as *const [bool] }).at(2), Ptr32::<bool>::from(0x1007));
+	/// ```
+	pub fn field_raw<U: ?Sized, F: FnOnce(*const T) -> *const U>(self, f: F) -> Ptr32<U> {
+		let offset = super::field_offset(f);
+		Ptr32(self.0.wrapping_add(offset as u32), PhantomData)
+	}
+}
 impl<T> Ptr32<[T]> {
 	pub fn decay(self) -> Ptr32<T> {
 		Ptr32(self.0, PhantomData)
@@ -56,6 +123,7 @@ impl<T> Ptr32<[T]> {
 }
 impl<T> ops::Sub for Ptr32<T> {
 	type Output = i32;
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn sub(self, rhs: Ptr32<T>) -> i32 {
 		(u32::wrapping_sub(self.0, rhs.0) as i32) / mem::size_of::<T>() as i32
 	}
@@ -84,7 +152,7 @@ impl<T> ops::Sub<u32> for Ptr32<T> {
 }
 impl<T: ?Sized> Clone for Ptr32<T> {
 	fn clone(&self) -> Self {
-		Ptr32(self.0, self.1)
+		*self
 	}
 }
 impl<T: ?Sized> Default for Ptr32<T> {
@@ -99,7 +167,7 @@ impl<T: ?Sized> PartialEq for Ptr32<T> {
 }
 impl<T: ?Sized> PartialOrd for Ptr32<T> {
 	fn partial_cmp(&self, rhs: &Ptr32<T>) -> Option<cmp::Ordering> {
-		self.0.partial_cmp(&rhs.0)
+		Some(self.cmp(rhs))
 	}
 }
 impl<T: ?Sized> Copy for Ptr32<T> {}
@@ -109,12 +177,26 @@ impl<T: ?Sized> Ord for Ptr32<T> {
 		self.0.cmp(&rhs.0)
 	}
 }
+impl<T: ?Sized> hash::Hash for Ptr32<T> {
+	fn hash<H: hash::Hasher>(&self, state: &mut H) {
+		self.0.hash(state)
+	}
+}
 #[cfg(feature = "serde")]
 impl<T: ?Sized> serde::Serialize for Ptr32<T> {
 	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
 		serializer.serialize_newtype_struct("Ptr32", &self.0)
 	}
 }
+#[cfg(feature = "serde")]
+impl<'de, T: ?Sized> serde::Deserialize<'de> for Ptr32<T> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Ptr32<T>, D::Error> {
+		#[derive(serde::Deserialize)]
+		#[serde(rename = "Ptr32")]
+		struct Raw(u32);
+		Raw::deserialize(deserializer).map(|Raw(addr)| Ptr32(addr, PhantomData))
+	}
+}
 
 unsafe impl<T: ?Sized> Pod for Ptr32<T> {}
 
@@ -122,7 +204,9 @@ unsafe impl<T: ?Sized> Pod for Ptr32<T> {}
 
 #[cfg(test)]
 mod tests {
-	use std::mem;
+	use std::{mem, ptr};
+	use std::num::NonZeroU32;
+	use crate::ptr::FromRemoteBytes;
 	use super::*;
 
 	#[test]
@@ -136,4 +220,55 @@ mod tests {
 		assert_eq!(format!("{}", a), "0x00002000");
 		assert_eq!(c.into_raw(), 0x1F00);
 	}
+
+	#[test]
+	fn field() {
+		let a = Ptr32::<[u32; 4]>::from(0x2000);
+		assert_eq!(a.field(|a| &a[3]).into_raw(), 0x200C);
+		assert_eq!(a.field(|a| &a[4..]).into_raw(), 0x2010);
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Inner {
+		x: u32,
+		y: u32,
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Outer {
+		tag: u64,
+		inner: Inner,
+		items: [u16; 4],
+	}
+
+	#[test]
+	fn field_nested() {
+		let outer = Ptr32::<Outer>::from(0x2000);
+		assert_eq!(outer.field(|o| &o.inner.y).into_raw(), 0x200C);
+		assert_eq!(outer.field(|o| &o.items[..]).at(3).into_raw(), 0x2016);
+	}
+
+	#[derive(Copy, Clone, FromRemoteBytes)]
+	#[repr(C)]
+	struct Flags {
+		id: NonZeroU32,
+		alive: bool,
+		state: [bool; 3],
+	}
+
+	#[test]
+	fn field_raw() {
+		let flags = Ptr32::<Flags>::from(0x2000);
+		assert_eq!(flags.field_raw(|f| unsafe { ptr::addr_of!((*f).alive) }).into_raw(), 0x2004);
+		assert_eq!(flags.field_raw(|f| unsafe { ptr::addr_of!((*f).state) as *const [bool] }).at(2).into_raw(), 0x2007);
+	}
+
+	#[test]
+	#[should_panic]
+	fn field_outside() {
+		static OTHER: u32 = 0;
+		Ptr32::<[u32; 4]>::from(0x2000).field(|_| &OTHER);
+	}
 }
@@ -1,4 +1,4 @@
-use std::{cmp, fmt, ops, mem};
+use std::{cmp, fmt, hash, ops, mem};
 use std::marker::PhantomData;
 use super::Pod;
 
@@ -46,6 +46,73 @@ impl<T: ?Sized> Ptr64<T> {
 		self.0
 	}
 }
+impl<T: Pod> Ptr64<T> {
+	/// Projects the pointer to a field of the pointed to struct.
+	///
+	/// The closure returns a reference to the field, which may be nested or an element of an array.
+	/// It is called with a zeroed instance to find the offset of the field, no memory is read.
+	/// Panics if the returned reference does not point into the struct.
+	///
+	/// Structs which are not valid for every bit pattern are projected with [`field_raw`](#method.field_raw).
+	///
+	/// ```
+	/// use external::ptr::{Pod, Ptr64};
+	///
+	/// #[derive(Copy, Clone, Pod)]
+	/// #[repr(C)]
+	/// struct Vec3 {
+	///     x: f32,
+	///     y: f32,
+	///     z: f32,
+	/// }
+	///
+	/// #[derive(Copy, Clone, Pod)]
+	/// #[repr(C)]
+	/// struct Player {
+	///     position: Vec3,
+	///     health: f32,
+	///     items: [u32; 8],
+	/// }
+	///
+	/// let player = Ptr64::<Player>::from(0x1000);
+	/// assert_eq!(player.field(|p| &p.health), Ptr64::<f32>::from(0x100C));
+	/// assert_eq!(player.field(|p| &p.position.y), Ptr64::<f32>::from(0x1004));
+	/// assert_eq!(player.field(|p| &p.items[..]).at(3), Ptr64::<u32>::from(0x101C));
+	/// ```
+	pub fn field<U: ?Sized, F: FnOnce(&T) -> &U>(self, f: F) -> Ptr64<U> {
+		// Every bit pattern is a valid Pod, the zeroed instance can be borrowed
+		self.field_raw(|base| f(unsafe { &*base }))
+	}
+}
+impl<T> Ptr64<T> {
+	/// Projects the pointer to a field of the pointed to struct, for any struct.
+	///
+	/// The closure returns a pointer to the field from a pointer to the struct, using `addr_of!` to avoid creating references.
+	/// It is called with a pointer to zeroed memory which is not necessarily a valid `T`, no memory is read.
+	/// Panics if the returned pointer does not point into the struct.
+	///
+	/// ```
+	/// use std::num::NonZeroU32;
+	/// use std::ptr::addr_of;
+	/// use external::ptr::{FromRemoteBytes, Ptr64};
+	///
+	/// #[derive(Copy, Clone, FromRemoteBytes)]
+	/// #[repr(C)]
+	/// struct Player {
+	///     id: NonZeroU32,
+	///     alive: bool,
+	///     flags: [bool; 3],
+	/// }
+	///
+	/// let player = Ptr64::<Player>::from(0x1000);
+	/// assert_eq!(player.field_raw(|p| unsafe { addr_of!((*p).alive) }), Ptr64::<bool>::from(0x1004));
+	/// assert_eq!(player.field_raw(|p| unsafe { addr_of!((*p).flags) as *const [bool] }).at(2), Ptr64::<bool>::from(0x1007));
+	/// ```
+	pub fn field_raw<U: ?Sized, F: FnOnce(*const T) -> *const U>(self, f: F) -> Ptr64<U> {
+		let offset = super::field_offset(f);
+		Ptr64(self.0.wrapping_add(offset as u64), PhantomData)
+	}
+}
 impl<T> Ptr64<[T]> {
 	pub fn decay(self) -> Ptr64<T> {
 		Ptr64(self.0, PhantomData)
@@ -56,6 +123,7 @@ impl<T> Ptr64<[T]> {
 }
 impl<T> ops::Sub for Ptr64<T> {
 	type Output = i64;
+	#[allow(clippy::suspicious_arithmetic_impl)]
 	fn sub(self, rhs: Ptr64<T>) -> i64 {
 		(u64::wrapping_sub(self.0, rhs.0) as i64) / mem::size_of::<T>() as i64
 	}
@@ -84,7 +152,7 @@ impl<T> ops::Sub<u64> for Ptr64<T> {
 }
 impl<T: ?Sized> Clone for Ptr64<T> {
 	fn clone(&self) -> Self {
-		Ptr64(self.0, self.1)
+		*self
 	}
 }
 impl<T: ?Sized> Default for Ptr64<T> {
@@ -99,7 +167,7 @@ impl<T: ?Sized> PartialEq for Ptr64<T> {
 }
 impl<T: ?Sized> PartialOrd for Ptr64<T> {
 	fn partial_cmp(&self, rhs: &Ptr64<T>) -> Option<cmp::Ordering> {
-		self.0.partial_cmp(&rhs.0)
+		Some(self.cmp(rhs))
 	}
 }
 impl<T: ?Sized> Copy for Ptr64<T> {}
@@ -109,12 +177,26 @@ impl<T: ?Sized> Ord for Ptr64<T> {
 		self.0.cmp(&rhs.0)
 	}
 }
+impl<T: ?Sized> hash::Hash for Ptr64<T> {
+	fn hash<H: hash::Hasher>(&self, state: &mut H) {
+		self.0.hash(state)
+	}
+}
 #[cfg(feature = "serde")]
 impl<T: ?Sized> serde::Serialize for Ptr64<T> {
 	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
 		serializer.serialize_newtype_struct("Ptr64", &self.0)
 	}
 }
+#[cfg(feature = "serde")]
+impl<'de, T: ?Sized> serde::Deserialize<'de> for Ptr64<T> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Ptr64<T>, D::Error> {
+		#[derive(serde::Deserialize)]
+		#[serde(rename = "Ptr64")]
+		struct Raw(u64);
+		Raw::deserialize(deserializer).map(|Raw(addr)| Ptr64(addr, PhantomData))
+	}
+}
 
 unsafe impl<T: ?Sized> Pod for Ptr64<T> {}
 
@@ -122,7 +204,9 @@ unsafe impl<T: ?Sized> Pod for Ptr64<T> {}
 
 #[cfg(test)]
 mod tests {
-	use std::mem;
+	use std::{mem, ptr};
+	use std::num::NonZeroU32;
+	use crate::ptr::FromRemoteBytes;
 	use super::*;
 
 	#[test]
@@ -136,4 +220,55 @@ mod tests {
 		assert_eq!(format!("{}", a), "0x0000000000002000");
 		assert_eq!(c.into_raw(), 0x1E00);
 	}
+
+	#[test]
+	fn field() {
+		let a = Ptr64::<[u64; 4]>::from(0x2000);
+		assert_eq!(a.field(|a| &a[3]).into_raw(), 0x2018);
+		assert_eq!(a.field(|a| &a[4..]).into_raw(), 0x2020);
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Inner {
+		x: u32,
+		y: u32,
+	}
+
+	#[derive(Copy, Clone, Pod)]
+	#[repr(C)]
+	struct Outer {
+		tag: u64,
+		inner: Inner,
+		items: [u16; 4],
+	}
+
+	#[test]
+	fn field_nested() {
+		let outer = Ptr64::<Outer>::from(0x2000);
+		assert_eq!(outer.field(|o| &o.inner.y).into_raw(), 0x200C);
+		assert_eq!(outer.field(|o| &o.items[..]).at(3).into_raw(), 0x2016);
+	}
+
+	#[derive(Copy, Clone, FromRemoteBytes)]
+	#[repr(C)]
+	struct Flags {
+		id: NonZeroU32,
+		alive: bool,
+		state: [bool; 3],
+	}
+
+	#[test]
+	fn field_raw() {
+		let flags = Ptr64::<Flags>::from(0x2000);
+		assert_eq!(flags.field_raw(|f| unsafe { ptr::addr_of!((*f).alive) }).into_raw(), 0x2004);
+		assert_eq!(flags.field_raw(|f| unsafe { ptr::addr_of!((*f).state) as *const [bool] }).at(2).into_raw(), 0x2007);
+	}
+
+	#[test]
+	#[should_panic]
+	fn field_outside() {
+		static OTHER: u64 = 0;
+		Ptr64::<[u64; 4]>::from(0x2000).field(|_| &OTHER);
+	}
 }
@@ -0,0 +1,12 @@
+/*!
+Standard access rights.
+
+These apply to every securable object, the process and thread rights are built from them.
+The values are those of the Windows SDK, they are defined here so the rights types exist on every platform.
+!*/
+
+pub const DELETE: u32 = 0x00010000;
+pub const READ_CONTROL: u32 = 0x00020000;
+pub const WRITE_DAC: u32 = 0x00040000;
+pub const WRITE_OWNER: u32 = 0x00080000;
+pub const SYNCHRONIZE: u32 = 0x00100000;
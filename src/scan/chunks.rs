@@ -0,0 +1,46 @@
+use std::cmp;
+use crate::vm::{regions_in, AddressRange, MemoryInformation, VirtualMemory};
+use crate::Result;
+
+/// Number of bytes scanned per read, excluding the overlap with the next chunk.
+pub(crate) const CHUNK_SIZE: usize = 0x10000;
+
+// Memory in the range of the regions accepted by the filter, adjacent regions are merged into a single span.
+pub(crate) fn spans<V, F>(vm: &V, range: AddressRange, mut filter: F) -> Result<Vec<AddressRange>>
+	where V: VirtualMemory + ?Sized, F: FnMut(&MemoryInformation) -> bool
+{
+	let mut spans: Vec<AddressRange> = Vec::new();
+	regions_in(vm, range, |mi| {
+		if !filter(mi) {
+			return;
+		}
+		let region = AddressRange::from(mi);
+		match spans.last_mut() {
+			Some(last) if last.end() == region.start() => *last = AddressRange::new(last.start(), region.end()),
+			_ => spans.push(region),
+		}
+	})?;
+	Ok(spans)
+}
+
+// Reads the span in chunks of `CHUNK_SIZE` bytes followed by `overlap` bytes of the next chunk.
+//
+// The callback receives the address of the chunk, its bytes and the length of the chunk without the overlap.
+// Bytes which cannot be read end the chunk early. Returns false if the callback stopped the scan by returning false.
+pub(crate) fn read_chunks<V, F>(vm: &V, span: AddressRange, overlap: usize, buffer: &mut Vec<u8>, mut f: F) -> Result<bool>
+	where V: VirtualMemory + ?Sized, F: FnMut(u64, &[u8], usize) -> bool
+{
+	buffer.resize(CHUNK_SIZE + overlap, 0);
+	let mut address = span.start().into_raw();
+	let end = span.end().into_raw();
+	while address < end {
+		let len = cmp::min((end - address) as usize, buffer.len());
+		let bytes = vm.vm_read_partial(address as usize, &mut buffer[..len])?;
+		let chunk_len = cmp::min(bytes.len(), CHUNK_SIZE);
+		if !f(address, bytes, chunk_len) {
+			return Ok(false);
+		}
+		address = address.saturating_add(CHUNK_SIZE as u64);
+	}
+	Ok(true)
+}
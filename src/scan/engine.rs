@@ -0,0 +1,262 @@
+use std::{cmp, thread};
+use std::ops::Range;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use crate::error::{ErrorCode, ERROR_CANCELLED};
+use crate::vm::{AddressRange, MemoryInformation, VirtualMemory};
+use crate::Result;
+use super::chunks::{spans, CHUNK_SIZE};
+
+/// Chunk of memory handed to a [`ChunkMatcher`](trait.ChunkMatcher.html).
+#[derive(Clone, Debug)]
+pub struct Chunk<'a> {
+	/// The span of adjacent regions the chunk belongs to.
+	pub span: AddressRange,
+	/// The address of the first byte.
+	pub address: u64,
+	/// The lookbehind, the chunk and the overlap, clipped to the span and shorter if reading stopped early.
+	pub bytes: &'a [u8],
+	/// The offsets of the chunk in the bytes, without the lookbehind and the overlap.
+	pub range: Range<usize>,
+}
+
+/// Matcher run on every chunk by a [`ScanEngine`](struct.ScanEngine.html).
+///
+/// Matchers report the matches starting in the chunk range, the lookbehind and overlap only provide context.
+pub trait ChunkMatcher: Sync {
+	/// The type of the matches.
+	type Match: Send;
+	/// The number of bytes read past the end of each chunk, matches starting in a chunk can extend this far into the next.
+	fn overlap(&self) -> usize {
+		0
+	}
+	/// The number of bytes read before the start of each chunk.
+	fn lookbehind(&self) -> usize {
+		0
+	}
+	/// Appends the matches in the chunk, in order.
+	fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<Self::Match>);
+}
+
+/// Cancellation and progress of a scan shared with other threads.
+///
+/// The scan checks for cancellation between chunks, a cancelled scan fails with `ERROR_CANCELLED`.
+/// A scan does not clear the cancellation so a scan cancelled before it starts fails right away,
+/// the owner of the control calls [`reset`](#method.reset) to reuse it after cancelling.
+#[derive(Debug, Default)]
+pub struct ScanControl {
+	cancelled: AtomicBool,
+	scanned: AtomicU64,
+	total: AtomicU64,
+}
+impl ScanControl {
+	/// Creates a control for a scan which is not cancelled.
+	pub fn new() -> ScanControl {
+		ScanControl::default()
+	}
+	/// Requests the scan to stop.
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::Relaxed);
+	}
+	/// Clears the cancellation and the progress, for the next scan.
+	pub fn reset(&self) {
+		self.cancelled.store(false, Ordering::Relaxed);
+		self.scanned.store(0, Ordering::Relaxed);
+		self.total.store(0, Ordering::Relaxed);
+	}
+	/// Returns if the scan was cancelled.
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::Relaxed)
+	}
+	/// The number of bytes scanned so far.
+	pub fn scanned(&self) -> u64 {
+		self.scanned.load(Ordering::Relaxed)
+	}
+	/// The number of bytes to scan, known once the regions have been collected.
+	pub fn total(&self) -> u64 {
+		self.total.load(Ordering::Relaxed)
+	}
+}
+
+/// Multithreaded scan of the committed memory of a process.
+///
+/// The regions accepted by a filter are merged into spans of adjacent regions and split into chunks.
+/// A pool of worker threads reads the chunks, along with the lookbehind and overlap of the matcher, and runs the matcher on them.
+/// The matches are returned in order of the chunks, the result does not depend on the number of threads.
+///
+/// Signatures, values and strings are matched by [`Signature`](struct.Signature.html), [`ValueMatcher`](struct.ValueMatcher.html) and [`StringMatcher`](struct.StringMatcher.html).
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::scan::{ScanControl, ScanEngine, Signature};
+/// use external::vm::{AddressRange, MemoryInformation, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, &[0x90, 0xE8, 0x01, 0x02, 0x03, 0x04, 0xC3], Protect::EXECUTE_READ).unwrap() as u64;
+///
+/// let signature: Signature = "E8 ?? ?? ?? ?? C3".parse().unwrap();
+/// let control = ScanControl::new();
+/// let found = ScanEngine::default().scan(&process, AddressRange::all(), MemoryInformation::is_readable, &signature, &control);
+/// assert_eq!(found, Ok(vec![Ptr64::from(base + 1)]));
+/// assert_eq!(control.scanned(), control.total());
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScanEngine {
+	/// The number of worker threads, zero uses the available parallelism.
+	pub threads: usize,
+	/// The number of bytes per chunk, excluding the lookbehind and the overlap.
+	pub chunk_size: usize,
+}
+impl Default for ScanEngine {
+	fn default() -> ScanEngine {
+		ScanEngine { threads: 0, chunk_size: CHUNK_SIZE }
+	}
+}
+impl ScanEngine {
+	/// Scans the regions in the range accepted by the filter.
+	///
+	/// Resets the progress of the control, fails with `ERROR_CANCELLED` if the scan was cancelled or with the first error reading a chunk.
+	pub fn scan<V, F, M>(&self, vm: &V, range: AddressRange, filter: F, matcher: &M, control: &ScanControl) -> Result<Vec<M::Match>>
+		where V: VirtualMemory + Sync + ?Sized, F: FnMut(&MemoryInformation) -> bool, M: ChunkMatcher + ?Sized
+	{
+		assert!(self.chunk_size > 0, "chunk size must not be zero");
+		control.scanned.store(0, Ordering::Relaxed);
+		control.total.store(0, Ordering::Relaxed);
+		let mut chunks = Vec::new();
+		for span in spans(vm, range, filter)? {
+			control.total.fetch_add(span.len(), Ordering::Relaxed);
+			let mut address = span.start().into_raw();
+			while address < span.end().into_raw() {
+				chunks.push((span, address));
+				address = address.saturating_add(self.chunk_size as u64);
+			}
+		}
+		let threads = match self.threads {
+			0 => thread::available_parallelism().map_or(1, |threads| threads.get()),
+			threads => threads,
+		};
+		let threads = cmp::max(cmp::min(threads, chunks.len()), 1);
+
+		let next = AtomicUsize::new(0);
+		// The error of the lowest chunk, chunks are handed out in order so the error does not depend on timing
+		let error: Mutex<Option<(usize, ErrorCode)>> = Mutex::new(None);
+		let worker = || {
+			let mut buffer = Vec::new();
+			let mut results = Vec::new();
+			while !control.is_cancelled() && error.lock().unwrap().is_none() {
+				let index = next.fetch_add(1, Ordering::Relaxed);
+				let &(span, address) = match chunks.get(index) {
+					Some(chunk) => chunk,
+					None => break,
+				};
+				match self.scan_chunk(vm, span, address, matcher, &mut buffer) {
+					Ok(matches) => results.push((index, matches)),
+					Err(err) => {
+						let mut error = error.lock().unwrap();
+						if error.is_none_or(|(first, _)| index < first) {
+							*error = Some((index, err));
+						}
+					},
+				}
+				let len = cmp::min(self.chunk_size as u64, span.end().into_raw() - address);
+				control.scanned.fetch_add(len, Ordering::Relaxed);
+			}
+			results
+		};
+		let mut results = if threads == 1 {
+			worker()
+		}
+		else {
+			thread::scope(|scope| {
+				let workers: Vec<_> = (0..threads).map(|_| scope.spawn(worker)).collect();
+				workers.into_iter().flat_map(|worker| worker.join().unwrap()).collect()
+			})
+		};
+
+		if let Some((_, err)) = error.into_inner().unwrap() {
+			return Err(err);
+		}
+		if control.is_cancelled() {
+			return Err(ERROR_CANCELLED);
+		}
+		results.sort_unstable_by_key(|&(index, _)| index);
+		Ok(results.into_iter().flat_map(|(_, matches)| matches).collect())
+	}
+	fn scan_chunk<V, M>(&self, vm: &V, span: AddressRange, address: u64, matcher: &M, buffer: &mut Vec<u8>) -> Result<Vec<M::Match>>
+		where V: VirtualMemory + ?Sized, M: ChunkMatcher + ?Sized
+	{
+		let start = cmp::max(address.saturating_sub(matcher.lookbehind() as u64), span.start().into_raw());
+		let end = cmp::min(address.saturating_add((self.chunk_size + matcher.overlap()) as u64), span.end().into_raw());
+		buffer.resize((end - start) as usize, 0);
+		let bytes = vm.vm_read_partial(start as usize, buffer)?;
+		let offset = (address - start) as usize;
+		let chunk_end = cmp::min(offset + self.chunk_size, (span.end().into_raw() - start) as usize);
+		let chunk = Chunk {
+			span,
+			address: start,
+			range: cmp::min(offset, bytes.len())..cmp::min(chunk_end, bytes.len()),
+			bytes,
+		};
+		let mut matches = Vec::new();
+		matcher.scan_chunk(&chunk, &mut matches);
+		Ok(matches)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::ptr::Ptr64;
+	use crate::vm::{MockProcess, Protect};
+	use super::*;
+
+	// Reports the address of every chunk and checks the context it was given.
+	struct Chunks;
+	impl ChunkMatcher for Chunks {
+		type Match = (u64, usize, usize);
+		fn overlap(&self) -> usize {
+			3
+		}
+		fn lookbehind(&self) -> usize {
+			2
+		}
+		fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<(u64, usize, usize)>) {
+			matches.push((chunk.address + chunk.range.start as u64, chunk.range.start, chunk.bytes.len() - chunk.range.end));
+		}
+	}
+
+	#[test]
+	fn chunks() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x3000], Protect::READ_WRITE).unwrap() as u64;
+		process.map(0x20000, &[0; 0x1000], Protect::READ_ONLY).unwrap();
+		let control = ScanControl::new();
+		for threads in 1..4 {
+			let engine = ScanEngine { threads, chunk_size: 0x1000 };
+			let range = AddressRange::from_len(Ptr64::from(base + 0x800), 0x2000);
+			let chunks = engine.scan(&process, range, MemoryInformation::is_readable, &Chunks, &control).unwrap();
+			assert_eq!(chunks, [(base + 0x800, 0, 3), (base + 0x1800, 2, 0)]);
+			assert_eq!((control.scanned(), control.total()), (0x2000, 0x2000));
+			let chunks = engine.scan(&process, AddressRange::all(), |mi| mi.protect == Protect::READ_ONLY, &Chunks, &control).unwrap();
+			assert_eq!(chunks, [(0x20000, 0, 0)]);
+		}
+	}
+
+	#[test]
+	fn cancel() {
+		let process = MockProcess::new();
+		process.map(0x10000, &[0; 0x4000], Protect::READ_WRITE).unwrap();
+		let control = ScanControl::new();
+		control.cancel();
+		let engine = ScanEngine { threads: 2, chunk_size: 0x1000 };
+		assert_eq!(engine.scan(&process, AddressRange::all(), MemoryInformation::is_readable, &Chunks, &control), Err(ERROR_CANCELLED));
+		assert_eq!((control.scanned(), control.total()), (0, 0x4000));
+		control.reset();
+		assert!(!control.is_cancelled());
+		assert_eq!(engine.scan(&process, AddressRange::all(), MemoryInformation::is_readable, &Chunks, &control).map(|chunks| chunks.len()), Ok(4));
+	}
+}
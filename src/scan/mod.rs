@@ -0,0 +1,27 @@
+/*!
+Scanning the memory of a process.
+
+Scans walk the committed regions of an [`AddressRange`](../vm/struct.AddressRange.html), adjacent regions are scanned as one.
+Signatures are searched in all readable memory, values only in writable memory.
+The memory is read in chunks which overlap, matches crossing the boundary between two chunks are found.
+The [`ScanEngine`](struct.ScanEngine.html) reads and matches the chunks on multiple threads, any [`ChunkMatcher`](trait.ChunkMatcher.html) plugs into it.
+
+Signature matches are turned into the address of interest by a [`Resolver`](struct.Resolver.html).
+
+Use [`AddressRange::all`](../vm/struct.AddressRange.html#method.all) to scan the whole address space or convert a `ModuleEntry` to scan a single module.
+!*/
+
+mod chunks;
+mod engine;
+mod signature;
+mod value;
+mod pointer;
+mod strings;
+mod resolve;
+
+pub use self::engine::*;
+pub use self::signature::*;
+pub use self::value::*;
+pub use self::pointer::*;
+pub use self::strings::*;
+pub use self::resolve::*;
@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use crate::error::ERROR_CANCELLED;
+use crate::ptr::{Ptr64, TargetArch};
+use crate::vm::{AddressRange, AddressSpaceMap, PointerPath, VirtualMemory};
+use crate::Result;
+use super::chunks::read_chunks;
+use super::ScanControl;
+
+/// Limits of a pointer scan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PointerScanOptions {
+	/// Maximum number of pointers followed from a module to the target.
+	pub max_depth: usize,
+	/// Maximum offset added to each pointer.
+	pub max_offset: u64,
+	/// The scan stops after finding this many paths.
+	pub max_results: usize,
+}
+impl Default for PointerScanOptions {
+	fn default() -> PointerScanOptions {
+		PointerScanOptions { max_depth: 4, max_offset: 0x1000, max_results: 10000 }
+	}
+}
+
+/// Reverse pointer map of the readable memory of a process.
+///
+/// Records the address of every aligned value which points into readable memory, sorted by the value pointed to.
+/// Pointer paths to an address are found by walking the map backwards from the address until a pointer stored in a module is found.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::{Ptr64, TargetArch};
+/// use external::scan::{PointerMap, PointerScanOptions};
+/// use external::vm::{AddressRange, AddressSpaceMap, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let player = process.map(0x20000, &[0; 0x100], Protect::READ_WRITE).unwrap() as u64;
+/// let module = process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap() as u64;
+/// process.poke(module as usize + 0x80, &player.to_le_bytes()).unwrap();
+///
+/// let mut space = AddressSpaceMap::from_vm(&process).unwrap();
+/// space.insert_module("game.exe", AddressRange::from_len(Ptr64::from(module), 0x1000));
+///
+/// let map = PointerMap::build(&process, &space, TargetArch::X64).unwrap();
+/// let paths = map.scan(Ptr64::from(player + 0x48), &PointerScanOptions::default());
+/// assert_eq!(paths[0].to_string(), "game.exe+0x80 -> 0x48");
+/// ```
+#[derive(Clone, Debug)]
+pub struct PointerMap {
+	arch: TargetArch,
+	// Pairs of the value and the address it is stored at.
+	pointers: Vec<(u64, u64)>,
+	modules: AddressSpaceMap,
+}
+impl PointerMap {
+	/// Reads the readable regions of the address space and collects the pointers.
+	///
+	/// Pointers are read with the pointer size of the target architecture at addresses aligned to it.
+	pub fn build<V: VirtualMemory + ?Sized>(vm: &V, space: &AddressSpaceMap, arch: TargetArch) -> Result<PointerMap> {
+		let size = arch.pointer_size();
+		let mut pointers = Vec::new();
+		let mut buffer = Vec::new();
+		for region in space.regions().iter().filter(|region| region.is_readable()) {
+			read_chunks(vm, AddressRange::from(region), 0, &mut buffer, |address, bytes, _| {
+				for (index, value) in bytes.chunks_exact(size).enumerate() {
+					let mut raw = [0u8; 8];
+					raw[..size].copy_from_slice(value);
+					let value = u64::from_le_bytes(raw);
+					if space.is_plausible_pointer(value) {
+						pointers.push((value, address + (index * size) as u64));
+					}
+				}
+				true
+			})?;
+		}
+		pointers.sort_unstable();
+		let mut modules = AddressSpaceMap::new();
+		for module in space.modules() {
+			modules.insert_module(module.name.clone(), module.range);
+		}
+		Ok(PointerMap { arch, pointers, modules })
+	}
+	/// The target architecture the pointers were read for.
+	pub fn arch(&self) -> TargetArch {
+		self.arch
+	}
+	/// The number of pointers in the map.
+	pub fn len(&self) -> usize {
+		self.pointers.len()
+	}
+	/// Returns if no pointers were found.
+	pub fn is_empty(&self) -> bool {
+		self.pointers.is_empty()
+	}
+	/// Finds the addresses storing a pointer to an address in the range, in order of the value pointed to.
+	pub fn pointers_to(&self, range: AddressRange) -> impl Iterator<Item = (Ptr64, Ptr64)> + '_ {
+		let start = self.pointers.partition_point(|&(value, _)| value < range.start().into_raw());
+		self.pointers[start..].iter()
+			.take_while(move |&&(value, _)| value < range.end().into_raw())
+			.map(|&(value, address)| (Ptr64::from(value), Ptr64::from(address)))
+	}
+	/// Finds the pointer paths from module static data to the target.
+	///
+	/// Paths are found depth first, in order of the offset of the last pointer from the target.
+	pub fn scan(&self, target: Ptr64, options: &PointerScanOptions) -> Vec<PointerPath> {
+		// Only cancellation fails the scan
+		self.scan_with(target, options, &ScanControl::new()).unwrap_or_default()
+	}
+	/// Finds the pointer paths from module static data to the target, checking the control for cancellation.
+	///
+	/// Addresses found not to lead to a module are not searched again, fails with `ERROR_CANCELLED` if the scan was cancelled.
+	pub fn scan_with(&self, target: Ptr64, options: &PointerScanOptions, control: &ScanControl) -> Result<Vec<PointerPath>> {
+		let mut search = Search { map: self, options, control, offsets: Vec::new(), paths: Vec::new(), dead_ends: HashMap::new() };
+		if options.max_results > 0 {
+			search.visit(target.into_raw())?;
+		}
+		Ok(search.paths)
+	}
+}
+
+// Depth first search for pointer paths.
+struct Search<'a> {
+	map: &'a PointerMap,
+	options: &'a PointerScanOptions,
+	control: &'a ScanControl,
+	// Offsets are pushed from the target backwards.
+	offsets: Vec<u64>,
+	paths: Vec<PointerPath>,
+	// The largest remaining depth for which an address is known not to lead to a module.
+	dead_ends: HashMap<u64, usize>,
+}
+impl<'a> Search<'a> {
+	// Returns false once enough paths are found.
+	fn visit(&mut self, target: u64) -> Result<bool> {
+		if self.control.is_cancelled() {
+			return Err(ERROR_CANCELLED);
+		}
+		let remaining = self.options.max_depth - self.offsets.len();
+		if self.dead_ends.get(&target).is_some_and(|&depth| depth >= remaining) {
+			return Ok(true);
+		}
+		let found = self.paths.len();
+		let map = self.map;
+		let low = target.saturating_sub(self.options.max_offset);
+		let start = map.pointers.partition_point(|&(value, _)| value < low);
+		let end = map.pointers.partition_point(|&(value, _)| value <= target);
+		// The closest pointers come last, visit them first
+		for &(value, address) in map.pointers[start..end].iter().rev() {
+			self.offsets.push(target - value);
+			if let Some(module) = map.modules.module(Ptr64::from(address)) {
+				let mut chain = self.offsets.clone();
+				chain.reverse();
+				self.paths.push(PointerPath::from_chain(&module.name, address - module.range.start().into_raw(), &chain));
+				if self.paths.len() >= self.options.max_results {
+					return Ok(false);
+				}
+			}
+			else if self.offsets.len() < self.options.max_depth && !self.visit(address)? {
+				return Ok(false);
+			}
+			self.offsets.pop();
+		}
+		if self.paths.len() == found {
+			self.dead_ends.insert(target, remaining);
+		}
+		Ok(true)
+	}
+}
+
+/// Keeps the pointer paths which resolve to the target.
+///
+/// Validates the paths of an earlier scan against a later session of the process, modules are looked up by name in the address space map.
+pub fn rescan_paths<V: VirtualMemory + ?Sized>(paths: &[PointerPath], vm: &V, space: &AddressSpaceMap, arch: TargetArch, target: Ptr64) -> Vec<PointerPath> {
+	let modules = |name: &str| space.module_by_name(name).map(|module| module.range.start().into_raw());
+	paths.iter()
+		.filter(|path| path.resolve_address(vm, arch, modules) == Ok(target.into_raw()))
+		.cloned()
+		.collect()
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::vm::{MemoryType, MockProcess, Protect};
+	use super::*;
+
+	fn poke(process: &MockProcess, address: u64, value: u64) {
+		process.poke(address as usize, &value.to_le_bytes()).unwrap();
+	}
+
+	#[test]
+	fn scan() {
+		let process = MockProcess::new();
+		let module = process.map_type(0x10000, &[0; 0x1000], Protect::READ_WRITE, MemoryType::IMAGE).unwrap() as u64;
+		let heap = process.map(0x20000, &[0; 0x2000], Protect::READ_WRITE).unwrap() as u64;
+		let (player, health) = (heap + 0x100, heap + 0x1000);
+		// game.exe+0x100 -> 0x10 -> 0x48 and game.exe+0x200 -> 0x40 -> 0x48
+		poke(&process, module + 0x100, player - 0x10);
+		poke(&process, module + 0x200, player - 0x40);
+		poke(&process, player, health - 0x48);
+
+		let mut space = AddressSpaceMap::from_vm(&process).unwrap();
+		space.insert_module("game.exe", AddressRange::from_len(Ptr64::from(module), 0x1000));
+		let map = PointerMap::build(&process, &space, TargetArch::X64).unwrap();
+		assert_eq!(map.len(), 3);
+		assert_eq!(map.pointers_to(AddressRange::from_len(Ptr64::from(player - 0x10), 1)).collect::<Vec<_>>(), [(Ptr64::from(player - 0x10), Ptr64::from(module + 0x100))]);
+
+		let mut options = PointerScanOptions { max_depth: 2, max_offset: 0x100, max_results: 10 };
+		let paths: Vec<String> = map.scan(Ptr64::from(health), &options).iter().map(|path| path.to_string()).collect();
+		assert_eq!(paths, ["game.exe+0x100 -> 0x10 -> 0x48", "game.exe+0x200 -> 0x40 -> 0x48"]);
+		let paths: Vec<String> = map.scan(Ptr64::from(player + 0x48), &options).iter().map(|path| path.to_string()).collect();
+		assert_eq!(paths, ["game.exe+0x100 -> 0x58", "game.exe+0x200 -> 0x88"]);
+		options.max_results = 1;
+		assert_eq!(map.scan(Ptr64::from(player + 0x48), &options).len(), 1);
+		options.max_depth = 1;
+		assert!(map.scan(Ptr64::from(health), &options).is_empty());
+
+		// The next session allocates the health elsewhere
+		let paths = map.scan(Ptr64::from(player + 0x48), &PointerScanOptions::default());
+		let health = heap + 0x1800;
+		poke(&process, player, health - 0x48);
+		let paths = [paths, vec![PointerPath::from_chain("game.exe", 0x100, &[0x10, 0x48])]].concat();
+		let valid = rescan_paths(&paths, &process, &space, TargetArch::X64, Ptr64::from(health));
+		assert_eq!(valid, [PointerPath::from_chain("game.exe", 0x100, &[0x10, 0x48])]);
+	}
+
+	#[test]
+	fn dead_ends() {
+		// Every pointer of the heap points into the heap, there is no path from a module
+		let process = MockProcess::new();
+		let heap = process.map(0x20000, &[0; 0x1000], Protect::READ_WRITE).unwrap() as u64;
+		process.map(0x10000, &[0; 0x1000], Protect::READ_ONLY).unwrap();
+		for offset in (0..0x1000).step_by(8) {
+			poke(&process, heap + offset, heap + offset / 2);
+		}
+		let mut space = AddressSpaceMap::from_vm(&process).unwrap();
+		space.insert_module("game.exe", AddressRange::from_len(Ptr64::from(0x10000), 0x1000));
+		let map = PointerMap::build(&process, &space, TargetArch::X64).unwrap();
+		let options = PointerScanOptions { max_depth: 8, ..PointerScanOptions::default() };
+		assert_eq!(map.scan(Ptr64::from(heap + 0x800), &options), []);
+
+		let control = ScanControl::new();
+		control.cancel();
+		assert_eq!(map.scan_with(Ptr64::from(heap + 0x800), &options, &control), Err(ERROR_CANCELLED));
+	}
+
+	#[test]
+	fn scan_x86() {
+		let process = MockProcess::new();
+		let module = process.map(0x10000, &[0; 0x1000], Protect::READ_ONLY).unwrap() as u64;
+		let heap = process.map(0x20000, &[0; 0x1000], Protect::READ_WRITE).unwrap() as u64;
+		process.poke(module as usize + 0x24, &(heap as u32 + 0x10).to_le_bytes()).unwrap();
+		let mut space = AddressSpaceMap::from_vm(&process).unwrap();
+		space.insert_module("game.exe", AddressRange::from_len(Ptr64::from(module), 0x1000));
+		let map = PointerMap::build(&process, &space, TargetArch::X86).unwrap();
+		let paths = map.scan(Ptr64::from(heap + 0x20), &PointerScanOptions::default());
+		assert_eq!(paths, [PointerPath::from_chain("game.exe", 0x24, &[0x10])]);
+	}
+}
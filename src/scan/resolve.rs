@@ -0,0 +1,360 @@
+use std::{error, fmt, str};
+use std::convert::TryFrom;
+use crate::error::ErrorCode;
+use crate::ptr::{Ptr64, TargetArch};
+use crate::vm::{AddressRange, VirtualMemory};
+use super::{PatternError, Signature};
+
+/// Integer type of a read or cast step.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum IntType {
+	U8, U16, U32, U64,
+	I8, I16, I32, I64,
+}
+impl IntType {
+	/// The size of the type, in bytes.
+	pub fn size(self) -> usize {
+		match self {
+			IntType::U8 | IntType::I8 => 1,
+			IntType::U16 | IntType::I16 => 2,
+			IntType::U32 | IntType::I32 => 4,
+			IntType::U64 | IntType::I64 => 8,
+		}
+	}
+	/// Truncates the value to the type, signed types are sign extended back to 64 bits.
+	pub fn cast(self, value: u64) -> u64 {
+		match self {
+			IntType::U8 => value as u8 as u64,
+			IntType::U16 => value as u16 as u64,
+			IntType::U32 => value as u32 as u64,
+			IntType::U64 => value,
+			IntType::I8 => value as i8 as u64,
+			IntType::I16 => value as i16 as u64,
+			IntType::I32 => value as i32 as u64,
+			IntType::I64 => value,
+		}
+	}
+	fn name(self) -> &'static str {
+		match self {
+			IntType::U8 => "u8",
+			IntType::U16 => "u16",
+			IntType::U32 => "u32",
+			IntType::U64 => "u64",
+			IntType::I8 => "i8",
+			IntType::I16 => "i16",
+			IntType::I32 => "i32",
+			IntType::I64 => "i64",
+		}
+	}
+}
+impl str::FromStr for IntType {
+	type Err = ();
+	fn from_str(s: &str) -> Result<IntType, ()> {
+		[IntType::U8, IntType::U16, IntType::U32, IntType::U64, IntType::I8, IntType::I16, IntType::I32, IntType::I64]
+			.iter()
+			.copied()
+			.find(|ty| ty.name() == s)
+			.ok_or(())
+	}
+}
+impl fmt::Display for IntType {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(self.name())
+	}
+}
+
+/// Step of a [`Resolver`](struct.Resolver.html).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Step {
+	/// Adds an offset to the address, written `+0x10` or `-8`.
+	Add(i64),
+	/// Resolves a relative displacement, written `rel32(disp, len)`.
+	///
+	/// Reads the signed 32-bit displacement `disp` bytes into the instruction at the address and adds it to the end of the instruction, `len` bytes from the address.
+	/// A `call rel32` is `rel32(1, 5)`, a `mov rax, [rip+disp32]` is `rel32(3, 7)`.
+	Rel32 { disp: u64, len: u64 },
+	/// Reads the pointer at the address, written `deref`.
+	Deref,
+	/// Reads an integer at the address, written `read(u32)`.
+	Read(IntType),
+	/// Truncates the value to an integer type, written `cast(i32)`.
+	Cast(IntType),
+}
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match *self {
+			Step::Add(offset) if offset < 0 => write!(f, "-{:#X}", offset.unsigned_abs()),
+			Step::Add(offset) => write!(f, "+{:#X}", offset),
+			Step::Rel32 { disp, len } => write!(f, "rel32({:#X}, {:#X})", disp, len),
+			Step::Deref => f.write_str("deref"),
+			Step::Read(ty) => write!(f, "read({})", ty),
+			Step::Cast(ty) => write!(f, "cast({})", ty),
+		}
+	}
+}
+
+/// Declarative pipeline turning a signature match into the address or value of interest.
+///
+/// The steps are applied in order to the address of the match, see [`Step`](enum.Step.html).
+/// Address arithmetic wraps around at the pointer width of the target architecture.
+///
+/// Resolvers are parsed with `FromStr` from the steps separated by whitespace and formatted with `Display`, with the `serde` feature they serialize as strings.
+/// Together with the signature pattern this describes a global in an offsets file.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::TargetArch;
+/// use external::scan::{Resolver, Signature};
+/// use external::vm::{AddressRange, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let global = process.map(0x20000, &0x1234u64.to_le_bytes(), Protect::READ_WRITE).unwrap() as u64;
+/// // mov rax, [rip+disp32]
+/// let code = 0x10000 + 0x10;
+/// let disp = (global as i64 - (code + 7)) as i32;
+/// let mut bytes = vec![0x90; 0x10];
+/// bytes.extend_from_slice(&[0x48, 0x8B, 0x05]);
+/// bytes.extend_from_slice(&disp.to_le_bytes());
+/// process.map(0x10000, &bytes, Protect::EXECUTE_READ).unwrap();
+///
+/// let signature: Signature = "48 8B 05 ?? ?? ?? ??".parse().unwrap();
+/// let resolver: Resolver = "rel32(3, 7) deref".parse().unwrap();
+/// assert_eq!(resolver.find(&process, TargetArch::X64, &signature, AddressRange::all()), Ok(0x1234));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub struct Resolver {
+	steps: Vec<Step>,
+}
+impl Resolver {
+	/// Constructs a resolver from its steps.
+	pub fn new(steps: Vec<Step>) -> Resolver {
+		Resolver { steps }
+	}
+	/// Parses the steps separated by whitespace.
+	pub fn parse(s: &str) -> Result<Resolver, PatternError> {
+		let mut steps = Vec::new();
+		let mut start = None;
+		let mut depth = 0;
+		for (i, chr) in s.char_indices().chain(Some((s.len(), ' '))) {
+			match chr {
+				'(' => depth += 1,
+				')' => depth -= 1,
+				_ if chr.is_whitespace() && depth == 0 => {
+					if let Some(start) = start.take() {
+						steps.push(parse_step(&s[start..i]).ok_or(PatternError { position: start, message: "expected a step like `+0x10`, `rel32(3, 7)`, `deref`, `read(u32)` or `cast(i32)`" })?);
+					}
+					continue;
+				},
+				_ => (),
+			}
+			if start.is_none() {
+				start = Some(i);
+			}
+		}
+		if depth != 0 {
+			return Err(PatternError { position: s.len(), message: "unbalanced parentheses" });
+		}
+		Ok(Resolver { steps })
+	}
+	/// The steps of the pipeline.
+	pub fn steps(&self) -> &[Step] {
+		&self.steps
+	}
+	/// Applies the steps to the address.
+	pub fn resolve<V: VirtualMemory + ?Sized>(&self, vm: &V, arch: TargetArch, address: Ptr64) -> Result<u64, ResolverError> {
+		let mask = match arch {
+			TargetArch::X86 => 0xFFFF_FFFF,
+			TargetArch::X64 => u64::MAX,
+		};
+		let mut value = address.into_raw();
+		for (index, &step) in self.steps.iter().enumerate() {
+			let read = |address: u64, size: usize| {
+				let mut bytes = [0u8; 8];
+				match vm.vm_read_bytes(address as usize, &mut bytes[..size]) {
+					Ok(_) => Ok(u64::from_le_bytes(bytes)),
+					Err(error) => Err(ResolverError::Step { index, step, address, error }),
+				}
+			};
+			value = match step {
+				Step::Add(offset) => value.wrapping_add(offset as u64) & mask,
+				Step::Rel32 { disp, len } => {
+					let disp = read(value.wrapping_add(disp) & mask, 4)? as u32 as i32;
+					value.wrapping_add(len).wrapping_add(disp as i64 as u64) & mask
+				},
+				Step::Deref => read(value, arch.pointer_size())?,
+				Step::Read(ty) => ty.cast(read(value, ty.size())?),
+				Step::Cast(ty) => ty.cast(value),
+			};
+		}
+		Ok(value)
+	}
+	/// Finds the first match of the signature in the range and applies the steps to its address.
+	pub fn find<V: VirtualMemory + ?Sized>(&self, vm: &V, arch: TargetArch, signature: &Signature, range: AddressRange) -> Result<u64, ResolverError> {
+		match signature.find(vm, range) {
+			Ok(Some(address)) => self.resolve(vm, arch, address),
+			Ok(None) => Err(ResolverError::NotFound),
+			Err(err) => Err(ResolverError::Scan(err)),
+		}
+	}
+}
+impl From<Vec<Step>> for Resolver {
+	fn from(steps: Vec<Step>) -> Resolver {
+		Resolver { steps }
+	}
+}
+impl str::FromStr for Resolver {
+	type Err = PatternError;
+	fn from_str(s: &str) -> Result<Resolver, PatternError> {
+		Resolver::parse(s)
+	}
+}
+impl fmt::Display for Resolver {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (index, step) in self.steps.iter().enumerate() {
+			if index > 0 {
+				f.write_str(" ")?;
+			}
+			step.fmt(f)?;
+		}
+		Ok(())
+	}
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for Resolver {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Resolver {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Resolver, D::Error> {
+		let s = <std::borrow::Cow<str> as serde::Deserialize>::deserialize(deserializer)?;
+		Resolver::parse(&s).map_err(serde::de::Error::custom)
+	}
+}
+
+fn parse_int(s: &str) -> Option<u64> {
+	let s = s.trim();
+	match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+		Some(hex) => u64::from_str_radix(hex, 16).ok(),
+		None => s.parse().ok(),
+	}
+}
+
+fn parse_step(s: &str) -> Option<Step> {
+	if let Some(offset) = s.strip_prefix('+') {
+		return parse_int(offset).and_then(|offset| i64::try_from(offset).ok()).map(Step::Add);
+	}
+	if let Some(offset) = s.strip_prefix('-') {
+		return parse_int(offset).and_then(|offset| 0i64.checked_sub_unsigned(offset)).map(Step::Add);
+	}
+	if s == "deref" {
+		return Some(Step::Deref);
+	}
+	let (name, args) = s.strip_suffix(')')?.split_once('(')?;
+	match name.trim() {
+		"rel32" => {
+			let (disp, len) = args.split_once(',')?;
+			Some(Step::Rel32 { disp: parse_int(disp)?, len: parse_int(len)? })
+		},
+		"read" => args.trim().parse().ok().map(Step::Read),
+		"cast" => args.trim().parse().ok().map(Step::Cast),
+		_ => None,
+	}
+}
+
+/// Error resolving a signature.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResolverError {
+	/// The signature was not found.
+	NotFound,
+	/// Scanning for the signature failed.
+	Scan(ErrorCode),
+	/// Reading the memory of a step failed.
+	Step { index: usize, step: Step, address: u64, error: ErrorCode },
+}
+impl fmt::Display for ResolverError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ResolverError::NotFound => f.write_str("signature not found"),
+			ResolverError::Scan(err) => write!(f, "scanning for the signature failed: {}", err),
+			ResolverError::Step { index, step, address, error } => write!(f, "step {} `{}` failed to read {:#x}: {}", index, step, address, error),
+		}
+	}
+}
+impl error::Error for ResolverError {}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::error::ERROR_PARTIAL_COPY;
+	use crate::vm::{MockProcess, Protect};
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let resolver: Resolver = " +3 rel32( 1,0x5 )\tderef -0x10 read(i16)  cast(u8) ".parse().unwrap();
+		assert_eq!(resolver.steps(), &[
+			Step::Add(3),
+			Step::Rel32 { disp: 1, len: 5 },
+			Step::Deref,
+			Step::Add(-0x10),
+			Step::Read(IntType::I16),
+			Step::Cast(IntType::U8),
+		]);
+		assert_eq!(resolver.to_string(), "+0x3 rel32(0x1, 0x5) deref -0x10 read(i16) cast(u8)");
+		assert_eq!(resolver.to_string().parse(), Ok(resolver));
+		assert_eq!(Resolver::parse(""), Ok(Resolver::default()));
+		assert_eq!(Resolver::parse("deref dref").map_err(|err| err.position()), Err(6));
+		assert_eq!(Resolver::parse("read(u128)").map_err(|err| err.position()), Err(0));
+		assert_eq!(Resolver::parse("rel32(1, 5").map_err(|err| err.message()), Err("unbalanced parentheses"));
+		assert_eq!(Resolver::parse("-0x8000000000000000"), Ok(Resolver::new(vec![Step::Add(i64::MIN)])));
+	}
+
+	#[test]
+	fn resolve() {
+		let process = MockProcess::new();
+		let code = process.map(0x10000, &[0x90; 0x100], Protect::EXECUTE_READ_WRITE).unwrap() as u64;
+		let data = process.map(0x20000, &[0; 0x100], Protect::READ_WRITE).unwrap() as u64;
+		let poke = |address: u64, bytes: &[u8]| process.poke(address as usize, bytes).unwrap();
+		// call rel32 backwards
+		poke(code + 0x20, &[0xE8]);
+		poke(code + 0x21, &(-0x25i32).to_le_bytes());
+		// mov rcx, [rip+disp32]; mov eax, [rcx+0x1234]
+		poke(code + 0x40, &[0x48, 0x8B, 0x0D]);
+		poke(code + 0x43, &((data + 0x10) as i64 - (code + 0x47) as i64).to_le_bytes()[..4]);
+		poke(code + 0x47, &[0x8B, 0x81, 0x34, 0x12, 0x00, 0x00]);
+		poke(data + 0x10, &(data + 0x80).to_le_bytes());
+		poke(data + 0x80, &(-2i32).to_le_bytes());
+
+		let call: Signature = "E8 ?? ?? ?? ??".parse().unwrap();
+		let resolver: Resolver = "rel32(1, 5)".parse().unwrap();
+		assert_eq!(resolver.find(&process, TargetArch::X64, &call, AddressRange::all()), Ok(code));
+
+		let global: Signature = "48 8B 0D ?? ?? ?? ?? 8B 81".parse().unwrap();
+		let resolver: Resolver = "rel32(3, 7) deref read(i32)".parse().unwrap();
+		assert_eq!(resolver.find(&process, TargetArch::X64, &global, AddressRange::all()), Ok(-2i64 as u64));
+		let resolver: Resolver = "+9 read(u32) cast(u16)".parse().unwrap();
+		assert_eq!(resolver.find(&process, TargetArch::X64, &global, AddressRange::all()), Ok(0x1234));
+
+		// Pointers of 32bit targets are four bytes and addresses wrap at 32 bits
+		let resolver = Resolver::new(vec![Step::Add(0x10 - code as i64 - 0x40 + data as i64), Step::Deref, Step::Add(0x1_0000_0000)]);
+		assert_eq!(resolver.resolve(&process, TargetArch::X86, Ptr64::from(code + 0x40)), Ok(data + 0x80));
+
+		let resolver: Resolver = "rel32(3, 7) deref +0x1000 deref".parse().unwrap();
+		assert_eq!(resolver.find(&process, TargetArch::X64, &global, AddressRange::all()), Err(ResolverError::Step {
+			index: 3,
+			step: Step::Deref,
+			address: data + 0x1080,
+			error: ERROR_PARTIAL_COPY,
+		}));
+		let missing: Signature = "0F 0B".parse().unwrap();
+		assert_eq!(resolver.find(&process, TargetArch::X64, &missing, AddressRange::all()), Err(ResolverError::NotFound));
+	}
+}
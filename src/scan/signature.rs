@@ -0,0 +1,375 @@
+use std::{error, fmt, str};
+use crate::ptr::Ptr64;
+use crate::vm::{AddressRange, MemoryInformation, VirtualMemory};
+use super::chunks::{spans, read_chunks, CHUNK_SIZE};
+use super::{Chunk, ChunkMatcher};
+
+/// Byte signature with wildcards.
+///
+/// Signatures are parsed from IDA-style patterns, bytes in hexadecimal separated by whitespace:
+///
+/// * `8B` matches the byte exactly.
+/// * `??` or `?` matches any byte.
+/// * `4?` and `?8` match the known nibble and any value for the other nibble.
+///
+/// Arbitrary bit masks are supported with [`Signature::new`](#method.new).
+///
+/// Signatures are formatted with `Display` as patterns, with the `serde` feature they serialize as pattern strings.
+/// Masks which are not made of whole nibbles cannot be written as a pattern, `Display` writes their partially masked nibbles as `?` and serializing them fails.
+///
+/// The search skips ahead using the longest run of exact bytes in the signature, signatures with a long run of exact bytes are found faster.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::scan::Signature;
+/// use external::vm::{AddressRange, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let code = [0x90, 0x48, 0x8B, 0x05, 0x78, 0x56, 0x34, 0x12, 0x89, 0xC3];
+/// let base = process.map(0x10000, &code, Protect::EXECUTE_READ).unwrap() as u64;
+///
+/// let signature: Signature = "48 8B 05 ?? ?? ?? ?? 89 C?".parse().unwrap();
+/// assert_eq!(signature.find(&process, AddressRange::all()), Ok(Some(Ptr64::from(base + 1))));
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash)]
+pub struct Signature {
+	// Bytes are stored masked.
+	bytes: Vec<u8>,
+	mask: Vec<u8>,
+	// The longest run of exact bytes and the Horspool skip table for it.
+	anchor: usize,
+	anchor_len: usize,
+	skip: Box<[u32; 256]>,
+}
+impl Signature {
+	/// Constructs a signature from bytes and a bit mask per byte, the bits set in the mask must match.
+	///
+	/// Panics if the signature is empty or the lengths differ.
+	pub fn new(bytes: &[u8], mask: &[u8]) -> Signature {
+		assert!(!bytes.is_empty(), "empty signature");
+		assert_eq!(bytes.len(), mask.len(), "signature and mask lengths differ");
+		let bytes: Vec<u8> = bytes.iter().zip(mask).map(|(&byte, &mask)| byte & mask).collect();
+		// Find the longest run of exact bytes
+		let (mut anchor, mut anchor_len, mut run) = (0, 0, 0);
+		for (i, &mask) in mask.iter().enumerate() {
+			run = if mask == 0xFF { run + 1 } else { 0 };
+			if run > anchor_len {
+				anchor = i + 1 - run;
+				anchor_len = run;
+			}
+		}
+		let mut skip = Box::new([anchor_len as u32; 256]);
+		if anchor_len > 0 {
+			for (i, &byte) in bytes[anchor..anchor + anchor_len - 1].iter().enumerate() {
+				skip[byte as usize] = (anchor_len - 1 - i) as u32;
+			}
+		}
+		Signature { bytes, mask: mask.to_vec(), anchor, anchor_len, skip }
+	}
+	/// Parses an IDA-style pattern.
+	pub fn parse(pattern: &str) -> Result<Signature, PatternError> {
+		let mut bytes = Vec::new();
+		let mut mask = Vec::new();
+		for token in pattern.split_ascii_whitespace() {
+			let position = token.as_ptr() as usize - pattern.as_ptr() as usize;
+			let (byte, byte_mask) = match token.as_bytes() {
+				b"?" | b"??" => (0, 0),
+				&[hi, lo] => match (nibble(hi), nibble(lo)) {
+					(Some((hi, hi_mask)), Some((lo, lo_mask))) => (hi << 4 | lo, hi_mask << 4 | lo_mask),
+					_ => return Err(PatternError { position, message: "expected a byte like `8B`, `4?` or `??`" }),
+				},
+				_ => return Err(PatternError { position, message: "expected a byte like `8B`, `4?` or `??`" }),
+			};
+			bytes.push(byte);
+			mask.push(byte_mask);
+		}
+		if bytes.is_empty() {
+			return Err(PatternError { position: 0, message: "empty signature" });
+		}
+		Ok(Signature::new(&bytes, &mask))
+	}
+	/// The length of the signature, in bytes.
+	pub fn len(&self) -> usize {
+		self.bytes.len()
+	}
+	/// Signatures are never empty.
+	pub fn is_empty(&self) -> bool {
+		false
+	}
+	/// The bytes of the signature, bits not in the mask are zero.
+	pub fn bytes(&self) -> &[u8] {
+		&self.bytes
+	}
+	/// The bit mask of each byte.
+	pub fn mask(&self) -> &[u8] {
+		&self.mask
+	}
+	/// Returns if the mask of every nibble is either set or clear, the signature can then be written as a pattern.
+	pub fn is_pattern(&self) -> bool {
+		self.mask.iter().all(|&mask| matches!(mask >> 4, 0x0 | 0xF) && matches!(mask & 0xF, 0x0 | 0xF))
+	}
+	/// Returns if the bytes match the signature, the length must be equal.
+	pub fn is_match(&self, bytes: &[u8]) -> bool {
+		bytes.len() == self.bytes.len() && bytes.iter().zip(&self.mask).zip(&self.bytes).all(|((&byte, &mask), &expected)| byte & mask == expected)
+	}
+	/// Iterates over the offsets of the matches in the haystack, matches may overlap.
+	pub fn matches<'a>(&'a self, haystack: &'a [u8]) -> Matches<'a> {
+		Matches { signature: self, haystack, position: 0 }
+	}
+	/// Finds the first match in the range.
+	pub fn find<V: VirtualMemory + ?Sized>(&self, vm: &V, range: AddressRange) -> crate::Result<Option<Ptr64>> {
+		let mut found = None;
+		self.scan(vm, range, |address| {
+			found = Some(address);
+			false
+		})?;
+		Ok(found)
+	}
+	/// Finds all the matches in the range, in order of their address.
+	pub fn find_all<V: VirtualMemory + ?Sized>(&self, vm: &V, range: AddressRange) -> crate::Result<Vec<Ptr64>> {
+		let mut found = Vec::new();
+		self.scan(vm, range, |address| {
+			found.push(address);
+			true
+		})?;
+		Ok(found)
+	}
+	/// Scans the range, calling the closure with each match in order until it returns false.
+	pub fn scan<V, F>(&self, vm: &V, range: AddressRange, mut f: F) -> crate::Result<()>
+		where V: VirtualMemory + ?Sized, F: FnMut(Ptr64) -> bool
+	{
+		let mut buffer = Vec::with_capacity(CHUNK_SIZE + self.len() - 1);
+		for span in spans(vm, range, MemoryInformation::is_readable)? {
+			let done = read_chunks(vm, span, self.len() - 1, &mut buffer, |address, bytes, len| {
+				// Matches starting in the overlap are found in the next chunk
+				self.matches(bytes)
+					.take_while(|&offset| offset < len)
+					.all(|offset| f(Ptr64::from(address + offset as u64)))
+			})?;
+			if !done {
+				break;
+			}
+		}
+		Ok(())
+	}
+}
+/// Finds the address of each match with a [`ScanEngine`](struct.ScanEngine.html).
+impl ChunkMatcher for Signature {
+	type Match = Ptr64;
+	fn overlap(&self) -> usize {
+		self.len() - 1
+	}
+	fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<Ptr64>) {
+		let address = chunk.address + chunk.range.start as u64;
+		let found = self.matches(&chunk.bytes[chunk.range.start..])
+			.take_while(|&offset| offset < chunk.range.len())
+			.map(|offset| Ptr64::from(address + offset as u64));
+		matches.extend(found);
+	}
+}
+impl str::FromStr for Signature {
+	type Err = PatternError;
+	fn from_str(s: &str) -> Result<Signature, PatternError> {
+		Signature::parse(s)
+	}
+}
+impl fmt::Display for Signature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		for (index, (&byte, &mask)) in self.bytes.iter().zip(&self.mask).enumerate() {
+			if index > 0 {
+				f.write_str(" ")?;
+			}
+			for shift in [4, 0] {
+				if (mask >> shift) & 0xF == 0xF {
+					write!(f, "{:X}", (byte >> shift) & 0xF)?;
+				}
+				else {
+					f.write_str("?")?;
+				}
+			}
+		}
+		Ok(())
+	}
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for Signature {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		if !self.is_pattern() {
+			return Err(serde::ser::Error::custom("signature mask is not made of whole nibbles"));
+		}
+		serializer.collect_str(self)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Signature {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Signature, D::Error> {
+		let s = <std::borrow::Cow<str> as serde::Deserialize>::deserialize(deserializer)?;
+		Signature::parse(&s).map_err(serde::de::Error::custom)
+	}
+}
+impl fmt::Debug for Signature {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Signature")
+			.field("bytes", &format_args!("{:02X?}", self.bytes))
+			.field("mask", &format_args!("{:02X?}", self.mask))
+			.finish()
+	}
+}
+
+// The value and mask of a pattern nibble.
+fn nibble(c: u8) -> Option<(u8, u8)> {
+	match c {
+		b'?' => Some((0, 0)),
+		_ => (c as char).to_digit(16).map(|value| (value as u8, 0xF)),
+	}
+}
+
+/// Iterator over the matches of a signature in a slice.
+#[derive(Clone, Debug)]
+pub struct Matches<'a> {
+	signature: &'a Signature,
+	haystack: &'a [u8],
+	position: usize,
+}
+impl<'a> Iterator for Matches<'a> {
+	type Item = usize;
+	fn next(&mut self) -> Option<usize> {
+		let sig = self.signature;
+		let len = sig.bytes.len();
+		while self.haystack.len() - self.position >= len {
+			let start = self.position;
+			if sig.anchor_len == 0 {
+				self.position += 1;
+				if sig.is_match(&self.haystack[start..start + len]) {
+					return Some(start);
+				}
+				continue;
+			}
+			// Horspool search for the anchor, the rest of the signature is checked when the anchor matches
+			let anchor = &self.haystack[start + sig.anchor..start + sig.anchor + sig.anchor_len];
+			if anchor == &sig.bytes[sig.anchor..sig.anchor + sig.anchor_len] && sig.is_match(&self.haystack[start..start + len]) {
+				self.position = start + 1;
+				return Some(start);
+			}
+			self.position = start + sig.skip[anchor[sig.anchor_len - 1] as usize] as usize;
+		}
+		self.position = self.haystack.len();
+		None
+	}
+}
+
+/// Signature or resolver pattern syntax error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PatternError {
+	pub(super) position: usize,
+	pub(super) message: &'static str,
+}
+impl PatternError {
+	/// The byte offset into the pattern where the error was found.
+	pub fn position(&self) -> usize {
+		self.position
+	}
+	/// Description of the error.
+	pub fn message(&self) -> &'static str {
+		self.message
+	}
+}
+impl fmt::Display for PatternError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at position {}", self.message, self.position)
+	}
+}
+impl error::Error for PatternError {}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::vm::{MockProcess, Protect};
+	use super::super::chunks::CHUNK_SIZE;
+	use super::super::{ScanControl, ScanEngine};
+	use super::*;
+
+	#[test]
+	fn display() {
+		let signature = Signature::parse(" 48 8b\t?? 4? ?C ? ").unwrap();
+		assert_eq!(signature.to_string(), "48 8B ?? 4? ?C ??");
+		assert_eq!(signature.to_string().parse(), Ok(signature));
+		assert!(Signature::parse("?? 4?").unwrap().is_pattern());
+		let bits = Signature::new(&[0x48, 0x80], &[0xFF, 0x80]);
+		assert!(!bits.is_pattern());
+		assert_eq!(bits.to_string(), "48 ??");
+	}
+
+	#[test]
+	fn parse() {
+		let signature = Signature::parse(" 48 8b\t?? 4? ?C ? ").unwrap();
+		assert_eq!(signature.bytes(), &[0x48, 0x8B, 0, 0x40, 0x0C, 0]);
+		assert_eq!(signature.mask(), &[0xFF, 0xFF, 0, 0xF0, 0x0F, 0]);
+		assert_eq!(Signature::parse("48 8G").map_err(|err| err.position()), Err(3));
+		assert_eq!(Signature::parse("48 123").map_err(|err| err.position()), Err(3));
+		assert_eq!(Signature::parse("  ").map_err(|err| err.message()), Err("empty signature"));
+	}
+
+	#[test]
+	fn matches() {
+		let haystack = [0xAA, 0x48, 0x8B, 0x48, 0x8B, 0x01, 0x48, 0x8B, 0x48, 0x8B, 0xF1];
+		let signature = Signature::parse("48 8B ?? ?? F?").unwrap();
+		assert_eq!(signature.matches(&haystack).collect::<Vec<_>>(), [6]);
+		let signature = Signature::parse("48 8B").unwrap();
+		assert_eq!(signature.matches(&haystack).collect::<Vec<_>>(), [1, 3, 6, 8]);
+		let signature = Signature::parse("8B 4? 8B").unwrap();
+		assert_eq!(signature.matches(&haystack).collect::<Vec<_>>(), [2, 7]);
+		let signature = Signature::parse("?? 4?").unwrap();
+		assert_eq!(signature.matches(&haystack).collect::<Vec<_>>(), [0, 2, 5, 7]);
+		let signature = Signature::new(&[0x01], &[0x01]);
+		assert_eq!(signature.matches(&haystack).collect::<Vec<_>>(), [2, 4, 5, 7, 9, 10]);
+		assert_eq!(signature.matches(&[]).next(), None);
+	}
+
+	#[test]
+	fn scan() {
+		let process = MockProcess::new();
+		let mut memory = vec![0u8; CHUNK_SIZE * 2];
+		// Across the chunk boundary
+		memory[CHUNK_SIZE - 2..CHUNK_SIZE + 2].copy_from_slice(&[0xE8, 0x11, 0x22, 0xC3]);
+		// At the very end
+		memory[CHUNK_SIZE * 2 - 4..].copy_from_slice(&[0xE8, 0x33, 0x44, 0xC3]);
+		let base = process.map(0x100000, &memory, Protect::READ_WRITE).unwrap() as u64;
+		// Across adjacent regions with different protection
+		process.vm_protect(base as usize + CHUNK_SIZE, 0x1000, Protect::READ_ONLY).unwrap();
+		// Unreadable memory is skipped
+		let hidden = process.map(0x200000, &[0xE8, 0x55, 0x66, 0xC3], Protect::NO_ACCESS).unwrap();
+
+		let signature = Signature::parse("E8 ?? ?? C3").unwrap();
+		let expected = [Ptr64::from(base + CHUNK_SIZE as u64 - 2), Ptr64::from(base + CHUNK_SIZE as u64 * 2 - 4)];
+		assert_eq!(signature.find_all(&process, AddressRange::all()), Ok(expected.to_vec()));
+		assert_eq!(signature.find(&process, AddressRange::all()), Ok(Some(expected[0])));
+		let range = AddressRange::new(Ptr64::from(expected[0].into_raw() + 1), Ptr64::from(u64::MAX));
+		assert_eq!(signature.find(&process, range), Ok(Some(expected[1])));
+		let range = AddressRange::from_len(Ptr64::from(base), CHUNK_SIZE as u64);
+		assert_eq!(signature.find(&process, range), Ok(None));
+		let engine = ScanEngine { threads: 4, chunk_size: 0x1000 };
+		let found = engine.scan(&process, AddressRange::all(), MemoryInformation::is_readable, &signature, &ScanControl::new());
+		assert_eq!(found, Ok(expected.to_vec()));
+
+		process.vm_protect(hidden, 0x1000, Protect::READ_ONLY).unwrap();
+		assert_eq!(signature.find_all(&process, AddressRange::all()).map(|found| found.len()), Ok(3));
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn scan_module() {
+		static NEEDLE: [u8; 12] = [0x13, 0x37, 0xC0, 0xDE, 0x5C, 0xA1, 0x10, 0xAB, 0x1E, 0x00, 0xFF, 0x7E];
+		let process = crate::process::Process::current();
+		let exe = std::env::current_exe().unwrap();
+		let module = crate::module::EnumModules::create(process.pid().unwrap()).unwrap()
+			.find(|module| Some(module.name().as_os_str()) == exe.file_name())
+			.unwrap();
+		let signature = Signature::parse("13 37 C0 DE 5C A1 1? AB ?? 00 FF 7E").unwrap();
+		let found = signature.find(&process, AddressRange::from(&module)).unwrap();
+		assert_eq!(found, Some(Ptr64::from(NEEDLE.as_ptr() as u64)));
+	}
+}
@@ -0,0 +1,385 @@
+use std::{mem, str};
+use crate::ptr::Ptr64;
+use crate::vm::{AddressRange, AddressSpaceMap, MemoryType, VirtualMemory};
+use crate::Result;
+use super::chunks::read_chunks;
+use super::{Chunk, ChunkMatcher};
+
+/// Encoding of an extracted string.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringEncoding {
+	/// ASCII or UTF-8.
+	Utf8,
+	/// UTF-16 little endian limited to Latin-1, aligned to two bytes.
+	Utf16Le,
+}
+
+/// String found in memory.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FoundString {
+	pub address: Ptr64,
+	pub encoding: StringEncoding,
+	pub text: String,
+	/// The region containing the start of the string, `None` when extracted from a buffer.
+	pub region: Option<AddressRange>,
+	/// The module containing the start of the string.
+	pub module: Option<String>,
+}
+
+/// Extracts strings from bytes fed in order, like the `strings` utility.
+///
+/// Finds runs of printable characters encoded as UTF-8 or as UTF-16LE with at least the minimum number of characters.
+/// Strings continue across calls to [`feed`](#method.feed) as long as the bytes are contiguous.
+///
+/// Like `strings -el` the UTF-16 strings are limited to Latin-1 characters, other code units are too likely to be binary data.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::scan::{StringEncoding, StringExtractor};
+///
+/// let mut extractor = StringExtractor::new(4);
+/// extractor.feed(0x1000, b"\x00\x01hello wo");
+/// extractor.feed(0x100A, b"rld\x00\x00\x00W\x00i\x00d\x00e\x00\x00\x00ab\x00");
+/// let strings = extractor.finish();
+/// assert_eq!(strings.len(), 2);
+/// assert_eq!((strings[0].address, strings[0].encoding, &*strings[0].text), (Ptr64::from(0x1002), StringEncoding::Utf8, "hello world"));
+/// assert_eq!((strings[1].address, strings[1].encoding, &*strings[1].text), (Ptr64::from(0x1010), StringEncoding::Utf16Le, "Wide"));
+/// ```
+#[derive(Clone, Debug)]
+pub struct StringExtractor {
+	min_len: usize,
+	next: u64,
+	utf8: Run,
+	utf16: Run,
+	// Incomplete UTF-8 sequence and its expected length.
+	utf8_partial: Vec<u8>,
+	utf8_width: usize,
+	// The low byte of a UTF-16 code unit.
+	utf16_low: Option<u8>,
+	strings: Vec<FoundString>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Run {
+	start: u64,
+	text: String,
+	len: usize,
+}
+impl Run {
+	fn push(&mut self, address: u64, chr: char) {
+		if self.len == 0 {
+			self.start = address;
+		}
+		self.text.push(chr);
+		self.len += 1;
+	}
+	fn end(&mut self, min_len: usize, encoding: StringEncoding, strings: &mut Vec<FoundString>) {
+		if self.len >= min_len {
+			let text = mem::take(&mut self.text);
+			strings.push(FoundString { address: Ptr64::from(self.start), encoding, text, region: None, module: None });
+		}
+		self.text.clear();
+		self.len = 0;
+	}
+}
+
+impl StringExtractor {
+	/// Constructs an extractor for strings of at least the minimum number of characters.
+	pub fn new(min_len: usize) -> StringExtractor {
+		StringExtractor {
+			min_len: usize::max(min_len, 1),
+			next: 0,
+			utf8: Run::default(),
+			utf16: Run::default(),
+			utf8_partial: Vec::new(),
+			utf8_width: 0,
+			utf16_low: None,
+			strings: Vec::new(),
+		}
+	}
+	/// Feeds the bytes at the address, strings end at a gap between the previous bytes and these.
+	pub fn feed(&mut self, address: u64, bytes: &[u8]) {
+		if address != self.next {
+			self.flush();
+		}
+		for (i, &byte) in bytes.iter().enumerate() {
+			let address = address + i as u64;
+			self.feed_utf8(address, byte);
+			self.feed_utf16(address, byte);
+		}
+		self.next = address + bytes.len() as u64;
+	}
+	/// Ends the strings in progress and returns the strings found, in order of their address.
+	pub fn finish(&mut self) -> Vec<FoundString> {
+		self.flush();
+		let mut strings = mem::take(&mut self.strings);
+		strings.sort_by_key(|string| string.address);
+		strings
+	}
+	fn flush(&mut self) {
+		self.utf8_partial.clear();
+		self.utf16_low = None;
+		self.utf8.end(self.min_len, StringEncoding::Utf8, &mut self.strings);
+		self.utf16.end(self.min_len, StringEncoding::Utf16Le, &mut self.strings);
+	}
+	fn feed_utf8(&mut self, address: u64, byte: u8) {
+		if !self.utf8_partial.is_empty() {
+			if byte & 0xC0 == 0x80 {
+				self.utf8_partial.push(byte);
+				if self.utf8_partial.len() == self.utf8_width {
+					let start = address + 1 - self.utf8_width as u64;
+					match str::from_utf8(&self.utf8_partial).ok().and_then(|s| s.chars().next()) {
+						Some(chr) if is_printable(chr) => self.utf8.push(start, chr),
+						_ => self.utf8.end(self.min_len, StringEncoding::Utf8, &mut self.strings),
+					}
+					self.utf8_partial.clear();
+				}
+				return;
+			}
+			// The sequence is cut short, the byte may start a new one
+			self.utf8_partial.clear();
+			self.utf8.end(self.min_len, StringEncoding::Utf8, &mut self.strings);
+		}
+		match byte {
+			0x00..=0x7F if is_printable(byte as char) => self.utf8.push(address, byte as char),
+			0xC2..=0xF4 => {
+				self.utf8_width = if byte < 0xE0 { 2 } else if byte < 0xF0 { 3 } else { 4 };
+				self.utf8_partial.push(byte);
+			},
+			_ => self.utf8.end(self.min_len, StringEncoding::Utf8, &mut self.strings),
+		}
+	}
+	fn feed_utf16(&mut self, address: u64, byte: u8) {
+		if address.is_multiple_of(2) {
+			self.utf16_low = Some(byte);
+			return;
+		}
+		match self.utf16_low.take() {
+			Some(low) if byte == 0 && is_printable(low as char) => self.utf16.push(address - 1, low as char),
+			Some(_) => self.utf16.end(self.min_len, StringEncoding::Utf16Le, &mut self.strings),
+			None => (),
+		}
+	}
+}
+
+fn is_printable(chr: char) -> bool {
+	chr == '\t' || !chr.is_control()
+}
+
+/// Extracts the strings from a buffer, the addresses are offsets into the buffer.
+pub fn extract_strings(bytes: &[u8], min_len: usize) -> Vec<FoundString> {
+	let mut extractor = StringExtractor::new(min_len);
+	extractor.feed(0, bytes);
+	extractor.finish()
+}
+
+/// Extracts strings with a [`ScanEngine`](struct.ScanEngine.html).
+///
+/// Each chunk is read with the maximum length of a string past its end, longer strings are cut off after `max_len` characters.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringMatcher {
+	/// Minimum number of characters of a string.
+	pub min_len: usize,
+	/// Maximum number of characters of a string.
+	pub max_len: usize,
+}
+impl Default for StringMatcher {
+	fn default() -> StringMatcher {
+		StringMatcher { min_len: 4, max_len: 0x100 }
+	}
+}
+impl ChunkMatcher for StringMatcher {
+	type Match = FoundString;
+	fn overlap(&self) -> usize {
+		// Characters take up to four bytes
+		self.max_len.saturating_mul(4)
+	}
+	fn lookbehind(&self) -> usize {
+		// Enough to see a string started before the chunk, even in the middle of a character
+		4
+	}
+	fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<FoundString>) {
+		let mut extractor = StringExtractor::new(self.min_len);
+		extractor.feed(chunk.address, chunk.bytes);
+		let start = chunk.address + chunk.range.start as u64;
+		let end = chunk.address + chunk.range.end as u64;
+		for mut string in extractor.finish() {
+			let address = string.address.into_raw();
+			if start <= address && address < end {
+				if let Some((index, _)) = string.text.char_indices().nth(self.max_len) {
+					string.text.truncate(index);
+				}
+				matches.push(string);
+			}
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Options of a string scan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StringScanOptions {
+	/// Minimum number of characters of a string.
+	pub min_len: usize,
+	/// Only scan private memory, skipping images and mapped files.
+	pub private_only: bool,
+}
+impl Default for StringScanOptions {
+	fn default() -> StringScanOptions {
+		StringScanOptions { min_len: 4, private_only: false }
+	}
+}
+
+/// Extracts the strings from the readable regions of the address space map in the range.
+///
+/// The strings are annotated with their region and module, only strings accepted by the filter are kept.
+/// To scan a single module pass its range, eg. from [`AddressSpaceMap::module_by_name`](../vm/struct.AddressSpaceMap.html#method.module_by_name).
+///
+/// The filter receives the text of each string, any matcher can be plugged in, eg. a regular expression with `|text| regex.is_match(text)`.
+///
+/// # Examples
+///
+/// ```
+/// use external::scan::{scan_strings, StringScanOptions};
+/// use external::vm::{AddressRange, AddressSpaceMap, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, b"\x00https://example.com\x00token=1234\x00", Protect::READ_ONLY).unwrap();
+/// let space = AddressSpaceMap::from_vm(&process).unwrap();
+///
+/// let options = StringScanOptions::default();
+/// let urls = scan_strings(&process, &space, AddressRange::all(), &options, |text| text.starts_with("http")).unwrap();
+/// assert_eq!(urls.len(), 1);
+/// assert_eq!(urls[0].address.into_raw(), base as u64 + 1);
+/// assert_eq!(urls[0].text, "https://example.com");
+/// ```
+pub fn scan_strings<V, F>(vm: &V, space: &AddressSpaceMap, range: AddressRange, options: &StringScanOptions, mut filter: F) -> Result<Vec<FoundString>>
+	where V: VirtualMemory + ?Sized, F: FnMut(&str) -> bool
+{
+	let mut extractor = StringExtractor::new(options.min_len);
+	let mut buffer = Vec::new();
+	let mut strings = Vec::new();
+	for region in space.regions() {
+		if !region.is_readable() || (options.private_only && region.mem_type != MemoryType::PRIVATE) {
+			continue;
+		}
+		if let Some(span) = AddressRange::from(region).intersect(range) {
+			read_chunks(vm, span, 0, &mut buffer, |address, bytes, _| {
+				extractor.feed(address, bytes);
+				true
+			})?;
+		}
+	}
+	for mut string in extractor.finish() {
+		if filter(&string.text) {
+			string.region = space.region(string.address).map(AddressRange::from);
+			string.module = space.module(string.address).map(|module| module.name.clone());
+			strings.push(string);
+		}
+	}
+	Ok(strings)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::vm::{MemoryInformation, MockProcess, Protect};
+	use super::super::{ScanControl, ScanEngine};
+	use super::*;
+
+	fn texts(strings: &[FoundString]) -> Vec<(u64, StringEncoding, &str)> {
+		strings.iter().map(|string| (string.address.into_raw(), string.encoding, &*string.text)).collect()
+	}
+
+	#[test]
+	fn extract() {
+		let mut bytes = b"\x01abc\x00abcd\x00caf\xC3\xA9 \xE2\x82\xAC\xF0\x9F\x98\x80\x00bad\xC3(\x00\x00\x00".to_vec();
+		let wide_start = bytes.len() as u64;
+		for unit in "\u{e9}t\u{e9} caf\u{e9}".encode_utf16() {
+			bytes.extend_from_slice(&unit.to_le_bytes());
+		}
+		bytes.extend_from_slice(&[0, 0, 0xFF]);
+		assert_eq!(texts(&extract_strings(&bytes, 4)), [
+			(5, StringEncoding::Utf8, "abcd"),
+			(10, StringEncoding::Utf8, "café €😀"),
+			(wide_start, StringEncoding::Utf16Le, "été café"),
+		]);
+		assert_eq!(texts(&extract_strings(&bytes, 3)).len(), 5);
+		// Odd addresses split the UTF-16 code units differently
+		let mut extractor = StringExtractor::new(4);
+		extractor.feed(1, &bytes);
+		assert_eq!(texts(&extractor.finish()).iter().filter(|(_, encoding, _)| *encoding == StringEncoding::Utf16Le).count(), 0);
+	}
+
+	#[test]
+	fn feed() {
+		let mut extractor = StringExtractor::new(4);
+		extractor.feed(0x100, b"ab\xE2\x82");
+		extractor.feed(0x104, b"\xACcd");
+		extractor.feed(0x200, b"efgh");
+		assert_eq!(texts(&extractor.finish()), [(0x100, StringEncoding::Utf8, "ab€cd"), (0x200, StringEncoding::Utf8, "efgh")]);
+	}
+
+	#[test]
+	fn scan() {
+		let process = MockProcess::new();
+		let image = process.map_type(0x10000, &[0; 0x1000], Protect::READ_ONLY, MemoryType::IMAGE).unwrap();
+		let heap = process.map(0x20000, &[0; 0x2000], Protect::READ_WRITE).unwrap();
+		process.poke(image + 0x10, b"kernel32.dll").unwrap();
+		// Continues into the next region
+		process.poke(heap + 0xFFC, b"password").unwrap();
+		process.vm_protect(heap + 0x1000, 0x1000, Protect::READ_ONLY).unwrap();
+
+		let mut space = AddressSpaceMap::from_vm(&process).unwrap();
+		space.insert_module("game.exe", AddressRange::from_len(Ptr64::from(image as u64), 0x1000));
+		let mut options = StringScanOptions::default();
+		let strings = scan_strings(&process, &space, AddressRange::all(), &options, |_| true).unwrap();
+		assert_eq!(texts(&strings), [
+			(image as u64 + 0x10, StringEncoding::Utf8, "kernel32.dll"),
+			(heap as u64 + 0xFFC, StringEncoding::Utf8, "password"),
+		]);
+		assert_eq!(strings[0].module.as_deref(), Some("game.exe"));
+		assert_eq!(strings[1].region, Some(AddressRange::from_len(Ptr64::from(heap as u64), 0x1000)));
+		assert_eq!(strings[1].module, None);
+
+		let module = space.module_by_name("game.exe").unwrap().range;
+		assert_eq!(scan_strings(&process, &space, module, &options, |_| true).unwrap().len(), 1);
+		assert_eq!(scan_strings(&process, &space, AddressRange::all(), &options, |text| text.contains("pass")).unwrap().len(), 1);
+		options.private_only = true;
+		assert_eq!(texts(&scan_strings(&process, &space, AddressRange::all(), &options, |_| true).unwrap()), [
+			(heap as u64 + 0xFFC, StringEncoding::Utf8, "password"),
+		]);
+	}
+
+	#[test]
+	fn engine() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x3000], Protect::READ_WRITE).unwrap();
+		process.poke(base + 0xFFC, b"password").unwrap();
+		process.poke(base + 0x1FFE, "€€€€".as_bytes()).unwrap();
+		process.poke(base + 0x2100, &b"0123456789".repeat(4)).unwrap();
+		process.poke(base + 0x27FE, &[b'W', 0, b'i', 0, b'd', 0, b'e', 0]).unwrap();
+		let space = AddressSpaceMap::from_vm(&process).unwrap();
+		let expected = scan_strings(&process, &space, AddressRange::all(), &StringScanOptions::default(), |_| true).unwrap();
+		assert_eq!(expected.len(), 4);
+
+		let control = ScanControl::new();
+		let matcher = StringMatcher { min_len: 4, max_len: 0x100 };
+		for chunk_size in [0x800, 0x1000, 0x10000] {
+			let engine = ScanEngine { threads: 2, chunk_size };
+			let strings = engine.scan(&process, AddressRange::all(), MemoryInformation::is_readable, &matcher, &control).unwrap();
+			assert_eq!(texts(&strings), texts(&expected));
+		}
+		let matcher = StringMatcher { min_len: 4, max_len: 12 };
+		let strings = ScanEngine::default().scan(&process, AddressRange::all(), MemoryInformation::is_readable, &matcher, &control).unwrap();
+		assert_eq!(strings[2].text, "012345678901");
+	}
+}
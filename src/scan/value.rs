@@ -0,0 +1,584 @@
+use std::{cmp, fmt, mem, ptr};
+use std::marker::PhantomData;
+use crate::ptr::{Pod, Ptr64};
+use crate::vm::{AddressRange, MemoryInformation, VirtualMemory};
+use crate::Result;
+use super::chunks::{read_chunks, spans, CHUNK_SIZE};
+use super::{Chunk, ChunkMatcher, ScanControl, ScanEngine};
+
+/// Condition of a first scan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FirstScan<T> {
+	/// The value equals the given value.
+	Exact(T),
+	/// The value lies in the inclusive range.
+	Between(T, T),
+	/// Any value, only its address is known.
+	Unknown,
+}
+impl<T: PartialOrd> FirstScan<T> {
+	fn is_match(&self, value: &T) -> bool {
+		match self {
+			FirstScan::Exact(expected) => value == expected,
+			FirstScan::Between(low, high) => low <= value && value <= high,
+			FirstScan::Unknown => true,
+		}
+	}
+}
+
+/// Condition of a next scan, comparing the value against the value of the previous scan.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum NextScan<T> {
+	/// The value equals the given value.
+	Exact(T),
+	/// The value lies in the inclusive range.
+	Between(T, T),
+	/// The bytes of the value changed.
+	Changed,
+	/// The bytes of the value did not change.
+	Unchanged,
+	/// The value is greater than before.
+	Increased,
+	/// The value is less than before.
+	Decreased,
+}
+impl<T: PartialOrd> NextScan<T> {
+	fn is_match(&self, old_bytes: &[u8], new_bytes: &[u8], old: &T, new: &T) -> bool {
+		match self {
+			NextScan::Exact(expected) => new == expected,
+			NextScan::Between(low, high) => low <= new && new <= high,
+			NextScan::Changed => old_bytes != new_bytes,
+			NextScan::Unchanged => old_bytes == new_bytes,
+			NextScan::Increased => new > old,
+			NextScan::Decreased => new < old,
+		}
+	}
+}
+
+/// Value scanner narrowing down the addresses of a value over multiple scans.
+///
+/// The first scan searches the writable memory for values of type `T`, each next scan keeps the candidates which still match.
+///
+/// The candidates of a region are stored as a bitmap next to a snapshot of the region's bytes while they are dense,
+/// and as a sorted list of their slots and values once that takes less space.
+/// Regions without candidates are dropped, scans read a chunk at a time and next scans only read the chunks which contain candidates.
+///
+/// Values are expected at multiples of their size from the start of each region, see [`with_alignment`](#method.with_alignment) to change this.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::scan::{FirstScan, NextScan, ValueScanner};
+/// use external::vm::{AddressRange, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap();
+/// process.poke(base + 0x10, &100i32.to_ne_bytes()).unwrap();
+/// process.poke(base + 0x20, &100i32.to_ne_bytes()).unwrap();
+///
+/// let mut scanner = ValueScanner::<i32>::new();
+/// assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Exact(100)), Ok(2));
+///
+/// process.poke(base + 0x20, &90i32.to_ne_bytes()).unwrap();
+/// assert_eq!(scanner.next_scan(&process, NextScan::Decreased), Ok(1));
+/// assert_eq!(scanner.results().collect::<Vec<_>>(), [(Ptr64::from(base as u64 + 0x20), 90)]);
+/// ```
+pub struct ValueScanner<T> {
+	alignment: usize,
+	regions: Vec<Region>,
+	count: usize,
+	_marker: PhantomData<fn() -> T>,
+}
+
+// Candidates of a region in order of their slot, slots are `alignment` bytes apart.
+enum Candidates {
+	// One bit for every slot and the bytes of the region, only the bytes of the candidates are kept.
+	Dense { hits: Vec<u64>, snapshot: Vec<u8> },
+	// The slots and their values, `size` bytes each.
+	Sparse { slots: Vec<usize>, values: Vec<u8> },
+}
+
+struct Region {
+	base: u64,
+	len: usize,
+	size: usize,
+	alignment: usize,
+	count: usize,
+	candidates: Candidates,
+}
+impl Region {
+	fn new(base: u64, len: usize, size: usize, alignment: usize) -> Region {
+		let candidates = Candidates::Sparse { slots: Vec::new(), values: Vec::new() };
+		Region { base, len, size, alignment, count: 0, candidates }
+	}
+	fn dense_size(&self) -> usize {
+		slot_count(self.len, self.size, self.alignment).div_ceil(64) * 8 + self.len
+	}
+	fn sparse_size(&self, count: usize) -> usize {
+		count * (mem::size_of::<usize>() + self.size)
+	}
+	// Adds a candidate after the existing ones, switching to dense candidates once they take less space.
+	fn push(&mut self, slot: usize, value: &[u8]) {
+		if matches!(self.candidates, Candidates::Sparse { .. }) && self.sparse_size(self.count + 1) > self.dense_size() {
+			let hits = vec![0u64; slot_count(self.len, self.size, self.alignment).div_ceil(64)];
+			let dense = Candidates::Dense { hits, snapshot: vec![0u8; self.len] };
+			if let Candidates::Sparse { slots, values } = mem::replace(&mut self.candidates, dense) {
+				for (&slot, value) in slots.iter().zip(values.chunks_exact(self.size)) {
+					self.insert(slot, value);
+				}
+			}
+		}
+		self.insert(slot, value);
+		self.count += 1;
+	}
+	fn insert(&mut self, slot: usize, value: &[u8]) {
+		match &mut self.candidates {
+			Candidates::Dense { hits, snapshot } => {
+				hits[slot / 64] |= 1 << (slot % 64);
+				snapshot[slot * self.alignment..slot * self.alignment + self.size].copy_from_slice(value);
+			},
+			Candidates::Sparse { slots, values } => {
+				slots.push(slot);
+				values.extend_from_slice(value);
+			},
+		}
+	}
+	// Switches to sparse candidates if they take less space, once the region has been scanned.
+	fn compact(&mut self) {
+		if matches!(self.candidates, Candidates::Dense { .. }) && self.sparse_size(self.count) < self.dense_size() {
+			let mut slots = Vec::with_capacity(self.count);
+			let mut values = Vec::with_capacity(self.count * self.size);
+			for (slot, value) in self.iter() {
+				slots.push(slot);
+				values.extend_from_slice(value);
+			}
+			self.candidates = Candidates::Sparse { slots, values };
+		}
+	}
+	fn iter(&self) -> CandidateIter<'_> {
+		CandidateIter { region: self, next: 0 }
+	}
+}
+
+// Iterates over the slots and the values of the candidates of a region.
+struct CandidateIter<'a> {
+	region: &'a Region,
+	next: usize,
+}
+impl<'a> Iterator for CandidateIter<'a> {
+	type Item = (usize, &'a [u8]);
+	fn next(&mut self) -> Option<(usize, &'a [u8])> {
+		let size = self.region.size;
+		match &self.region.candidates {
+			Candidates::Dense { hits, snapshot } => {
+				let slot = Bits { bits: hits, next: self.next }.next()?;
+				self.next = slot + 1;
+				let offset = slot * self.region.alignment;
+				Some((slot, &snapshot[offset..offset + size]))
+			},
+			Candidates::Sparse { slots, values } => {
+				let slot = *slots.get(self.next)?;
+				let value = &values[self.next * size..(self.next + 1) * size];
+				self.next += 1;
+				Some((slot, value))
+			},
+		}
+	}
+}
+
+impl<T: Pod + PartialOrd> ValueScanner<T> {
+	/// Constructs a scanner for values aligned to their size.
+	pub fn new() -> ValueScanner<T> {
+		ValueScanner::with_alignment(mem::size_of::<T>())
+	}
+	/// Constructs a scanner for values at multiples of the alignment from the start of each region.
+	///
+	/// Panics if the alignment or the size of `T` is zero.
+	pub fn with_alignment(alignment: usize) -> ValueScanner<T> {
+		assert!(alignment > 0, "alignment must not be zero");
+		assert!(mem::size_of::<T>() > 0, "cannot scan for zero sized values");
+		ValueScanner { alignment, regions: Vec::new(), count: 0, _marker: PhantomData }
+	}
+	/// The number of candidates.
+	pub fn count(&self) -> usize {
+		self.count
+	}
+	/// Forgets the candidates, the next scan must be a first scan.
+	pub fn reset(&mut self) {
+		self.regions.clear();
+		self.count = 0;
+	}
+	/// Scans the writable committed memory in the range, replacing any previous candidates.
+	///
+	/// Returns the number of candidates.
+	pub fn first_scan<V: VirtualMemory + ?Sized>(&mut self, vm: &V, range: AddressRange, scan: FirstScan<T>) -> Result<usize> {
+		self.reset();
+		let size = mem::size_of::<T>();
+		let mut buffer = Vec::new();
+		for span in spans(vm, range, is_writable)? {
+			let mut region = Region::new(span.start().into_raw(), span.len() as usize, size, self.alignment);
+			read_chunks(vm, span, size - 1, &mut buffer, |address, bytes, chunk_len| {
+				let chunk = Chunk { span, address, bytes, range: 0..chunk_len };
+				for (slot, index) in chunk_slots(&chunk, size, self.alignment) {
+					let value = &bytes[index..index + size];
+					if scan.is_match(&read_value(value)) {
+						region.push(slot, value);
+					}
+				}
+				true
+			})?;
+			self.push_region(region);
+		}
+		Ok(self.count)
+	}
+	/// Scans the writable committed memory in the range with the scan engine, replacing any previous candidates.
+	///
+	/// Finds the same candidates as [`first_scan`](#method.first_scan) reading and matching the memory on multiple threads.
+	/// Returns the number of candidates.
+	pub fn first_scan_with<V>(&mut self, vm: &V, range: AddressRange, scan: FirstScan<T>, engine: &ScanEngine, control: &ScanControl) -> Result<usize>
+		where V: VirtualMemory + Sync + ?Sized, T: Sync
+	{
+		self.reset();
+		let size = mem::size_of::<T>();
+		let matcher = SnapshotMatcher { scan, alignment: self.alignment };
+		let mut region: Option<Region> = None;
+		for chunk in engine.scan(vm, range, is_writable, &matcher, control)? {
+			let base = chunk.span.start().into_raw();
+			if region.as_ref().is_none_or(|region| region.base != base) {
+				if let Some(region) = region.take() {
+					self.push_region(region);
+				}
+				region = Some(Region::new(base, chunk.span.len() as usize, size, self.alignment));
+			}
+			let region = region.as_mut().unwrap();
+			for (&slot, value) in chunk.slots.iter().zip(chunk.values.chunks_exact(size)) {
+				region.push(slot, value);
+			}
+		}
+		if let Some(region) = region {
+			self.push_region(region);
+		}
+		Ok(self.count)
+	}
+	/// Keeps the candidates which match the scan.
+	///
+	/// Candidates which can no longer be read are dropped. Returns the number of candidates.
+	pub fn next_scan<V: VirtualMemory + ?Sized>(&mut self, vm: &V, scan: NextScan<T>) -> Result<usize> {
+		let size = mem::size_of::<T>();
+		let mut buffer = Vec::new();
+		let regions = mem::take(&mut self.regions);
+		self.count = 0;
+		for old in &regions {
+			let mut region = Region::new(old.base, old.len, size, self.alignment);
+			// Only the chunks containing candidates are read, along with the overlap into the next chunk
+			let mut loaded = None;
+			let mut valid = 0;
+			for (slot, old_bytes) in old.iter() {
+				let offset = slot * self.alignment;
+				let chunk = offset - offset % CHUNK_SIZE;
+				if loaded != Some(chunk) {
+					buffer.resize(cmp::min(CHUNK_SIZE + size - 1, old.len - chunk), 0);
+					valid = vm.vm_read_partial((old.base + chunk as u64) as usize, &mut buffer)?.len();
+					loaded = Some(chunk);
+				}
+				let index = offset - chunk;
+				if index + size <= valid {
+					let new_bytes = &buffer[index..index + size];
+					if scan.is_match(old_bytes, new_bytes, &read_value(old_bytes), &read_value(new_bytes)) {
+						region.push(slot, new_bytes);
+					}
+				}
+			}
+			self.push_region(region);
+		}
+		Ok(self.count)
+	}
+	// Keeps the scanned region if it has candidates.
+	fn push_region(&mut self, mut region: Region) {
+		if region.count > 0 {
+			region.compact();
+			self.count += region.count;
+			self.regions.push(region);
+		}
+	}
+	/// Iterates over the address and the value of each candidate as of the last scan, in order of their address.
+	pub fn results(&self) -> Results<'_, T> {
+		Results { scanner: self, region: 0, candidates: None }
+	}
+}
+impl<T: Pod + PartialOrd> Default for ValueScanner<T> {
+	fn default() -> ValueScanner<T> {
+		ValueScanner::new()
+	}
+}
+impl<T> fmt::Debug for ValueScanner<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ValueScanner")
+			.field("alignment", &self.alignment)
+			.field("regions", &self.regions.len())
+			.field("count", &self.count)
+			.finish()
+	}
+}
+
+/// Iterator over the candidates of a [`ValueScanner`](struct.ValueScanner.html).
+pub struct Results<'a, T> {
+	scanner: &'a ValueScanner<T>,
+	region: usize,
+	candidates: Option<CandidateIter<'a>>,
+}
+impl<'a, T: Pod> Iterator for Results<'a, T> {
+	type Item = (Ptr64, T);
+	fn next(&mut self) -> Option<(Ptr64, T)> {
+		loop {
+			if self.candidates.is_none() {
+				self.candidates = Some(self.scanner.regions.get(self.region)?.iter());
+			}
+			let candidates = self.candidates.as_mut().unwrap();
+			match candidates.next() {
+				Some((slot, value)) => {
+					let address = candidates.region.base + (slot * self.scanner.alignment) as u64;
+					return Some((Ptr64::from(address), read_value(value)));
+				},
+				None => {
+					self.region += 1;
+					self.candidates = None;
+				},
+			}
+		}
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(0, Some(self.scanner.count))
+	}
+}
+
+// Iterates over the set bits of a bitmap.
+struct Bits<'a> {
+	bits: &'a [u64],
+	next: usize,
+}
+impl<'a> Iterator for Bits<'a> {
+	type Item = usize;
+	fn next(&mut self) -> Option<usize> {
+		let mut index = self.next / 64;
+		let mut word = self.bits.get(index)? & (u64::MAX << (self.next % 64));
+		while word == 0 {
+			index += 1;
+			word = *self.bits.get(index)?;
+		}
+		let bit = index * 64 + word.trailing_zeros() as usize;
+		self.next = bit + 1;
+		Some(bit)
+	}
+}
+
+/// Matches values with a [`ScanEngine`](struct.ScanEngine.html), finding the address and the value of each match.
+///
+/// Values are expected at multiples of the alignment from the start of each span of adjacent regions.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct ValueMatcher<T> {
+	scan: FirstScan<T>,
+	alignment: usize,
+}
+impl<T: Pod> ValueMatcher<T> {
+	/// Constructs a matcher for values aligned to their size.
+	pub fn new(scan: FirstScan<T>) -> ValueMatcher<T> {
+		ValueMatcher::with_alignment(scan, mem::size_of::<T>())
+	}
+	/// Constructs a matcher for values at multiples of the alignment.
+	///
+	/// Panics if the alignment or the size of `T` is zero.
+	pub fn with_alignment(scan: FirstScan<T>, alignment: usize) -> ValueMatcher<T> {
+		assert!(alignment > 0, "alignment must not be zero");
+		assert!(mem::size_of::<T>() > 0, "cannot scan for zero sized values");
+		ValueMatcher { scan, alignment }
+	}
+}
+impl<T: Pod + PartialOrd + Send + Sync> ChunkMatcher for ValueMatcher<T> {
+	type Match = (Ptr64, T);
+	fn overlap(&self) -> usize {
+		mem::size_of::<T>() - 1
+	}
+	fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<(Ptr64, T)>) {
+		for (_, index) in chunk_slots(chunk, mem::size_of::<T>(), self.alignment) {
+			let value = read_value(&chunk.bytes[index..]);
+			if self.scan.is_match(&value) {
+				matches.push((Ptr64::from(chunk.address + index as u64), value));
+			}
+		}
+	}
+}
+
+// Matches of the first scan of a value scanner in a chunk.
+struct SnapshotChunk {
+	span: AddressRange,
+	// The slots matched relative to the start of the span and their values.
+	slots: Vec<usize>,
+	values: Vec<u8>,
+}
+
+struct SnapshotMatcher<T> {
+	scan: FirstScan<T>,
+	alignment: usize,
+}
+impl<T: Pod + PartialOrd + Sync> ChunkMatcher for SnapshotMatcher<T> {
+	type Match = SnapshotChunk;
+	fn overlap(&self) -> usize {
+		mem::size_of::<T>() - 1
+	}
+	fn scan_chunk(&self, chunk: &Chunk, matches: &mut Vec<SnapshotChunk>) {
+		let size = mem::size_of::<T>();
+		let mut slots = Vec::new();
+		let mut values = Vec::new();
+		for (slot, index) in chunk_slots(chunk, size, self.alignment) {
+			let value = &chunk.bytes[index..index + size];
+			if self.scan.is_match(&read_value(value)) {
+				slots.push(slot);
+				values.extend_from_slice(value);
+			}
+		}
+		if !slots.is_empty() {
+			matches.push(SnapshotChunk { span: chunk.span, slots, values });
+		}
+	}
+}
+
+// The slots starting in the chunk which were read completely, as the slot relative to the span and the index in the bytes.
+fn chunk_slots<'a>(chunk: &'a Chunk, size: usize, alignment: usize) -> impl Iterator<Item = (usize, usize)> + 'a {
+	let start = (chunk.address - chunk.span.start().into_raw()) as usize;
+	let first = (start + chunk.range.start).div_ceil(alignment);
+	(first..)
+		.map(move |slot| (slot, slot * alignment - start))
+		.take_while(move |&(_, index)| index < chunk.range.end && index + size <= chunk.bytes.len())
+}
+
+fn is_writable(mi: &MemoryInformation) -> bool {
+	mi.is_readable() && mi.protect.is_writable()
+}
+
+fn slot_count(len: usize, size: usize, alignment: usize) -> usize {
+	if len < size { 0 } else { (len - size) / alignment + 1 }
+}
+
+fn read_value<T: Pod>(bytes: &[u8]) -> T {
+	assert!(bytes.len() >= mem::size_of::<T>());
+	// Any bit pattern is a valid Pod
+	unsafe { ptr::read_unaligned(bytes.as_ptr() as *const T) }
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::vm::{MockProcess, Protect};
+	use super::*;
+
+	#[test]
+	fn bits() {
+		let bits = [0b1001, 0, 1 << 63, 0];
+		assert_eq!(Bits { bits: &bits, next: 0 }.collect::<Vec<_>>(), [0, 3, 191]);
+		assert_eq!(Bits { bits: &bits, next: 4 }.next(), Some(191));
+		assert_eq!(Bits { bits: &bits, next: 192 }.next(), None);
+		assert_eq!(slot_count(7, 4, 2), 2);
+		assert_eq!(slot_count(3, 4, 1), 0);
+	}
+
+	#[test]
+	fn narrow() {
+		let process = MockProcess::new();
+		let base = process.map(0x100000, &vec![0u8; CHUNK_SIZE * 3], Protect::READ_WRITE).unwrap();
+		let readonly = process.map(0x200000, &[0; 0x1000], Protect::READ_ONLY).unwrap();
+		let poke = |address: usize, value: u32| process.poke(address, &value.to_ne_bytes()).unwrap();
+		poke(base + 0x10, 7);
+		poke(base + CHUNK_SIZE * 2 + 0x10, 7);
+		poke(readonly, 7);
+
+		let mut scanner = ValueScanner::<u32>::new();
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Exact(7)), Ok(2));
+		poke(base + 0x10, 8);
+		assert_eq!(scanner.next_scan(&process, NextScan::Unchanged), Ok(1));
+		assert_eq!(scanner.results().collect::<Vec<_>>(), [(Ptr64::from((base + CHUNK_SIZE * 2 + 0x10) as u64), 7)]);
+		poke(base + CHUNK_SIZE * 2 + 0x10, 9);
+		assert_eq!(scanner.next_scan(&process, NextScan::Increased), Ok(1));
+		assert_eq!(scanner.next_scan(&process, NextScan::Exact(8)), Ok(0));
+		assert_eq!(scanner.results().next(), None);
+
+		// Unknown values across chunks and a freed region
+		let other = process.map(0x300000, &[0; 0x1000], Protect::READ_WRITE).unwrap();
+		let count = (CHUNK_SIZE * 3 + 0x1000) / 4;
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Unknown), Ok(count));
+		poke(base + CHUNK_SIZE, 1);
+		poke(base + CHUNK_SIZE * 3 - 4, 2);
+		assert_eq!(scanner.next_scan(&process, NextScan::Changed), Ok(2));
+		poke(base + CHUNK_SIZE * 3 - 4, 1);
+		assert_eq!(scanner.next_scan(&process, NextScan::Decreased), Ok(1));
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Between(0, 0)), Ok(count - 4));
+		process.vm_release(other).unwrap();
+		assert_eq!(scanner.next_scan(&process, NextScan::Unchanged), Ok(count - 4 - 0x400));
+	}
+
+	#[test]
+	fn storage() {
+		let process = MockProcess::new();
+		let base = process.map(0x100000, &vec![0u8; CHUNK_SIZE * 16], Protect::READ_WRITE).unwrap();
+		let is_sparse = |scanner: &ValueScanner<u32>| scanner.regions.iter().all(|region| matches!(region.candidates, Candidates::Sparse { .. }));
+		process.poke(base + 0x10, &7u32.to_ne_bytes()).unwrap();
+		process.poke(base + CHUNK_SIZE * 9, &7u32.to_ne_bytes()).unwrap();
+
+		// Few candidates in a large region only keep their values
+		let mut scanner = ValueScanner::<u32>::new();
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Exact(7)), Ok(2));
+		assert!(is_sparse(&scanner));
+
+		// Every slot is a candidate, until all but a few are dropped
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Unknown), Ok(CHUNK_SIZE * 4));
+		assert!(!is_sparse(&scanner));
+		process.poke(base + CHUNK_SIZE * 9, &8u32.to_ne_bytes()).unwrap();
+		assert_eq!(scanner.next_scan(&process, NextScan::Changed), Ok(1));
+		assert!(is_sparse(&scanner));
+		assert_eq!(scanner.results().collect::<Vec<_>>(), [(Ptr64::from((base + CHUNK_SIZE * 9) as u64), 8)]);
+		process.poke(base + CHUNK_SIZE * 9, &9u32.to_ne_bytes()).unwrap();
+		assert_eq!(scanner.next_scan(&process, NextScan::Increased), Ok(1));
+	}
+
+	#[test]
+	fn unaligned() {
+		let process = MockProcess::new();
+		let base = process.map(0x100000, &vec![0u8; CHUNK_SIZE * 2], Protect::READ_WRITE).unwrap();
+		process.poke(base + CHUNK_SIZE - 1, &0x11223344u32.to_ne_bytes()).unwrap();
+		let mut scanner = ValueScanner::<u32>::with_alignment(1);
+		assert_eq!(scanner.first_scan(&process, AddressRange::all(), FirstScan::Exact(0x11223344)), Ok(1));
+		process.poke(base + CHUNK_SIZE + 2, &[0xFF]).unwrap();
+		assert_eq!(scanner.next_scan(&process, NextScan::Changed), Ok(1));
+		assert_eq!(scanner.results().next().map(|(address, _)| address), Some(Ptr64::from((base + CHUNK_SIZE - 1) as u64)));
+	}
+
+	#[test]
+	fn engine() {
+		let process = MockProcess::new();
+		let base = process.map(0x100000, &[0; 0x4000], Protect::READ_WRITE).unwrap();
+		process.vm_protect(base + 0x2000, 0x1000, Protect::EXECUTE_READ_WRITE).unwrap();
+		for offset in [0x7FF, 0xFFE, 0x1FFF, 0x3FFC] {
+			process.poke(base + offset, &0x5A5Au16.to_ne_bytes()).unwrap();
+		}
+		let engine = ScanEngine { threads: 3, chunk_size: 0x1000 };
+		let control = ScanControl::new();
+		for alignment in [1, 2, 3] {
+			let mut expected = ValueScanner::<u16>::with_alignment(alignment);
+			let mut scanner = ValueScanner::<u16>::with_alignment(alignment);
+			for scan in [FirstScan::Exact(0x5A5A), FirstScan::Between(0x5A00, 0x5AFF), FirstScan::Unknown] {
+				let count = expected.first_scan(&process, AddressRange::all(), scan).unwrap();
+				assert_eq!(scanner.first_scan_with(&process, AddressRange::all(), scan, &engine, &control), Ok(count));
+				assert!(scanner.results().eq(expected.results()));
+				let matcher = ValueMatcher::with_alignment(scan, alignment);
+				let matches = engine.scan(&process, AddressRange::all(), MemoryInformation::is_readable, &matcher, &control).unwrap();
+				assert!(matches.into_iter().eq(expected.results()));
+			}
+			process.poke(base + 0x1FFF, &[0]).unwrap();
+			assert_eq!(scanner.next_scan(&process, NextScan::Changed), expected.next_scan(&process, NextScan::Changed));
+			assert!(scanner.results().eq(expected.results()));
+			process.poke(base + 0x1FFF, &[0x5A]).unwrap();
+		}
+	}
+}
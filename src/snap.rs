@@ -3,6 +3,7 @@ Screenshots using GDI.
 !*/
 
 use std::{mem, ptr, io};
+use std::mem::MaybeUninit;
 use crate::winapi::*;
 use crate::window::Window;
 use crate::error::ErrorCode;
@@ -11,6 +12,7 @@ use crate::{Result, IntoInner};
 //----------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 pub struct Color {
 	pub blue: u8,
@@ -37,6 +39,7 @@ impl PartialEq<Color> for Color {
 //----------------------------------------------------------------
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
 	pub left: i32,
 	pub top: i32,
@@ -114,11 +117,11 @@ impl Capture {
 	}
 	pub fn info(&self) -> BITMAP {
 		unsafe {
-			let mut bitmap: BITMAP = mem::uninitialized();
+			let mut bitmap = MaybeUninit::<BITMAP>::uninit();
 			let size_of = mem::size_of::<BITMAP>() as i32;
-			let returned = GetObjectW(self.hbmp as *mut c_void, size_of, &mut bitmap as *mut _ as *mut c_void);
+			let returned = GetObjectW(self.hbmp as *mut c_void, size_of, bitmap.as_mut_ptr() as *mut c_void);
 			assert_eq!(returned, size_of);
-			bitmap
+			bitmap.assume_init()
 		}
 	}
 	/// Capture the screen pixels.
@@ -168,12 +171,33 @@ impl Capture {
 //----------------------------------------------------------------
 
 #[derive(PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(try_from = "ImageData"))]
 pub struct Image {
 	pixels: Vec<Color>,
 	width: i32,
 	height: i32,
 }
 
+// Deserialized image, the dimensions are checked against the pixels before it becomes an image.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct ImageData {
+	pixels: Vec<Color>,
+	width: i32,
+	height: i32,
+}
+#[cfg(feature = "serde")]
+impl std::convert::TryFrom<ImageData> for Image {
+	type Error = &'static str;
+	fn try_from(data: ImageData) -> std::result::Result<Image, &'static str> {
+		let len = if data.width >= 0 && data.height >= 0 { (data.width as usize).checked_mul(data.height as usize) } else { None };
+		if len != Some(data.pixels.len()) {
+			return Err("image dimensions do not match the pixels");
+		}
+		Ok(Image { pixels: data.pixels, width: data.width, height: data.height })
+	}
+}
+
 impl Image {
 	pub fn pixels(&self) -> &[Color] {
 		&self.pixels
@@ -2,6 +2,8 @@ use std::{cmp, fmt, mem, ops, slice};
 use std::path::Path;
 use crate::winapi::*;
 use crate::{AsInner, util};
+#[cfg(feature = "serde")]
+use crate::util::to_char_buf;
 
 //----------------------------------------------------------------
 
@@ -111,6 +113,45 @@ impl fmt::Debug for SystemModule {
 	}
 }
 
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "SystemModule")]
+struct SystemModuleData {
+	image_base: usize,
+	image_size: usize,
+	flags: u32,
+	full_path_name: String,
+	file_name: String,
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for SystemModule {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		SystemModuleData {
+			image_base: self.image_base(),
+			image_size: self.image_size(),
+			flags: self.flags(),
+			full_path_name: self.full_path_name().to_string_lossy().into_owned(),
+			file_name: self.file_name().to_string_lossy().into_owned(),
+		}.serialize(serializer)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SystemModule {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<SystemModule, D::Error> {
+		let data = SystemModuleData::deserialize(deserializer)?;
+		let mut module: RTL_PROCESS_MODULE_INFORMATION = unsafe { mem::zeroed() };
+		module.ImageBase = data.image_base as PVOID;
+		module.ImageSize = data.image_size as ULONG;
+		module.Flags = data.flags;
+		to_char_buf(data.full_path_name.as_bytes(), &mut module.FullPathName);
+		// The file name is the tail of the full path name
+		let len = util::from_char_buf(&module.FullPathName).len();
+		let offset = if data.full_path_name.ends_with(&data.file_name) { data.full_path_name.len() - data.file_name.len() } else { 0 };
+		module.OffsetToFileName = cmp::min(offset, len) as USHORT;
+		Ok(SystemModule(module))
+	}
+}
+
 //----------------------------------------------------------------
 
 #[cfg(test)]
@@ -4,10 +4,22 @@ Threads.
 
 mod thread_id;
 mod thread_rights;
+#[cfg(windows)]
 mod thread_enum;
+#[cfg(target_os = "linux")]
+mod thread_enum_linux;
+#[cfg(windows)]
 mod thread;
+#[cfg(target_os = "linux")]
+mod thread_linux;
 
 pub use self::thread_id::*;
 pub use self::thread_rights::*;
+#[cfg(windows)]
 pub use self::thread_enum::*;
+#[cfg(target_os = "linux")]
+pub use self::thread_enum_linux::*;
+#[cfg(windows)]
 pub use self::thread::*;
+#[cfg(target_os = "linux")]
+pub use self::thread_linux::*;
@@ -1,4 +1,4 @@
-use std::{mem};
+use std::mem::MaybeUninit;
 use crate::winapi::*;
 use crate::process::ProcessId;
 use crate::thread::{ThreadId, ThreadRights};
@@ -50,8 +50,9 @@ impl Thread {
 	/// Get the exit code for the thread, `None` if the thread is still running.
 	pub fn exit_code(&self) -> Result<Option<DWORD>> {
 		unsafe {
-			let mut code: DWORD = mem::uninitialized();
-			if GetExitCodeThread(self.0, &mut code) != FALSE {
+			let mut code = MaybeUninit::<DWORD>::uninit();
+			if GetExitCodeThread(self.0, code.as_mut_ptr()) != FALSE {
+				let code = code.assume_init();
 				if code == 259/*STILL_ACTIVE*/ {
 					Ok(None)
 				}
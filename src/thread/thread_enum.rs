@@ -28,7 +28,7 @@ impl Iterator for EnumThreads {
 	type Item = ThreadEntry;
 	fn next(&mut self) -> Option<ThreadEntry> {
 		unsafe {
-			let mut entry: ThreadEntry = mem::uninitialized();
+			let mut entry: ThreadEntry = mem::zeroed();
 			entry.0.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
 			let result = if self.1 {
 				Thread32Next(self.0, &mut entry.0)
@@ -86,3 +86,34 @@ impl fmt::Debug for ThreadEntry {
 			.finish()
 	}
 }
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "ThreadEntry")]
+struct ThreadEntryData {
+	thread_id: ThreadId,
+	process_id: ProcessId,
+	base_priority: i32,
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for ThreadEntry {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+		ThreadEntryData {
+			thread_id: self.thread_id(),
+			process_id: self.process_id(),
+			base_priority: self.base_priority(),
+		}.serialize(serializer)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ThreadEntry {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<ThreadEntry, D::Error> {
+		use crate::IntoInner;
+		let data = ThreadEntryData::deserialize(deserializer)?;
+		let mut entry: THREADENTRY32 = unsafe { mem::zeroed() };
+		entry.dwSize = mem::size_of::<THREADENTRY32>() as DWORD;
+		entry.th32ThreadID = data.thread_id.into_inner();
+		entry.th32OwnerProcessID = data.process_id.into_inner();
+		entry.tpBasePri = data.base_priority;
+		Ok(ThreadEntry(entry))
+	}
+}
@@ -0,0 +1,255 @@
+use std::{fmt, fs};
+use std::time::Duration;
+use crate::process::ProcessId;
+use crate::thread::ThreadId;
+use crate::{procfs, Result, FromInner};
+
+//----------------------------------------------------------------
+
+/// Thread enumeration.
+///
+/// Walks the `/proc/<pid>/task` directories, threads which exit while enumerating are skipped.
+#[derive(Debug)]
+pub struct EnumThreads {
+	processes: Option<fs::ReadDir>,
+	tasks: Option<(ProcessId, fs::ReadDir)>,
+}
+impl EnumThreads {
+	/// Iterate over all running threads.
+	pub fn create() -> Result<EnumThreads> {
+		let processes = fs::read_dir("/proc")?;
+		Ok(EnumThreads { processes: Some(processes), tasks: None })
+	}
+	/// Iterate over the running threads of a process.
+	pub fn process(pid: ProcessId) -> Result<EnumThreads> {
+		let tasks = fs::read_dir(format!("/proc/{}/task", pid))?;
+		Ok(EnumThreads { processes: None, tasks: Some((pid, tasks)) })
+	}
+}
+impl Iterator for EnumThreads {
+	type Item = ThreadEntry;
+	fn next(&mut self) -> Option<ThreadEntry> {
+		loop {
+			if let Some((pid, tasks)) = &mut self.tasks {
+				for entry in tasks {
+					let tid = match entry.ok().and_then(|entry| entry.file_name().to_str()?.parse().ok()) {
+						Some(tid) => ThreadId(tid),
+						None => continue,
+					};
+					if let Some(entry) = ThreadEntry::read(*pid, tid) {
+						return Some(entry);
+					}
+				}
+				self.tasks = None;
+			}
+			let entry = self.processes.as_mut()?.next()?;
+			let pid = match entry.ok().and_then(|entry| entry.file_name().to_str()?.parse().ok()) {
+				Some(pid) => unsafe { ProcessId::from_inner(pid) },
+				None => continue,
+			};
+			self.tasks = fs::read_dir(format!("/proc/{}/task", pid)).ok().map(|tasks| (pid, tasks));
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Thread scheduling state.
+///
+/// See [proc(5)](http://man7.org/linux/man-pages/man5/proc.5.html) for more information.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ThreadState {
+	/// `R`: Running or runnable.
+	Running,
+	/// `S`: Sleeping in an interruptible wait.
+	Sleeping,
+	/// `D`: Waiting in uninterruptible disk sleep.
+	DiskSleep,
+	/// `T`: Stopped on a signal.
+	Stopped,
+	/// `t`: Stopped by a debugger.
+	TracingStop,
+	/// `Z`: Exited but not yet reaped.
+	Zombie,
+	/// `X`: Dead.
+	Dead,
+	/// `I`: Idle kernel thread.
+	Idle,
+	/// `P`: Parked kernel thread.
+	Parked,
+	/// Any other state code.
+	Unknown(char),
+}
+impl ThreadState {
+	/// Decodes the state code.
+	pub fn from_code(code: char) -> ThreadState {
+		match code {
+			'R' => ThreadState::Running,
+			'S' => ThreadState::Sleeping,
+			'D' => ThreadState::DiskSleep,
+			'T' => ThreadState::Stopped,
+			't' => ThreadState::TracingStop,
+			'Z' => ThreadState::Zombie,
+			'X' | 'x' => ThreadState::Dead,
+			'I' => ThreadState::Idle,
+			'P' => ThreadState::Parked,
+			code => ThreadState::Unknown(code),
+		}
+	}
+	/// The state code.
+	pub fn code(self) -> char {
+		match self {
+			ThreadState::Running => 'R',
+			ThreadState::Sleeping => 'S',
+			ThreadState::DiskSleep => 'D',
+			ThreadState::Stopped => 'T',
+			ThreadState::TracingStop => 't',
+			ThreadState::Zombie => 'Z',
+			ThreadState::Dead => 'X',
+			ThreadState::Idle => 'I',
+			ThreadState::Parked => 'P',
+			ThreadState::Unknown(code) => code,
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Thread entry.
+///
+/// See [proc(5)](http://man7.org/linux/man-pages/man5/proc.5.html) for more information.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreadEntry {
+	tid: ThreadId,
+	pid: ProcessId,
+	name: String,
+	state: ThreadState,
+	priority: i32,
+	nice: i32,
+	user_ticks: u64,
+	kernel_ticks: u64,
+	processor: Option<u32>,
+	wait_channel: Option<String>,
+}
+impl ThreadEntry {
+	pub(crate) fn read(pid: ProcessId, tid: ThreadId) -> Option<ThreadEntry> {
+		let stat = procfs::read(format!("/proc/{}/task/{}/stat", pid, tid)).ok()?;
+		let mut entry = ThreadEntry::parse(pid, tid, &stat)?;
+		// Reading the wait channel requires permission to trace the process
+		entry.wait_channel = procfs::read(format!("/proc/{}/task/{}/wchan", pid, tid)).ok()
+			.filter(|wchan| !wchan.is_empty() && wchan != "0");
+		Some(entry)
+	}
+	fn parse(pid: ProcessId, tid: ThreadId, stat: &str) -> Option<ThreadEntry> {
+		let (comm, fields) = procfs::stat_fields(stat)?;
+		Some(ThreadEntry {
+			tid,
+			pid,
+			name: comm.to_owned(),
+			state: ThreadState::from_code(fields.first()?.chars().next()?),
+			user_ticks: fields.get(11)?.parse().ok()?,
+			kernel_ticks: fields.get(12)?.parse().ok()?,
+			priority: fields.get(15)?.parse().ok()?,
+			nice: fields.get(16)?.parse().ok()?,
+			processor: fields.get(36).and_then(|cpu| cpu.parse().ok()),
+			wait_channel: None,
+		})
+	}
+	/// The thread identifier.
+	pub fn thread_id(&self) -> ThreadId {
+		self.tid
+	}
+	/// The identifier of the process that created the thread.
+	pub fn process_id(&self) -> ProcessId {
+		self.pid
+	}
+	/// The name of the thread, truncated to 15 bytes.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+	/// The scheduling state of the thread.
+	pub fn state(&self) -> ThreadState {
+		self.state
+	}
+	/// The kernel priority of the thread.
+	///
+	/// This is 20 plus the nice value for normal threads and negative for real-time threads.
+	pub fn base_priority(&self) -> i32 {
+		self.priority
+	}
+	/// The nice value of the thread, ranging from 19 (low priority) to -20 (high priority).
+	pub fn nice(&self) -> i32 {
+		self.nice
+	}
+	/// The time the thread has spent executing in user mode.
+	pub fn user_time(&self) -> Duration {
+		procfs::clock_ticks(self.user_ticks)
+	}
+	/// The time the thread has spent executing in kernel mode.
+	pub fn kernel_time(&self) -> Duration {
+		procfs::clock_ticks(self.kernel_ticks)
+	}
+	/// The processor the thread last executed on.
+	pub fn processor(&self) -> Option<u32> {
+		self.processor
+	}
+	/// The kernel function the thread is waiting in, `None` if the thread is running or the wait channel is not accessible.
+	pub fn wait_channel(&self) -> Option<&str> {
+		self.wait_channel.as_deref()
+	}
+}
+impl fmt::Debug for ThreadEntry {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ThreadEntry")
+			.field("thread_id", &self.thread_id())
+			.field("process_id", &self.process_id())
+			.field("name", &self.name())
+			.field("state", &self.state())
+			.field("base_priority", &self.base_priority())
+			.field("nice", &self.nice())
+			.field("user_time", &self.user_time())
+			.field("kernel_time", &self.kernel_time())
+			.field("wait_channel", &self.wait_channel())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let stat = "4243 (worker 1) D 1 4242 4242 0 -1 4194368 10 0 0 0 250 100 0 0 25 5 3 0 123 4096 10 18446744073709551615 1 1 0 0 0 0 0 0 0 0 0 0 17 2 0 0";
+		let entry = ThreadEntry::parse(unsafe { ProcessId::from_inner(4242) }, ThreadId(4243), stat).unwrap();
+		assert_eq!(entry.name(), "worker 1");
+		assert_eq!(entry.state(), ThreadState::DiskSleep);
+		assert_eq!(entry.base_priority(), 25);
+		assert_eq!(entry.nice(), 5);
+		assert_eq!(entry.user_time(), procfs::clock_ticks(250));
+		assert_eq!(entry.kernel_time(), procfs::clock_ticks(100));
+		assert_eq!(entry.processor(), Some(2));
+	}
+
+	#[test]
+	fn enum_self() {
+		let pid = unsafe { ProcessId::from_inner(std::process::id()) };
+		let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+		let (tx, rx) = std::sync::mpsc::channel::<()>();
+		let handle = std::thread::Builder::new().name("enum_self".into()).spawn(move || {
+			ready_tx.send(()).unwrap();
+			rx.recv()
+		}).unwrap();
+		ready_rx.recv().unwrap();
+		let threads: Vec<_> = EnumThreads::process(pid).unwrap().collect();
+		assert!(threads.iter().all(|thread| thread.process_id() == pid));
+		assert!(threads.iter().any(|thread| thread.name() == "enum_self"));
+		assert!(EnumThreads::create().unwrap().any(|thread| thread.process_id() == pid));
+		tx.send(()).unwrap();
+		handle.join().unwrap().unwrap();
+	}
+}
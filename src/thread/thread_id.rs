@@ -1,14 +1,19 @@
 use std::fmt;
-use crate::winapi::*;
 
 /// Wraps a thread identifier.
 #[derive(Copy, Clone, Eq, PartialEq)]
-pub struct ThreadId(pub(super) DWORD);
-impl_inner!(ThreadId: DWORD);
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ThreadId(pub(super) u32);
+impl_inner!(ThreadId: u32);
 
-// Custom Debug implementation to disable pretty formatting
+// Custom Debug and Display implementation to disable pretty formatting
 impl fmt::Debug for ThreadId {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
 		write!(f, "ThreadId({})", self.0)
 	}
 }
+impl fmt::Display for ThreadId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
@@ -0,0 +1,90 @@
+use std::time::{Duration, Instant};
+use crate::process::{ProcessId, WAIT_OBJECT_0, WAIT_TIMEOUT, INFINITE};
+use crate::thread::{ThreadEntry, ThreadId, ThreadRights, ThreadState};
+use crate::error::{ERROR_FILE_NOT_FOUND, ERROR_INVALID_DATA, ERROR_INVALID_PARAMETER};
+use crate::{procfs, Result, FromInner};
+
+//----------------------------------------------------------------
+
+/// Thread handle.
+///
+/// Refers to the thread by its id and the id of its process, threads are inspected through `/proc/<pid>/task/<tid>`.
+#[derive(Clone, Debug)]
+pub struct Thread {
+	tid: ThreadId,
+	pid: ProcessId,
+}
+impl Thread {
+	/// Get the current thread.
+	pub fn current() -> Thread {
+		unsafe {
+			let tid = ThreadId(libc::syscall(libc::SYS_gettid) as u32);
+			let pid = ProcessId::from_inner(libc::getpid() as u32);
+			Thread { tid, pid }
+		}
+	}
+	/// Attach to a thread by id and given rights.
+	///
+	/// The rights are not checked on Linux.
+	pub fn attach(tid: ThreadId, _access: ThreadRights) -> Result<Thread> {
+		let status = procfs::read(format!("/proc/{}/status", tid)).map_err(|err| {
+			if err == ERROR_FILE_NOT_FOUND { ERROR_INVALID_PARAMETER } else { err }
+		})?;
+		let pid = procfs::status_field(&status, "Tgid")
+			.and_then(|tgid| tgid.parse().ok())
+			.ok_or(ERROR_INVALID_DATA)?;
+		Ok(Thread { tid, pid: unsafe { ProcessId::from_inner(pid) } })
+	}
+	/// Get the id for this thread.
+	pub fn tid(&self) -> Result<ThreadId> {
+		Ok(self.tid)
+	}
+	/// Get the owning process' id.
+	pub fn process_id(&self) -> Result<ProcessId> {
+		Ok(self.pid)
+	}
+	/// Get the current state, priority, CPU time and wait channel of the thread.
+	pub fn info(&self) -> Result<ThreadEntry> {
+		ThreadEntry::read(self.pid, self.tid).ok_or(ERROR_INVALID_PARAMETER)
+	}
+	/// Wait for the thread to finish.
+	///
+	/// Returns `WAIT_OBJECT_0` if the thread finished or `WAIT_TIMEOUT` if the timeout elapsed, pass `INFINITE` to wait indefinitely.
+	/// Linux has no handle to wait on a thread of another process, the thread is polled instead.
+	pub fn wait(&self, milis: u32) -> Result<u32> {
+		let deadline = if milis == INFINITE { None } else { Some(Instant::now() + Duration::from_millis(milis as u64)) };
+		let exited = procfs::poll_until(deadline, || Ok(self.has_exited()))?;
+		Ok(if exited { WAIT_OBJECT_0 } else { WAIT_TIMEOUT })
+	}
+	fn has_exited(&self) -> bool {
+		// The task directory is removed once the thread has been reaped
+		ThreadEntry::read(self.pid, self.tid)
+			.is_none_or(|entry| matches!(entry.state(), ThreadState::Zombie | ThreadState::Dead))
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::sync::mpsc;
+	use std::thread;
+	use super::*;
+
+	#[test]
+	fn attach_wait() {
+		let current = Thread::current();
+		assert_eq!(current.info().unwrap().state(), ThreadState::Running);
+		let (tx, rx) = mpsc::channel();
+		let handle = thread::spawn(move || {
+			tx.send(Thread::current().tid().unwrap()).unwrap();
+			thread::sleep(Duration::from_millis(100));
+		});
+		let tid = rx.recv().unwrap();
+		let thread = Thread::attach(tid, ThreadRights::new()).unwrap();
+		assert_eq!(thread.process_id(), current.process_id());
+		assert_eq!(thread.wait(0), Ok(WAIT_TIMEOUT));
+		assert_eq!(thread.wait(INFINITE), Ok(WAIT_OBJECT_0));
+		handle.join().unwrap();
+	}
+}
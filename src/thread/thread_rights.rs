@@ -1,4 +1,4 @@
-use crate::winapi::*;
+use crate::rights::*;
 
 //use winapi::{THREAD_ALL_ACCESS, THREAD_DIRECT_IMPERSONATION, THREAD_GET_CONTEXT, THREAD_IMPERSONATE, THREAD_QUERY_INFORMATION, THREAD_QUERY_LIMITED_INFORMATION,
 //	THREAD_SET_CONTEXT, THREAD_SET_INFORMATION, THREAD_SET_LIMITED_INFORMATION, THREAD_SET_THREAD_TOKEN, THREAD_SUSPEND_RESUME, THREAD_TERMINATE};
@@ -6,8 +6,15 @@ use crate::winapi::*;
 /// Create thread access rights using the builder pattern.
 ///
 /// See [Thread Security and Access Rights](https://msdn.microsoft.com/en-us/library/windows/desktop/ms686769.aspx) for more information.
-pub struct ThreadRights(DWORD);
-impl_inner!(ThreadRights: DWORD);
+///
+/// On Linux the rights are not checked, threads are inspected through `/proc`.
+pub struct ThreadRights(u32);
+impl_inner!(ThreadRights: u32);
+impl Default for ThreadRights {
+	fn default() -> ThreadRights {
+		ThreadRights::new()
+	}
+}
 impl ThreadRights {
 	pub fn new() -> ThreadRights {
 		ThreadRights(0)
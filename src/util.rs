@@ -15,12 +15,46 @@ pub fn from_wchar_buf(buf: &[u16]) -> &[u16] {
 
 #[inline]
 pub fn from_char_buf(buf: &[u8]) -> &[u8] {
-	let mut len = buf.len();
-	for i in 0..len {
-		if buf[i] == 0 {
-			len = i;
-			break;
-		}
-	}
+	let len = buf.iter()
+		.position(|&byte| byte == 0)
+		.unwrap_or(buf.len());
 	&buf[..len]
 }
+
+/// Copies the string into a null terminated wide character buffer, truncating it if it does not fit.
+#[inline]
+pub fn to_wchar_buf(s: &str, buf: &mut [u16]) {
+	let len = buf.len().saturating_sub(1);
+	let mut end = 0;
+	for (dest, word) in buf[..len].iter_mut().zip(s.encode_utf16()) {
+		*dest = word;
+		end += 1;
+	}
+	if let Some(term) = buf.get_mut(end) {
+		*term = 0;
+	}
+}
+
+/// Copies the bytes into a null terminated character buffer, truncating them if they do not fit.
+#[inline]
+pub fn to_char_buf(bytes: &[u8], buf: &mut [u8]) {
+	let len = usize::min(bytes.len(), buf.len().saturating_sub(1));
+	buf[..len].copy_from_slice(&bytes[..len]);
+	if let Some(term) = buf.get_mut(len) {
+		*term = 0;
+	}
+}
+
+/// Serializes an `OsString` as a string, invalid unicode is replaced.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_os_string {
+	use std::ffi::{OsStr, OsString};
+	use serde::{Deserialize, Deserializer, Serializer};
+
+	pub fn serialize<S: Serializer>(s: &OsStr, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_str(&s.to_string_lossy())
+	}
+	pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OsString, D::Error> {
+		String::deserialize(deserializer).map(OsString::from)
+	}
+}
@@ -0,0 +1,161 @@
+use crate::ptr::Ptr64;
+use crate::Result;
+use super::{AddressRange, MemoryInformation, MemoryState, MemoryType, VirtualMemory};
+
+/// Module in an [`AddressSpaceMap`](struct.AddressSpaceMap.html).
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ModuleRange {
+	pub range: AddressRange,
+	pub name: String,
+}
+
+/// Map of the regions and modules in an address space.
+///
+/// Both are kept sorted by address so the region or module containing an address is found with a binary search.
+/// The map is a snapshot, it does not follow changes in the process.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::vm::{AddressRange, AddressSpaceMap, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, &[0; 0x2000], Protect::EXECUTE_READ).unwrap() as u64;
+///
+/// let mut map = AddressSpaceMap::from_vm(&process).unwrap();
+/// map.insert_module("game.exe", AddressRange::from_len(Ptr64::from(base), 0x2000));
+/// assert_eq!(map.module(Ptr64::from(base + 0x1234)).map(|module| &*module.name), Some("game.exe"));
+/// assert!(map.is_plausible_pointer(base + 0x10));
+/// assert!(!map.is_plausible_pointer(0x1234));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct AddressSpaceMap {
+	regions: Vec<MemoryInformation>,
+	modules: Vec<ModuleRange>,
+}
+impl AddressSpaceMap {
+	/// Creates an empty map.
+	pub fn new() -> AddressSpaceMap {
+		AddressSpaceMap::default()
+	}
+	/// Collects the allocated regions of the address space with `vm_regions`.
+	pub fn from_vm<V: VirtualMemory + ?Sized>(vm: &V) -> Result<AddressSpaceMap> {
+		let mut map = AddressSpaceMap::new();
+		super::regions_in(vm, AddressRange::all(), |mi| {
+			if mi.state != MemoryState::FREE {
+				map.regions.push(*mi);
+			}
+		})?;
+		Ok(map)
+	}
+	/// Collects the allocated regions and the modules of a live process.
+	#[cfg(any(windows, target_os = "linux"))]
+	pub fn from_process(process: &crate::process::Process) -> Result<AddressSpaceMap> {
+		let mut map = AddressSpaceMap::from_vm(process)?;
+		for entry in crate::module::EnumModules::create(process.pid()?)? {
+			map.insert_module(entry.name().to_string_lossy(), AddressRange::from(&entry));
+		}
+		Ok(map)
+	}
+	/// Inserts a region, the regions in the map must not overlap.
+	pub fn insert_region(&mut self, mi: MemoryInformation) {
+		let index = self.regions.partition_point(|region| region.base_address < mi.base_address);
+		self.regions.insert(index, mi);
+	}
+	/// Inserts a module, the modules in the map must not overlap.
+	pub fn insert_module<S: Into<String>>(&mut self, name: S, range: AddressRange) {
+		let index = self.modules.partition_point(|module| module.range.start() < range.start());
+		self.modules.insert(index, ModuleRange { range, name: name.into() });
+	}
+	/// The regions sorted by address.
+	pub fn regions(&self) -> &[MemoryInformation] {
+		&self.regions
+	}
+	/// The modules sorted by address.
+	pub fn modules(&self) -> &[ModuleRange] {
+		&self.modules
+	}
+	/// Finds the region containing the address.
+	pub fn region(&self, address: Ptr64) -> Option<&MemoryInformation> {
+		let address = address.into_raw();
+		let index = self.regions.partition_point(|region| region.base_address as u64 <= address);
+		let region = self.regions.get(index.checked_sub(1)?)?;
+		if address - (region.base_address as u64) < region.region_size as u64 { Some(region) } else { None }
+	}
+	/// Finds the module containing the address.
+	pub fn module(&self, address: Ptr64) -> Option<&ModuleRange> {
+		let index = self.modules.partition_point(|module| module.range.start() <= address);
+		let module = self.modules.get(index.checked_sub(1)?)?;
+		if module.range.contains(address) { Some(module) } else { None }
+	}
+	/// Finds a module by name, ignoring ASCII case.
+	pub fn module_by_name(&self, name: &str) -> Option<&ModuleRange> {
+		self.modules.iter().find(|module| module.name.eq_ignore_ascii_case(name))
+	}
+	/// Returns if the value points into readable memory.
+	pub fn is_plausible_pointer(&self, value: u64) -> bool {
+		self.region(Ptr64::from(value)).is_some_and(|region| region.is_readable())
+	}
+	/// Returns if the address is in executable memory of an image.
+	///
+	/// Images are the regions mapped as images or covered by a module.
+	pub fn is_executable_image(&self, address: Ptr64) -> bool {
+		match self.region(address) {
+			Some(region) => region.state == MemoryState::COMMIT && region.protect.is_executable()
+				&& (region.mem_type == MemoryType::IMAGE || self.module(address).is_some()),
+			None => false,
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::super::*;
+	use super::*;
+
+	#[test]
+	fn lookup() {
+		let process = MockProcess::new();
+		let image = process.map_type(0x10000, &[0; 0x3000], Protect::READ_ONLY, MemoryType::IMAGE).unwrap();
+		process.vm_protect(image + 0x1000, 0x1000, Protect::EXECUTE_READ).unwrap();
+		let heap = process.map(0x20000, &[0; 0x1000], Protect::EXECUTE_READ_WRITE).unwrap() as u64;
+		let guard = process.map(0x30000, &[0; 0x1000], Protect::READ_WRITE.set_guard(true)).unwrap() as u64;
+
+		let mut map = AddressSpaceMap::from_vm(&process).unwrap();
+		assert_eq!(map.regions().len(), 5);
+		map.insert_module("b.dll", AddressRange::from_len(Ptr64::from(0x7000_0000), 0x1000));
+		map.insert_module("a.exe", AddressRange::from_len(Ptr64::from(image as u64), 0x3000));
+		assert_eq!(map.modules()[0].name, "a.exe");
+
+		let image = image as u64;
+		assert_eq!(map.region(Ptr64::from(image + 0x1800)).map(|mi| mi.base_address as u64), Some(image + 0x1000));
+		assert_eq!(map.region(Ptr64::from(image - 1)), None);
+		assert_eq!(map.module(Ptr64::from(image + 0x2FFF)).map(|module| &*module.name), Some("a.exe"));
+		assert_eq!(map.module(Ptr64::from(image + 0x3000)), None);
+		assert_eq!(map.module_by_name("B.DLL").map(|module| module.range.start()), Some(Ptr64::from(0x7000_0000)));
+
+		assert!(map.is_plausible_pointer(image) && map.is_plausible_pointer(heap + 0xFFF));
+		assert!(!map.is_plausible_pointer(guard) && !map.is_plausible_pointer(0));
+		assert!(map.is_executable_image(Ptr64::from(image + 0x1000)));
+		assert!(!map.is_executable_image(Ptr64::from(image)));
+		assert!(!map.is_executable_image(Ptr64::from(heap)));
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn from_process() {
+		fn code() {}
+		let data = Box::new(0u64);
+		let map = AddressSpaceMap::from_process(&crate::process::Process::current()).unwrap();
+		let exe = std::env::current_exe().unwrap();
+		let code = Ptr64::from(code as fn() as usize as u64);
+		assert_eq!(map.module(code).map(|module| &*module.name), exe.file_name().and_then(|name| name.to_str()));
+		assert!(map.is_executable_image(code));
+		assert!(map.is_plausible_pointer(&*data as *const u64 as u64));
+		assert!(!map.is_executable_image(Ptr64::from(&*data as *const u64 as u64)));
+	}
+}
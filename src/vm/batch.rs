@@ -0,0 +1,209 @@
+use std::{cmp, fmt};
+use crate::ptr::{Pod, RemotePtr};
+use crate::error::ERROR_PARTIAL_COPY;
+use crate::Result;
+use super::VirtualMemory;
+
+// Requests separated by at most this many bytes are read together.
+const MAX_GAP: usize = 0x100;
+// Requests are not merged into reads larger than this many bytes.
+const MAX_RUN: usize = 0x10000;
+
+//----------------------------------------------------------------
+
+/// Read request of a batch.
+///
+/// See [`vm_read_batch`](trait.VirtualMemory.html#method.vm_read_batch) for more information.
+pub struct ReadRequest<'a> {
+	address: usize,
+	dest: &'a mut [u8],
+	result: Result<usize>,
+}
+impl<'a> ReadRequest<'a> {
+	/// Requests bytes to be read into the destination buffer.
+	pub fn new(address: usize, dest: &'a mut [u8]) -> ReadRequest<'a> {
+		ReadRequest { address, dest, result: Ok(0) }
+	}
+	/// Requests a Pod `T` to be read into the destination.
+	pub fn from_ptr<T: Pod + ?Sized, P: RemotePtr<Target = T>>(ptr: P, dest: &'a mut T) -> ReadRequest<'a> {
+		ReadRequest::new(ptr.into_address() as usize, dest.as_bytes_mut())
+	}
+	/// The address to read from.
+	pub fn address(&self) -> usize {
+		self.address
+	}
+	/// The number of bytes requested.
+	pub fn len(&self) -> usize {
+		self.dest.len()
+	}
+	/// Returns if no bytes are requested.
+	pub fn is_empty(&self) -> bool {
+		self.dest.is_empty()
+	}
+	/// The bytes which were read, may be fewer than requested.
+	///
+	/// Follows the semantics of [`vm_read_partial`](trait.VirtualMemory.html#method.vm_read_partial).
+	pub fn bytes(&self) -> Result<&[u8]> {
+		match self.result {
+			Ok(len) => Ok(&self.dest[..len]),
+			Err(err) => Err(err),
+		}
+	}
+	/// Returns success if all requested bytes were read.
+	///
+	/// Follows the semantics of [`vm_read_bytes`](trait.VirtualMemory.html#method.vm_read_bytes), fails with `ERROR_PARTIAL_COPY` if fewer bytes were read.
+	pub fn result(&self) -> Result<()> {
+		match self.result {
+			Ok(len) if len == self.dest.len() => Ok(()),
+			Ok(_) => Err(ERROR_PARTIAL_COPY),
+			Err(err) => Err(err),
+		}
+	}
+	fn end(&self) -> usize {
+		self.address.saturating_add(self.dest.len())
+	}
+}
+impl fmt::Debug for ReadRequest<'_> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ReadRequest")
+			.field("address", &format_args!("{:#x}", self.address))
+			.field("len", &format_args!("{:#x}", self.dest.len()))
+			.field("result", &self.result)
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+// Adjacent requests merged into a single read.
+struct Run {
+	start: usize,
+	end: usize,
+	// Range into the sorted request indices.
+	first: usize,
+	last: usize,
+}
+
+fn coalesce(requests: &[ReadRequest], pending: &[usize]) -> Vec<Run> {
+	let mut runs: Vec<Run> = Vec::new();
+	for (i, &index) in pending.iter().enumerate() {
+		let request = &requests[index];
+		if let Some(run) = runs.last_mut() {
+			let end = cmp::max(run.end, request.end());
+			if request.address <= run.end.saturating_add(MAX_GAP) && end - run.start <= MAX_RUN {
+				run.end = end;
+				run.last = i + 1;
+				continue;
+			}
+		}
+		runs.push(Run { start: request.address, end: request.end(), first: i, last: i + 1 });
+	}
+	runs
+}
+
+pub(crate) fn read_batch<V: VirtualMemory + ?Sized>(vm: &V, requests: &mut [ReadRequest]) {
+	let mut pending: Vec<usize> = Vec::new();
+	for (index, request) in requests.iter_mut().enumerate() {
+		request.result = Ok(0);
+		if !request.is_empty() {
+			pending.push(index);
+		}
+	}
+	pending.sort_by_key(|&index| requests[index].address);
+	while !pending.is_empty() {
+		let runs = coalesce(requests, &pending);
+		let mut buffers: Vec<Vec<u8>> = runs.iter().map(|run| vec![0u8; run.end - run.start]).collect();
+		let mut iov: Vec<(usize, &mut [u8])> = runs.iter().zip(buffers.iter_mut()).map(|(run, buffer)| (run.start, &mut buffer[..])).collect();
+		let mut remaining = match vm.vm_read_scatter(&mut iov) {
+			Ok(total) => total,
+			Err(err) => {
+				for &index in &pending {
+					requests[index].result = Err(err);
+				}
+				return;
+			},
+		};
+		// Ranges are read in order up to the first range which could not be read completely
+		let mut unresolved = Vec::new();
+		let mut stopped = false;
+		for (run, buffer) in runs.iter().zip(&buffers) {
+			if stopped {
+				unresolved.extend_from_slice(&pending[run.first..run.last]);
+				continue;
+			}
+			let read = cmp::min(remaining, buffer.len());
+			remaining -= read;
+			stopped = read < buffer.len();
+			let read_end = run.start + read;
+			for &index in &pending[run.first..run.last] {
+				let request = &mut requests[index];
+				// Requests starting past the first unreadable byte may still be readable
+				if request.address > read_end {
+					unresolved.push(index);
+					continue;
+				}
+				let offset = request.address - run.start;
+				let len = cmp::min(request.dest.len(), read_end - request.address);
+				request.dest[..len].copy_from_slice(&buffer[offset..offset + len]);
+				request.result = Ok(len);
+			}
+		}
+		pending = unresolved;
+	}
+}
+
+// The default implementation of `vm_read_scatter`.
+pub(crate) fn read_scatter<V: VirtualMemory + ?Sized>(vm: &V, iov: &mut [(usize, &mut [u8])]) -> Result<usize> {
+	let mut total = 0;
+	for (address, dest) in iov.iter_mut() {
+		let len = dest.len();
+		let read = vm.vm_read_partial(*address, dest)?.len();
+		total += read;
+		if read < len {
+			break;
+		}
+	}
+	Ok(total)
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::ptr::Ptr;
+	use super::super::*;
+	use super::*;
+
+	#[test]
+	fn batch() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &(0..0x2000).map(|i| i as u8).collect::<Vec<u8>>(), Protect::READ_WRITE).unwrap();
+		process.vm_protect(base + 0x1000, 0x1000, Protect::NO_ACCESS).unwrap();
+		let second = process.map(0x20000, &[0x77; 0x10], Protect::READ_ONLY).unwrap();
+
+		let mut a = [0u8; 4];
+		let mut b = 0u32;
+		let mut c = [0u8; 0x20];
+		let mut d = [0u8; 2];
+		let mut e = [0u8; 4];
+		let mut empty = [0u8; 0];
+		let mut requests = [
+			ReadRequest::new(base + 0x10, &mut a),
+			ReadRequest::from_ptr(Ptr::<u32>::from(base as u64 + 0x14), &mut b),
+			ReadRequest::new(base + 0xFF0, &mut c),
+			ReadRequest::new(base + 0x1008, &mut d),
+			ReadRequest::new(second, &mut e),
+			ReadRequest::new(0, &mut empty),
+		];
+		process.vm_read_batch(&mut requests);
+		assert_eq!(requests[0].bytes(), Ok(&[0x10, 0x11, 0x12, 0x13][..]));
+		assert_eq!(requests[1].result(), Ok(()));
+		assert_eq!(requests[2].bytes().map(|bytes| bytes.len()), Ok(0x10));
+		assert_eq!(requests[2].result(), Err(ERROR_PARTIAL_COPY));
+		assert_eq!(requests[3].bytes(), Ok(&[][..]));
+		assert_eq!(requests[4].bytes(), Ok(&[0x77; 4][..]));
+		assert_eq!(requests[5].result(), Ok(()));
+		assert_eq!(b, 0x17161514);
+		assert_eq!(c[0xF], 0xFF);
+	}
+}
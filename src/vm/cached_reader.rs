@@ -0,0 +1,298 @@
+use std::{cmp, fmt};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use crate::error::ERROR_PARTIAL_COPY;
+use crate::Result;
+use super::*;
+
+const PAGE_SIZE: usize = 0x1000;
+
+//----------------------------------------------------------------
+
+/// Hit and miss statistics of a [`CachedReader`](struct.CachedReader.html).
+///
+/// Counts pages, a read spanning multiple pages counts a hit or miss for every page.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+	/// Pages served from the cache.
+	pub hits: u64,
+	/// Pages fetched from the backend.
+	pub misses: u64,
+	/// Read calls made to the backend, adjacent missing pages are fetched together.
+	pub fetches: u64,
+}
+impl CacheStats {
+	/// The ratio of hits to page accesses, zero if nothing was read.
+	pub fn hit_ratio(&self) -> f64 {
+		let total = self.hits + self.misses;
+		if total == 0 { 0.0 } else { self.hits as f64 / total as f64 }
+	}
+}
+
+/// Page granular read cache.
+///
+/// Wraps a [`VirtualMemory`](trait.VirtualMemory.html) backend and fetches whole pages on demand, subsequent reads of the same pages are served from the cache.
+/// Pages which cannot be read are remembered as well and fail with `ERROR_PARTIAL_COPY` without asking the backend again.
+///
+/// The cache is never refreshed automatically, call [`invalidate`](#method.invalidate) once per tick or [`invalidate_range`](#method.invalidate_range) when memory is known to have changed.
+/// Writes, allocations and protection changes made through the reader invalidate the affected pages.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr;
+/// use external::vm::{CachedReader, MockProcess, Protect, VirtualMemory};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0, &[1, 0, 0, 0, 2, 0, 0, 0], Protect::READ_WRITE).unwrap();
+/// let reader = CachedReader::new(process);
+/// assert_eq!(reader.vm_read(Ptr::<u32>::from(base as u64)), Ok(1));
+/// assert_eq!(reader.vm_read(Ptr::<u32>::from(base as u64 + 4)), Ok(2));
+/// assert_eq!((reader.stats().hits, reader.stats().misses), (1, 1));
+/// ```
+pub struct CachedReader<V> {
+	inner: V,
+	// Pages which could not be read are stored as `None`.
+	pages: RefCell<HashMap<usize, Option<Box<[u8]>>>>,
+	stats: Cell<CacheStats>,
+}
+impl<V: VirtualMemory> CachedReader<V> {
+	/// Wraps the backend with an empty cache.
+	pub fn new(inner: V) -> CachedReader<V> {
+		CachedReader {
+			inner,
+			pages: RefCell::new(HashMap::new()),
+			stats: Cell::new(CacheStats::default()),
+		}
+	}
+	/// Gets a reference to the backend.
+	///
+	/// Reads made directly on the backend bypass the cache.
+	pub fn get_ref(&self) -> &V {
+		&self.inner
+	}
+	/// Unwraps the backend, discarding the cache.
+	pub fn into_inner(self) -> V {
+		self.inner
+	}
+	/// Discards all cached pages.
+	pub fn invalidate(&self) {
+		self.pages.borrow_mut().clear();
+	}
+	/// Discards the cached pages overlapping the address range.
+	pub fn invalidate_range(&self, address: usize, len: usize) {
+		if len == 0 {
+			return;
+		}
+		let first = address & !(PAGE_SIZE - 1);
+		let last = address.saturating_add(len - 1) & !(PAGE_SIZE - 1);
+		let mut pages = self.pages.borrow_mut();
+		if (last - first) / PAGE_SIZE >= pages.len() {
+			pages.retain(|&page, _| page < first || page > last);
+		}
+		else {
+			let mut page = first;
+			loop {
+				pages.remove(&page);
+				if page == last {
+					break;
+				}
+				page += PAGE_SIZE;
+			}
+		}
+	}
+	/// The number of cached pages.
+	pub fn len(&self) -> usize {
+		self.pages.borrow().len()
+	}
+	/// Returns if no pages are cached.
+	pub fn is_empty(&self) -> bool {
+		self.pages.borrow().is_empty()
+	}
+	/// Gets the hit and miss statistics.
+	pub fn stats(&self) -> CacheStats {
+		self.stats.get()
+	}
+	/// Resets the hit and miss statistics.
+	pub fn reset_stats(&self) {
+		self.stats.set(CacheStats::default());
+	}
+
+	// Copies as many bytes as are readable, returns the number of bytes copied.
+	fn read_cached(&self, address: usize, dest: &mut [u8]) -> Result<usize> {
+		let mut stats = self.stats.get();
+		let mut pages = self.pages.borrow_mut();
+		let mut done = 0;
+		// Pages fetched by this read are counted as misses only
+		let mut fetched = 0..0;
+		let result = loop {
+			if done >= dest.len() {
+				break Ok(done);
+			}
+			let current = match address.checked_add(done) {
+				Some(current) => current,
+				None => break Ok(done),
+			};
+			let page_base = current & !(PAGE_SIZE - 1);
+			let offset = current - page_base;
+			if !fetched.contains(&page_base) {
+				if pages.contains_key(&page_base) {
+					stats.hits += 1;
+				}
+				else {
+					match self.fetch(&mut pages, &mut stats, page_base, dest.len() - done + offset) {
+						Ok(count) => fetched = page_base..page_base.saturating_add(count * PAGE_SIZE),
+						Err(err) => break Err(err),
+					}
+				}
+			}
+			let len = cmp::min(PAGE_SIZE - offset, dest.len() - done);
+			let bytes = match &pages[&page_base] {
+				Some(bytes) => &bytes[cmp::min(offset, bytes.len())..],
+				None => &[][..],
+			};
+			let copied = cmp::min(len, bytes.len());
+			dest[done..done + copied].copy_from_slice(&bytes[..copied]);
+			done += copied;
+			if copied < len {
+				break Ok(done);
+			}
+		};
+		self.stats.set(stats);
+		result
+	}
+	// Fetches the missing pages starting at the page base covering up to len bytes in a single read, returns the number of pages cached.
+	fn fetch(&self, pages: &mut HashMap<usize, Option<Box<[u8]>>>, stats: &mut CacheStats, page_base: usize, len: usize) -> Result<usize> {
+		let mut count = 1;
+		while count * PAGE_SIZE < len {
+			match page_base.checked_add(count * PAGE_SIZE) {
+				Some(next) if !pages.contains_key(&next) => count += 1,
+				_ => break,
+			}
+		}
+		let mut buffer = vec![0u8; count * PAGE_SIZE];
+		let read = self.inner.vm_read_partial(page_base, &mut buffer)?.len();
+		stats.fetches += 1;
+		let mut cached = 0;
+		while cached < count {
+			let start = cached * PAGE_SIZE;
+			let end = cmp::min(start + PAGE_SIZE, read);
+			let bytes = if start < end { Some(buffer[start..end].into()) } else { None };
+			let complete = end - cmp::min(start, end) == PAGE_SIZE;
+			pages.insert(page_base + start, bytes);
+			stats.misses += 1;
+			cached += 1;
+			// Nothing is known about the pages after the first page which was not fully read
+			if !complete {
+				break;
+			}
+		}
+		Ok(cached)
+	}
+}
+
+impl<V: VirtualMemory> VirtualMemory for CachedReader<V> {
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		if self.read_cached(address, bytes)? == bytes.len() {
+			Ok(bytes)
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let bytes_read = self.read_cached(address, dest)?;
+		Ok(&mut dest[..bytes_read])
+	}
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		let result = self.inner.vm_write_bytes(address, bytes);
+		self.invalidate_range(address, bytes.len());
+		result
+	}
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]> {
+		let result = self.inner.vm_write_partial(address, bytes);
+		self.invalidate_range(address, bytes.len());
+		result
+	}
+	fn vm_alloc(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect) -> Result<usize> {
+		let result = self.inner.vm_alloc(address, len, alloc_type, protect);
+		if let Ok(address) = result {
+			self.invalidate_range(address, len);
+		}
+		result
+	}
+	fn vm_free(&self, address: usize, len: usize, free_type: FreeType) -> Result<()> {
+		// Releasing frees the whole allocation, its size is not known here
+		self.invalidate();
+		self.inner.vm_free(address, len, free_type)
+	}
+	fn vm_protect(&self, address: usize, len: usize, protect: Protect) -> Result<Protect> {
+		let result = self.inner.vm_protect(address, len, protect);
+		self.invalidate_range(address, len);
+		result
+	}
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		self.inner.vm_query(address)
+	}
+	fn vm_regions<F: FnMut(&MemoryInformation)>(&self, base_address: usize, size: usize, f: F) -> Result<()> {
+		self.inner.vm_regions(base_address, size, f)
+	}
+}
+
+impl<V: fmt::Debug> fmt::Debug for CachedReader<V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("CachedReader")
+			.field("inner", &self.inner)
+			.field("pages", &self.pages.borrow().len())
+			.field("stats", &self.stats.get())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::error::ERROR_PARTIAL_COPY;
+	use super::*;
+
+	#[test]
+	fn read_invalidate() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0xAA; 0x3000], Protect::READ_WRITE).unwrap();
+		let reader = CachedReader::new(process);
+		let mut bytes = [0u8; 0x2000];
+		reader.vm_read_bytes(base + 0x800, &mut bytes).unwrap();
+		assert_eq!(reader.stats(), CacheStats { hits: 0, misses: 3, fetches: 1 });
+
+		// Changes are not visible until invalidated
+		reader.get_ref().poke(base + 0x1000, &[1, 2]).unwrap();
+		let mut two = [0u8; 2];
+		reader.vm_read_bytes(base + 0x1000, &mut two).unwrap();
+		assert_eq!(two, [0xAA, 0xAA]);
+		reader.invalidate_range(base + 0x1001, 1);
+		reader.vm_read_bytes(base + 0x1000, &mut two).unwrap();
+		assert_eq!(two, [1, 2]);
+		assert_eq!(reader.stats(), CacheStats { hits: 1, misses: 4, fetches: 2 });
+
+		// Writes through the reader invalidate
+		reader.vm_write_bytes(base, &[3]).unwrap();
+		reader.vm_read_bytes(base, &mut two).unwrap();
+		assert_eq!(two, [3, 0xAA]);
+		reader.invalidate();
+		assert!(reader.is_empty());
+	}
+
+	#[test]
+	fn partial_copy() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0x55; 0x1000], Protect::READ_ONLY).unwrap();
+		let reader = CachedReader::new(process);
+		let mut bytes = [0u8; 0x20];
+		assert_eq!(reader.vm_read_partial(base + 0xFF0, &mut bytes).map(|bytes| bytes.len()), Ok(0x10));
+		assert_eq!(reader.vm_read_bytes(base + 0xFF0, &mut bytes), Err(ERROR_PARTIAL_COPY));
+		// The unreadable page is cached as well
+		assert_eq!(reader.stats(), CacheStats { hits: 2, misses: 2, fetches: 1 });
+		assert_eq!(reader.vm_read_partial(0, &mut bytes).map(|bytes| bytes.len()), Ok(0));
+	}
+}
@@ -0,0 +1,171 @@
+use std::{cmp, fmt, io};
+use std::io::SeekFrom;
+use crate::error::{ERROR_PARTIAL_COPY};
+use super::VirtualMemory;
+
+/// Cursor over a range of virtual memory.
+///
+/// Implements `Read`, `Write` and `Seek` so remote memory can be streamed into parsers, hashers and files.
+/// The position is relative to the base address, reading or writing stops at the end of the range.
+///
+/// Partially accessible memory results in short reads and writes, as with `vm_read_partial` and `vm_write_partial`.
+/// When no bytes at all can be transferred at the current position the operation fails with `ERROR_PARTIAL_COPY` rather than returning zero, which would be mistaken for the end of the range.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::Read;
+/// use external::vm::{MockProcess, ProcessMemoryCursor, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0, b"hello world", Protect::READ_ONLY).unwrap();
+/// let mut text = String::new();
+/// ProcessMemoryCursor::new(&process, base, 11).read_to_string(&mut text).unwrap();
+/// assert_eq!(text, "hello world");
+/// ```
+pub struct ProcessMemoryCursor<V> {
+	vm: V,
+	base: usize,
+	len: usize,
+	pos: u64,
+}
+impl<V: VirtualMemory> ProcessMemoryCursor<V> {
+	/// Creates a cursor over `len` bytes starting at the base address.
+	pub fn new(vm: V, base: usize, len: usize) -> ProcessMemoryCursor<V> {
+		ProcessMemoryCursor { vm, base, len, pos: 0 }
+	}
+	/// The base address of the range.
+	pub fn base(&self) -> usize {
+		self.base
+	}
+	/// The length of the range, in bytes.
+	pub fn len(&self) -> usize {
+		self.len
+	}
+	/// Returns if the range is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+	/// The current position relative to the base address.
+	pub fn position(&self) -> u64 {
+		self.pos
+	}
+	/// Sets the position relative to the base address.
+	pub fn set_position(&mut self, pos: u64) {
+		self.pos = pos;
+	}
+	/// The current address.
+	pub fn address(&self) -> usize {
+		self.base.wrapping_add(self.pos as usize)
+	}
+	/// Gets a reference to the backend.
+	pub fn get_ref(&self) -> &V {
+		&self.vm
+	}
+	/// Unwraps the backend.
+	pub fn into_inner(self) -> V {
+		self.vm
+	}
+	// The number of bytes left in the range, at most `max`.
+	fn remaining(&self, max: usize) -> usize {
+		if self.pos >= self.len as u64 { 0 } else { cmp::min(max, self.len - self.pos as usize) }
+	}
+}
+impl<V: VirtualMemory> io::Read for ProcessMemoryCursor<V> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		let len = self.remaining(buf.len());
+		if len == 0 {
+			return Ok(0);
+		}
+		let bytes_read = self.vm.vm_read_partial(self.address(), &mut buf[..len])?.len();
+		if bytes_read == 0 {
+			return Err(ERROR_PARTIAL_COPY.into());
+		}
+		self.pos += bytes_read as u64;
+		Ok(bytes_read)
+	}
+}
+impl<V: VirtualMemory> io::Write for ProcessMemoryCursor<V> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let len = self.remaining(buf.len());
+		if len == 0 {
+			return Ok(0);
+		}
+		let bytes_written = self.vm.vm_write_partial(self.address(), &buf[..len])?.len();
+		if bytes_written == 0 {
+			return Err(ERROR_PARTIAL_COPY.into());
+		}
+		self.pos += bytes_written as u64;
+		Ok(bytes_written)
+	}
+	fn flush(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+}
+impl<V> io::Seek for ProcessMemoryCursor<V> {
+	fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+		let (base, offset) = match pos {
+			SeekFrom::Start(pos) => {
+				self.pos = pos;
+				return Ok(pos);
+			},
+			SeekFrom::End(offset) => (self.len as u64, offset),
+			SeekFrom::Current(offset) => (self.pos, offset),
+		};
+		match base.checked_add_signed(offset) {
+			Some(pos) => {
+				self.pos = pos;
+				Ok(pos)
+			},
+			None => Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")),
+		}
+	}
+}
+impl<V: fmt::Debug> fmt::Debug for ProcessMemoryCursor<V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ProcessMemoryCursor")
+			.field("vm", &self.vm)
+			.field("base", &format_args!("{:#x}", self.base))
+			.field("len", &format_args!("{:#x}", self.len))
+			.field("pos", &self.pos)
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::io::{self, Read, Seek, SeekFrom, Write};
+	use crate::error::ErrorCode;
+	use super::super::*;
+	use super::*;
+
+	#[test]
+	fn read_write_seek() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0x11; 0x2000], Protect::READ_WRITE).unwrap();
+		process.vm_protect(base + 0x1000, 0x1000, Protect::NO_ACCESS).unwrap();
+		let mut cursor = ProcessMemoryCursor::new(&process, base + 0xFF0, 0x20);
+
+		// Short read up to the inaccessible page, then an error instead of end of file
+		let mut buf = [0u8; 0x20];
+		assert_eq!(cursor.read(&mut buf).unwrap(), 0x10);
+		let err = cursor.read(&mut buf).unwrap_err();
+		assert_eq!(ErrorCode::from(err), ERROR_PARTIAL_COPY);
+
+		assert_eq!(cursor.seek(SeekFrom::Current(-4)).unwrap(), 0xC);
+		assert_eq!(cursor.write(&[1, 2, 3, 4, 5, 6]).unwrap(), 4);
+		assert_eq!(cursor.seek(SeekFrom::End(0)).unwrap(), 0x20);
+		assert_eq!(cursor.read(&mut buf).unwrap(), 0);
+		assert!(cursor.seek(SeekFrom::Current(-0x21)).is_err());
+
+		let mut cursor = ProcessMemoryCursor::new(&process, base + 0xFF8, 8);
+		let mut bytes = Vec::new();
+		cursor.read_to_end(&mut bytes).unwrap();
+		assert_eq!(bytes, [0x11, 0x11, 0x11, 0x11, 1, 2, 3, 4]);
+
+		let mut sink = ProcessMemoryCursor::new(&process, base, 0x10);
+		assert_eq!(io::copy(&mut &[0x22u8; 0x18][..], &mut sink).unwrap_err().kind(), io::ErrorKind::WriteZero);
+	}
+}
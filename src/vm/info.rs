@@ -0,0 +1,106 @@
+use std::fmt;
+#[cfg(windows)]
+use crate::winapi::*;
+use crate::FromInner;
+use super::{Protect, MemoryState, MemoryType};
+
+//----------------------------------------------------------------
+
+/// Information about a region of pages sharing the same attributes.
+///
+/// See [MEMORY_BASIC_INFORMATION](https://msdn.microsoft.com/en-us/library/windows/desktop/aa366775.aspx) for more information.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryInformation {
+	pub base_address: usize,
+	pub allocation_base: usize,
+	pub allocation_protect: Protect,
+	pub region_size: usize,
+	pub state: MemoryState,
+	pub protect: Protect,
+	pub mem_type: MemoryType,
+}
+impl MemoryInformation {
+	/// Returns if the region is committed and its pages can be read.
+	pub fn is_readable(&self) -> bool {
+		self.state == MemoryState::COMMIT && self.protect.is_readable() && !self.protect.has_guard()
+	}
+}
+#[cfg(windows)]
+impl From<MEMORY_BASIC_INFORMATION> for MemoryInformation {
+	fn from(mbi: MEMORY_BASIC_INFORMATION) -> MemoryInformation {
+		unsafe {
+			MemoryInformation {
+				base_address: mbi.BaseAddress as usize,
+				allocation_base: mbi.AllocationBase as usize,
+				allocation_protect: Protect::from_inner(mbi.AllocationProtect),
+				region_size: mbi.RegionSize,
+				state: MemoryState::from_inner(mbi.State),
+				protect: Protect::from_inner(mbi.Protect),
+				mem_type: MemoryType::from_inner(mbi.Type),
+			}
+		}
+	}
+}
+
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForEachAllocation {
+	pub allocation_base: usize,
+	pub allocation_size: usize,
+	pub allocation_protect: u32,
+	pub regions_state: u32,
+	pub regions_protect: u32,
+	pub regions_type: u32,
+}
+
+#[derive(Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorkingSetExBlock(usize);
+impl_inner!(WorkingSetExBlock: usize);
+#[cfg(windows)]
+impl From<PSAPI_WORKING_SET_EX_BLOCK> for WorkingSetExBlock {
+	fn from(ws_ex_block: PSAPI_WORKING_SET_EX_BLOCK) -> WorkingSetExBlock {
+		WorkingSetExBlock(ws_ex_block.Flags)
+	}
+}
+impl WorkingSetExBlock {
+	pub fn valid(&self) -> bool {
+		self.0 & 1 != 0
+	}
+	pub fn share_count(&self) -> u32 {
+		((self.0 >> 1) & 0x7) as u32
+	}
+	pub fn win32_protection(&self) -> Protect {
+		unsafe { Protect::from_inner((self.0 >> 4) as u32) }
+	}
+	pub fn shared(&self) -> bool {
+		self.0 & (1 << 15) != 0
+	}
+	pub fn node(&self) -> u32 {
+		((self.0 >> 16) & 0x3f) as u32
+	}
+	pub fn locked(&self) -> bool {
+		self.0 & (1 << 22) != 0
+	}
+	pub fn large_page(&self) -> bool {
+		self.0 & (1 << 23) != 0
+	}
+	pub fn bad(&self) -> bool {
+		self.0 & (1 << 31) != 0
+	}
+}
+impl fmt::Debug for WorkingSetExBlock {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("WorkingSetExBlock")
+			.field("valid", &self.valid())
+			.field("share_count", &self.share_count())
+			.field("win32_protection", &self.win32_protection())
+			.field("shared", &self.shared())
+			.field("node", &self.node())
+			.field("locked", &self.locked())
+			.field("large_page", &self.large_page())
+			.field("bad", &self.bad())
+			.finish()
+	}
+}
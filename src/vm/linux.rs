@@ -0,0 +1,268 @@
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+use crate::process::Process;
+use crate::error::{ERROR_INVALID_ADDRESS, ERROR_INVALID_PARAMETER, ERROR_NOT_SUPPORTED, ERROR_PARTIAL_COPY};
+use crate::{Result, IntoInner};
+use super::*;
+
+// The maximum number of ranges passed to `process_vm_readv`.
+const IOV_MAX: usize = 1024;
+
+//----------------------------------------------------------------
+
+impl Process {
+	// Reads as many bytes as are available, returns the number of bytes read.
+	fn vm_readv(&self, address: usize, dest: &mut [u8]) -> Result<usize> {
+		if dest.is_empty() {
+			return Ok(0);
+		}
+		let local = libc::iovec { iov_base: dest.as_mut_ptr() as *mut libc::c_void, iov_len: dest.len() };
+		let remote = libc::iovec { iov_base: address as *mut libc::c_void, iov_len: dest.len() };
+		let result = unsafe { libc::process_vm_readv(self.pid.into_inner() as libc::pid_t, &local, 1, &remote, 1, 0) };
+		if result >= 0 {
+			return Ok(result as usize);
+		}
+		let err = io::Error::last_os_error();
+		match (err.raw_os_error(), &self.mem) {
+			// The syscall is missing or filtered by seccomp, the mem file does not require it
+			(Some(libc::ENOSYS), Some(mem)) | (Some(libc::EPERM), Some(mem)) => {
+				let mut done = 0;
+				while done < dest.len() {
+					match mem.read_at(&mut dest[done..], (address + done) as u64) {
+						Ok(0) => break,
+						Ok(n) => done += n,
+						Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+						Err(ref err) if err.raw_os_error() == Some(libc::EIO) => break,
+						Err(err) => return Err(err.into()),
+					}
+				}
+				Ok(done)
+			},
+			// The first page is not accessible
+			(Some(libc::EFAULT), _) => Ok(0),
+			_ => Err(err.into()),
+		}
+	}
+	// Writes as many bytes as it can, returns the number of bytes written.
+	fn vm_writev(&self, address: usize, src: &[u8]) -> Result<usize> {
+		if src.is_empty() {
+			return Ok(0);
+		}
+		let local = libc::iovec { iov_base: src.as_ptr() as *mut libc::c_void, iov_len: src.len() };
+		let remote = libc::iovec { iov_base: address as *mut libc::c_void, iov_len: src.len() };
+		let result = unsafe { libc::process_vm_writev(self.pid.into_inner() as libc::pid_t, &local, 1, &remote, 1, 0) };
+		if result >= 0 {
+			return Ok(result as usize);
+		}
+		let err = io::Error::last_os_error();
+		match (err.raw_os_error(), &self.mem) {
+			// Unlike `process_vm_writev` the mem file can also write to read-only pages
+			(Some(libc::ENOSYS), Some(mem)) | (Some(libc::EPERM), Some(mem)) | (Some(libc::EFAULT), Some(mem)) => {
+				let mut done = 0;
+				while done < src.len() {
+					match mem.write_at(&src[done..], (address + done) as u64) {
+						Ok(0) => break,
+						Ok(n) => done += n,
+						Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+						Err(ref err) if err.raw_os_error() == Some(libc::EIO) => break,
+						Err(err) => return Err(err.into()),
+					}
+				}
+				Ok(done)
+			},
+			(Some(libc::EFAULT), None) => Ok(0),
+			_ => Err(err.into()),
+		}
+	}
+}
+
+/// Virtual memory API.
+///
+/// Allocating, freeing and changing the protection of memory in another process is not supported on Linux.
+impl VirtualMemory for Process {
+	#[inline]
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		if self.vm_readv(address, bytes)? == bytes.len() {
+			Ok(bytes)
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	#[inline]
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let bytes_read = self.vm_readv(address, dest)?;
+		Ok(&mut dest[..bytes_read])
+	}
+	#[inline]
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		if self.vm_writev(address, bytes)? == bytes.len() {
+			Ok(())
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	#[inline]
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]> {
+		let bytes_written = self.vm_writev(address, bytes)?;
+		Ok(&bytes[..bytes_written])
+	}
+	/// Reads the ranges with `process_vm_readv`, passing up to 1024 ranges per call.
+	fn vm_read_scatter(&self, iov: &mut [(usize, &mut [u8])]) -> Result<usize> {
+		let mut total = 0;
+		for chunk in iov.chunks_mut(IOV_MAX) {
+			let len: usize = chunk.iter().map(|(_, dest)| dest.len()).sum();
+			let remote: Vec<libc::iovec> = chunk.iter().map(|(address, dest)| libc::iovec { iov_base: *address as *mut libc::c_void, iov_len: dest.len() }).collect();
+			let local: Vec<libc::iovec> = chunk.iter_mut().map(|(_, dest)| libc::iovec { iov_base: dest.as_mut_ptr() as *mut libc::c_void, iov_len: dest.len() }).collect();
+			let result = unsafe { libc::process_vm_readv(self.pid.into_inner() as libc::pid_t, local.as_ptr(), local.len() as libc::c_ulong, remote.as_ptr(), remote.len() as libc::c_ulong, 0) };
+			let read = if result >= 0 {
+				result as usize
+			}
+			else {
+				let err = io::Error::last_os_error();
+				match err.raw_os_error() {
+					// Read the ranges one by one, `vm_readv` falls back to the mem file
+					Some(libc::ENOSYS) | Some(libc::EPERM) => {
+						let mut read = 0;
+						for (address, dest) in chunk.iter_mut() {
+							let n = self.vm_readv(*address, dest)?;
+							read += n;
+							if n < dest.len() {
+								break;
+							}
+						}
+						read
+					},
+					// The first range is not accessible
+					Some(libc::EFAULT) => 0,
+					_ => return Err(err.into()),
+				}
+			};
+			total += read;
+			if read < len {
+				break;
+			}
+		}
+		Ok(total)
+	}
+	fn vm_alloc(&self, _address: usize, _len: usize, _alloc_type: AllocType, _protect: Protect) -> Result<usize> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	fn vm_free(&self, _address: usize, _len: usize, _free_type: FreeType) -> Result<()> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	fn vm_protect(&self, _address: usize, _len: usize, _protect: Protect) -> Result<Protect> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	/// Queries the state of virtual memory in the process.
+	///
+	/// Each query parses the process' maps file, prefer `vm_regions` to walk the address space.
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		ProcMaps::read(self.pid)?.query(address).ok_or(ERROR_INVALID_PARAMETER)
+	}
+	#[inline]
+	fn vm_regions<F: FnMut(&MemoryInformation)>(&self, mut base_address: usize, size: usize, mut f: F) -> Result<()> {
+		let maps = ProcMaps::read(self.pid)?;
+		let end_address = base_address.saturating_add(size);
+		while base_address < end_address {
+			let mi = maps.query(base_address).ok_or(ERROR_INVALID_PARAMETER)?;
+			f(&mi);
+			base_address = mi.base_address + mi.region_size;
+		}
+		Ok(())
+	}
+}
+
+/// Linux specific virtual memory API.
+impl Process {
+	/// Foreach virtual memory allocation and associated mapped file.
+	///
+	/// Adjacent mappings of the same file are reported as a single allocation, free address space is skipped.
+	pub fn vm_allocations<F: FnMut(&ForEachAllocation, Option<&Path>)>(&self, mut f: F) -> Result<()> {
+		let maps = ProcMaps::read(self.pid)?;
+		let mut index = 0;
+		while index < maps.len() {
+			let allocation_base = maps.allocation_base(index);
+			let first = &maps[index];
+			let mut allocation = ForEachAllocation {
+				allocation_base,
+				allocation_protect: first.protect().into_inner(),
+				..ForEachAllocation::default()
+			};
+			while index < maps.len() && maps.allocation_base(index) == allocation_base {
+				let entry = &maps[index];
+				allocation.allocation_size += entry.size();
+				allocation.regions_state |= entry.state().into_inner();
+				allocation.regions_protect |= entry.protect().into_inner();
+				allocation.regions_type |= entry.mem_type().into_inner();
+				index += 1;
+			}
+			f(&allocation, first.path.as_deref());
+		}
+		Ok(())
+	}
+	/// Get the path of the file mapped at the address.
+	pub fn mapped_file_name(&self, address: usize) -> Result<PathBuf> {
+		ProcMaps::read(self.pid)?
+			.find(address)
+			.and_then(|entry| entry.path.clone())
+			.ok_or(ERROR_INVALID_ADDRESS)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::process::{ProcessRights};
+	use crate::ptr::Ptr;
+	use super::*;
+
+	#[test]
+	fn read_write_self() {
+		let process = Process::attach(Process::current().pid().unwrap(), ProcessRights::new().vm_read().vm_write()).unwrap();
+		let value = Box::new(0x1234_5678u32);
+		let ptr = Ptr::<u32>::from(&*value as *const u32 as usize as u64);
+		assert_eq!(process.vm_read(ptr), Ok(0x1234_5678));
+		process.vm_write(ptr, &42).unwrap();
+		assert_eq!(unsafe { std::ptr::read_volatile(&*value) }, 42);
+		let mut bytes = [0u8; 4];
+		assert_eq!(process.vm_read_partial(0, &mut bytes).map(|bytes| bytes.len()), Ok(0));
+		assert_eq!(process.vm_read_bytes(0, &mut bytes), Err(ERROR_PARTIAL_COPY));
+	}
+
+	#[test]
+	fn query_self() {
+		let process = Process::current();
+		let value = Box::new(0u64);
+		let mi = process.vm_query(&*value as *const u64 as usize).unwrap();
+		assert_eq!(mi.state, MemoryState::COMMIT);
+		assert!(mi.protect.is_readable() && mi.protect.is_writable());
+		let mut regions = 0;
+		process.vm_regions(0, usize::MAX, |mi| regions += (mi.state != MemoryState::FREE) as usize).unwrap_err();
+		let mut allocations = 0;
+		process.vm_allocations(|_, _| allocations += 1).unwrap();
+		assert!(allocations > 0 && allocations <= regions);
+		let code = query_self as *const () as usize;
+		assert!(process.vm_query(code).unwrap().protect.is_executable());
+		assert!(process.mapped_file_name(code).is_ok());
+	}
+
+	#[test]
+	fn read_batch_self() {
+		let process = Process::attach(Process::current().pid().unwrap(), ProcessRights::new().vm_read()).unwrap();
+		let values: Vec<u64> = (0..64).collect();
+		let mut dest = vec![0u64; 64];
+		let mut missing = [0u8; 8];
+		let mut requests: Vec<_> = dest.iter_mut().enumerate()
+			.map(|(i, dest)| ReadRequest::from_ptr(Ptr::<u64>::from(&values[63 - i] as *const u64 as usize as u64), dest))
+			.collect();
+		requests.push(ReadRequest::new(0x1000, &mut missing));
+		process.vm_read_batch(&mut requests);
+		assert!(requests[..64].iter().all(|request| request.result().is_ok()));
+		assert_eq!(requests[64].result(), Err(ERROR_PARTIAL_COPY));
+		assert!(dest.iter().rev().eq(values.iter()));
+	}
+}
@@ -0,0 +1,338 @@
+use std::{fs, ops, slice};
+use std::path::{Path, PathBuf};
+use crate::error::ERROR_INVALID_DATA;
+#[cfg(target_os = "linux")]
+use crate::process::ProcessId;
+use crate::Result;
+use super::{MemoryInformation, MemoryState, MemoryType, Protect};
+
+//----------------------------------------------------------------
+
+/// Memory usage of a mapping from the `smaps` file, in bytes.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct MapsUsage {
+	pub size: u64,
+	pub rss: u64,
+	pub pss: u64,
+	pub shared_clean: u64,
+	pub shared_dirty: u64,
+	pub private_clean: u64,
+	pub private_dirty: u64,
+	pub swap: u64,
+}
+
+/// Memory mapping of a process.
+///
+/// See [proc(5)](http://man7.org/linux/man-pages/man5/proc.5.html) for more information.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MapsEntry {
+	pub start: usize,
+	pub end: usize,
+	pub read: bool,
+	pub write: bool,
+	pub exec: bool,
+	pub shared: bool,
+	pub offset: u64,
+	pub dev_major: u32,
+	pub dev_minor: u32,
+	pub inode: u64,
+	/// The mapped file or a pseudo-path such as `[heap]` or `[stack]`.
+	pub path: Option<PathBuf>,
+	/// Only available when parsed from an `smaps` file.
+	pub usage: Option<MapsUsage>,
+}
+impl MapsEntry {
+	/// The size of the mapping, in bytes.
+	pub fn size(&self) -> usize {
+		self.end - self.start
+	}
+	/// Returns if the mapping contains the address.
+	pub fn contains(&self, address: usize) -> bool {
+		address >= self.start && address < self.end
+	}
+	/// Returns if the mapping is backed by a file.
+	pub fn is_file_backed(&self) -> bool {
+		self.inode != 0
+	}
+	/// Translates the permissions to the closest memory protection.
+	pub fn protect(&self) -> Protect {
+		match (self.read, self.write, self.exec) {
+			(false, false, false) => Protect::NO_ACCESS,
+			(true, false, false) => Protect::READ_ONLY,
+			(_, true, false) => Protect::READ_WRITE,
+			(false, false, true) => Protect::EXECUTE,
+			(true, false, true) => Protect::EXECUTE_READ,
+			(_, true, true) => Protect::EXECUTE_READ_WRITE,
+		}
+	}
+	/// Classifies the mapping.
+	///
+	/// Shared mappings are `MAPPED`, private file backed mappings are `IMAGE` and private anonymous mappings are `PRIVATE`.
+	pub fn mem_type(&self) -> MemoryType {
+		if self.shared {
+			MemoryType::MAPPED
+		}
+		else if self.is_file_backed() {
+			MemoryType::IMAGE
+		}
+		else {
+			MemoryType::PRIVATE
+		}
+	}
+	/// Mappings without any access are reserved address space, eg. guard pages and reservations made with `PROT_NONE`.
+	pub fn state(&self) -> MemoryState {
+		if self.read || self.write || self.exec { MemoryState::COMMIT } else { MemoryState::RESERVE }
+	}
+
+	fn parse(line: &str) -> Option<MapsEntry> {
+		let mut fields = line.splitn(6, ' ');
+		let range = fields.next()?;
+		let perms = fields.next()?.as_bytes();
+		let offset = fields.next()?;
+		let dev = fields.next()?;
+		let inode = fields.next()?;
+		let path = fields.next().map(str::trim_start).filter(|path| !path.is_empty());
+		let (start, end) = split2(range, '-')?;
+		let (dev_major, dev_minor) = split2(dev, ':')?;
+		if perms.len() != 4 {
+			return None;
+		}
+		let entry = MapsEntry {
+			start: usize::from_str_radix(start, 16).ok()?,
+			end: usize::from_str_radix(end, 16).ok()?,
+			read: perms[0] == b'r',
+			write: perms[1] == b'w',
+			exec: perms[2] == b'x',
+			shared: perms[3] == b's',
+			offset: u64::from_str_radix(offset, 16).ok()?,
+			dev_major: u32::from_str_radix(dev_major, 16).ok()?,
+			dev_minor: u32::from_str_radix(dev_minor, 16).ok()?,
+			inode: inode.parse().ok()?,
+			path: path.map(PathBuf::from),
+			usage: None,
+		};
+		if entry.start > entry.end {
+			return None;
+		}
+		Some(entry)
+	}
+}
+
+fn split2(s: &str, sep: char) -> Option<(&str, &str)> {
+	let mut parts = s.splitn(2, sep);
+	Some((parts.next()?, parts.next()?))
+}
+
+//----------------------------------------------------------------
+
+/// Memory mappings of a process.
+///
+/// Parses the contents of `/proc/<pid>/maps` or `/proc/<pid>/smaps`, live or previously saved to a file.
+///
+/// # Examples
+///
+/// ```
+/// use external::vm::{ProcMaps, MemoryType};
+///
+/// let maps = ProcMaps::parse("\
+/// 00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon
+/// 00452000-00453000 rw-p 00052000 08:02 173521      /usr/bin/dbus-daemon
+/// 00e03000-00e24000 rw-p 00000000 00:00 0           [heap]
+/// ").unwrap();
+/// let mi = maps.query(0x452010).unwrap();
+/// assert_eq!(mi.allocation_base, 0x400000);
+/// assert_eq!(mi.mem_type, MemoryType::IMAGE);
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProcMaps(Vec<MapsEntry>);
+impl ProcMaps {
+	/// Parses the contents of a `maps` or `smaps` file.
+	pub fn parse(text: &str) -> Result<ProcMaps> {
+		let mut entries: Vec<MapsEntry> = Vec::new();
+		for line in text.lines().filter(|line| !line.trim().is_empty()) {
+			// Lines in smaps following the mapping look like `Rss:  4 kB`
+			if let Some(entry) = entries.last_mut() {
+				if let Some((key, value)) = split2(line, ':') {
+					if key.bytes().all(|c| c.is_ascii_alphanumeric() || c == b'_') {
+						parse_usage(entry.usage.get_or_insert_with(MapsUsage::default), key, value);
+						continue;
+					}
+				}
+			}
+			let entry = MapsEntry::parse(line).ok_or(ERROR_INVALID_DATA)?;
+			if entries.last().is_some_and(|last| last.end > entry.start) {
+				return Err(ERROR_INVALID_DATA);
+			}
+			entries.push(entry);
+		}
+		Ok(ProcMaps(entries))
+	}
+	/// Loads a saved `maps` or `smaps` file.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<ProcMaps> {
+		let bytes = fs::read(path)?;
+		ProcMaps::parse(&String::from_utf8_lossy(&bytes))
+	}
+	/// Reads the mappings of a live process.
+	#[cfg(target_os = "linux")]
+	pub fn read(pid: ProcessId) -> Result<ProcMaps> {
+		ProcMaps::load(format!("/proc/{}/maps", pid))
+	}
+	/// Reads the mappings and their memory usage of a live process.
+	#[cfg(target_os = "linux")]
+	pub fn read_smaps(pid: ProcessId) -> Result<ProcMaps> {
+		ProcMaps::load(format!("/proc/{}/smaps", pid))
+	}
+	/// Finds the index of the mapping containing the address.
+	pub fn position(&self, address: usize) -> Option<usize> {
+		let index = self.0.partition_point(|entry| entry.end <= address);
+		self.0.get(index).filter(|entry| entry.contains(address)).map(|_| index)
+	}
+	/// Finds the mapping containing the address.
+	pub fn find(&self, address: usize) -> Option<&MapsEntry> {
+		self.position(address).map(|index| &self.0[index])
+	}
+	/// The start of the allocation the mapping at the index belongs to.
+	///
+	/// Adjacent mappings of the same file are considered a single allocation, like the sections of a Windows image.
+	pub fn allocation_base(&self, index: usize) -> usize {
+		let entry = &self.0[index];
+		if !entry.is_file_backed() {
+			return entry.start;
+		}
+		let mut base = entry.start;
+		for prev in self.0[..index].iter().rev() {
+			if prev.end != base || prev.inode != entry.inode || prev.dev_major != entry.dev_major || prev.dev_minor != entry.dev_minor {
+				break;
+			}
+			base = prev.start;
+		}
+		base
+	}
+	/// Queries the region containing the address.
+	///
+	/// Addresses between mappings are in a free region, returns `None` past the last mapping.
+	pub fn query(&self, address: usize) -> Option<MemoryInformation> {
+		let index = self.0.partition_point(|entry| entry.end <= address);
+		let entry = self.0.get(index)?;
+		if entry.contains(address) {
+			Some(MemoryInformation {
+				base_address: entry.start,
+				allocation_base: self.allocation_base(index),
+				allocation_protect: self.0[self.position(self.allocation_base(index))?].protect(),
+				region_size: entry.size(),
+				state: entry.state(),
+				protect: entry.protect(),
+				mem_type: entry.mem_type(),
+			})
+		}
+		else {
+			let base_address = address & !0xfff;
+			Some(MemoryInformation {
+				base_address,
+				allocation_base: 0,
+				allocation_protect: Protect::default(),
+				region_size: entry.start - base_address,
+				state: MemoryState::FREE,
+				protect: Protect::NO_ACCESS,
+				mem_type: MemoryType::default(),
+			})
+		}
+	}
+}
+impl ops::Deref for ProcMaps {
+	type Target = [MapsEntry];
+	fn deref(&self) -> &[MapsEntry] {
+		&self.0
+	}
+}
+impl<'a> IntoIterator for &'a ProcMaps {
+	type Item = &'a MapsEntry;
+	type IntoIter = slice::Iter<'a, MapsEntry>;
+	fn into_iter(self) -> slice::Iter<'a, MapsEntry> {
+		self.0.iter()
+	}
+}
+
+fn parse_usage(usage: &mut MapsUsage, key: &str, value: &str) {
+	let field = match key {
+		"Size" => &mut usage.size,
+		"Rss" => &mut usage.rss,
+		"Pss" => &mut usage.pss,
+		"Shared_Clean" => &mut usage.shared_clean,
+		"Shared_Dirty" => &mut usage.shared_dirty,
+		"Private_Clean" => &mut usage.private_clean,
+		"Private_Dirty" => &mut usage.private_dirty,
+		"Swap" => &mut usage.swap,
+		_ => return,
+	};
+	let mut value = value.split_whitespace();
+	if let (Some(n), Some("kB")) = (value.next().and_then(|n| n.parse::<u64>().ok()), value.next()) {
+		*field = n * 1024;
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	static MAPS: &str = "\
+55d4c5a00000-55d4c5a02000 r--p 00000000 fd:01 1234                       /usr/bin/cat
+55d4c5a02000-55d4c5a07000 r-xp 00002000 fd:01 1234                       /usr/bin/cat
+55d4c5a0a000-55d4c5a0b000 rw-p 00009000 fd:01 1234                       /usr/bin/cat
+55d4c6c1e000-55d4c6c3f000 rw-p 00000000 00:00 0                          [heap]
+7f1e2c000000-7f1e2c021000 rw-s 00000000 00:05 98765                      /dev/shm/my file (deleted)
+7f1e2c021000-7f1e30000000 ---p 00000000 00:00 0
+7ffd8a1f0000-7ffd8a211000 rw-p 00000000 00:00 0                          [stack]
+";
+
+	#[test]
+	fn parse() {
+		let maps = ProcMaps::parse(MAPS).unwrap();
+		assert_eq!(maps.len(), 7);
+		let cat = &maps[1];
+		assert_eq!((cat.start, cat.end, cat.offset, cat.inode), (0x55d4c5a02000, 0x55d4c5a07000, 0x2000, 1234));
+		assert_eq!((cat.dev_major, cat.dev_minor), (0xfd, 0x01));
+		assert_eq!(cat.protect(), Protect::EXECUTE_READ);
+		assert_eq!(cat.mem_type(), MemoryType::IMAGE);
+		assert_eq!(maps[3].path.as_deref(), Some(Path::new("[heap]")));
+		assert_eq!(maps[3].mem_type(), MemoryType::PRIVATE);
+		assert_eq!(maps[4].path.as_deref(), Some(Path::new("/dev/shm/my file (deleted)")));
+		assert_eq!(maps[4].mem_type(), MemoryType::MAPPED);
+		assert_eq!(maps[5].path, None);
+		assert_eq!(maps[5].state(), MemoryState::RESERVE);
+		assert!(ProcMaps::parse("00400000 r-xp 00000000 08:02 1").is_err());
+	}
+
+	#[test]
+	fn query() {
+		let maps = ProcMaps::parse(MAPS).unwrap();
+		// Adjacent sections of the file are a single allocation, the gap separates the writable section
+		let mi = maps.query(0x55d4c5a03010).unwrap();
+		assert_eq!((mi.base_address, mi.allocation_base, mi.region_size), (0x55d4c5a02000, 0x55d4c5a00000, 0x5000));
+		assert_eq!(mi.allocation_protect, Protect::READ_ONLY);
+		assert_eq!(maps.query(0x55d4c5a0a000).unwrap().allocation_base, 0x55d4c5a0a000);
+		let free = maps.query(0x55d4c5a08123).unwrap();
+		assert_eq!((free.base_address, free.region_size, free.state), (0x55d4c5a08000, 0x2000, MemoryState::FREE));
+		assert_eq!(maps.query(0).unwrap().region_size, 0x55d4c5a00000);
+		assert_eq!(maps.query(0x7ffd8a211000), None);
+	}
+
+	#[test]
+	fn smaps() {
+		let maps = ProcMaps::parse("\
+00400000-00452000 r-xp 00000000 08:02 173521      /usr/bin/dbus-daemon
+Size:                328 kB
+Rss:                 256 kB
+Swap:                  0 kB
+VmFlags: rd ex mr mw me dw
+00e03000-00e24000 rw-p 00000000 00:00 0           [heap]
+Size:                132 kB
+Private_Dirty:         8 kB
+").unwrap();
+		assert_eq!(maps.len(), 2);
+		assert_eq!(maps[0].usage.map(|usage| (usage.size, usage.rss)), Some((328 * 1024, 256 * 1024)));
+		assert_eq!(maps[1].usage.map(|usage| usage.private_dirty), Some(8 * 1024));
+	}
+}
@@ -0,0 +1,470 @@
+use std::{cmp, fmt};
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use crate::error::{ERROR_INVALID_ADDRESS, ERROR_INVALID_PARAMETER, ERROR_NOT_ENOUGH_MEMORY, ERROR_PARTIAL_COPY};
+use crate::Result;
+use super::*;
+
+const PAGE_SIZE: usize = 0x1000;
+const ALLOCATION_GRANULARITY: usize = 0x10000;
+const MIN_ADDRESS: usize = 0x10000;
+#[cfg(target_pointer_width = "64")]
+const MAX_ADDRESS: usize = 0x7FFF_FFFF_0000;
+#[cfg(target_pointer_width = "32")]
+const MAX_ADDRESS: usize = 0x7FFF_0000;
+
+fn page_floor(address: usize) -> usize {
+	address & !(PAGE_SIZE - 1)
+}
+fn page_ceil(address: usize) -> usize {
+	address.checked_add(PAGE_SIZE - 1).map_or(usize::MAX & !(PAGE_SIZE - 1), page_floor)
+}
+
+//----------------------------------------------------------------
+
+struct Page {
+	allocation_base: usize,
+	allocation_protect: Protect,
+	mem_type: MemoryType,
+	protect: Protect,
+	// Only committed pages have contents.
+	bytes: Option<Box<[u8]>>,
+}
+impl Page {
+	fn is_committed(&self) -> bool {
+		self.bytes.is_some()
+	}
+	fn is_readable(&self) -> bool {
+		self.bytes.is_some() && self.protect.is_readable() && !self.protect.has_guard()
+	}
+	fn is_writable(&self) -> bool {
+		self.bytes.is_some() && self.protect.is_writable() && !self.protect.has_guard()
+	}
+}
+
+/// Simulated process virtual memory.
+///
+/// Models a sparse address space of page granular allocations following the semantics of the Windows virtual memory API.
+/// Pages which are free, reserved, inaccessible or guarded fail to read or write with `ERROR_PARTIAL_COPY`.
+///
+/// Implements [`VirtualMemory`](trait.VirtualMemory.html) so code written against the trait can be tested without a live process.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr;
+/// use external::vm::{MockProcess, Protect, VirtualMemory};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, &42u32.to_le_bytes(), Protect::READ_ONLY).unwrap();
+/// assert_eq!(process.vm_read(Ptr::<u32>::from(base as u64)), Ok(42));
+/// assert!(process.vm_write(Ptr::<u32>::from(base as u64), &13).is_err());
+/// ```
+pub struct MockProcess {
+	pages: RwLock<BTreeMap<usize, Page>>,
+}
+impl MockProcess {
+	/// Creates an empty address space.
+	pub fn new() -> MockProcess {
+		MockProcess { pages: RwLock::new(BTreeMap::new()) }
+	}
+	/// Allocates and commits private memory initialized with the given bytes.
+	///
+	/// The address may be zero to let the mock pick a free address, returns the base address of the allocation.
+	pub fn map(&self, address: usize, bytes: &[u8], protect: Protect) -> Result<usize> {
+		self.map_type(address, bytes, protect, MemoryType::PRIVATE)
+	}
+	/// Allocates and commits memory of the given type initialized with the given bytes.
+	pub fn map_type(&self, address: usize, bytes: &[u8], protect: Protect, mem_type: MemoryType) -> Result<usize> {
+		let len = cmp::max(bytes.len(), 1);
+		let base = self.allocate(address, len, AllocType::RESERVE.with(AllocType::COMMIT), protect, mem_type)?;
+		self.poke(if address != 0 { address } else { base }, bytes)?;
+		Ok(base)
+	}
+	/// Writes bytes to committed memory regardless of its protection.
+	pub fn poke(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		if self.copy_to(address, bytes, Page::is_committed) == bytes.len() {
+			Ok(())
+		}
+		else {
+			Err(ERROR_INVALID_ADDRESS)
+		}
+	}
+
+	// Reads from the address until the end of the destination or the first unreadable page.
+	fn copy_from(&self, address: usize, dest: &mut [u8]) -> usize {
+		let pages = self.pages.read().unwrap();
+		let mut done = 0;
+		while done < dest.len() {
+			let current = match address.checked_add(done) { Some(current) => current, None => break };
+			let page = match pages.get(&page_floor(current)) {
+				Some(page) if page.is_readable() => page,
+				_ => break,
+			};
+			let offset = current - page_floor(current);
+			let n = cmp::min(PAGE_SIZE - offset, dest.len() - done);
+			if let Some(bytes) = &page.bytes {
+				dest[done..done + n].copy_from_slice(&bytes[offset..offset + n]);
+			}
+			done += n;
+		}
+		done
+	}
+	// Writes to the address until the end of the source or the first page rejected by the filter.
+	fn copy_to(&self, address: usize, src: &[u8], filter: fn(&Page) -> bool) -> usize {
+		let mut pages = self.pages.write().unwrap();
+		let mut done = 0;
+		while done < src.len() {
+			let current = match address.checked_add(done) { Some(current) => current, None => break };
+			let page = match pages.get_mut(&page_floor(current)) {
+				Some(page) if filter(page) => page,
+				_ => break,
+			};
+			let offset = current - page_floor(current);
+			let n = cmp::min(PAGE_SIZE - offset, src.len() - done);
+			if let Some(bytes) = &mut page.bytes {
+				bytes[offset..offset + n].copy_from_slice(&src[done..done + n]);
+			}
+			done += n;
+		}
+		done
+	}
+	// Finds a free range aligned to the allocation granularity.
+	fn find_free(pages: &BTreeMap<usize, Page>, size: usize) -> Result<usize> {
+		let mut candidate = MIN_ADDRESS;
+		loop {
+			let end = match candidate.checked_add(size) {
+				Some(end) if end <= MAX_ADDRESS => end,
+				_ => return Err(ERROR_NOT_ENOUGH_MEMORY),
+			};
+			match pages.range(candidate..end).next_back() {
+				Some((&address, _)) => {
+					let next = address + PAGE_SIZE;
+					candidate = (next + ALLOCATION_GRANULARITY - 1) & !(ALLOCATION_GRANULARITY - 1);
+				},
+				None => return Ok(candidate),
+			}
+		}
+	}
+	fn allocate(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect, mem_type: MemoryType) -> Result<usize> {
+		let reserve = alloc_type.contains(AllocType::RESERVE);
+		let commit = alloc_type.contains(AllocType::COMMIT);
+		if len == 0 || address >= MAX_ADDRESS {
+			return Err(ERROR_INVALID_PARAMETER);
+		}
+		let mut pages = self.pages.write().unwrap();
+		// Reset only applies to committed memory and does not otherwise change the state of the pages
+		if !reserve && !commit {
+			if !alloc_type.contains(AllocType::RESET) && !alloc_type.contains(AllocType::RESET_UNDO) {
+				return Err(ERROR_INVALID_PARAMETER);
+			}
+			let start = page_floor(address);
+			let end = page_ceil(address.saturating_add(len));
+			return if (start..end).step_by(PAGE_SIZE).all(|page| pages.get(&page).is_some_and(Page::is_committed)) {
+				Ok(start)
+			}
+			else {
+				Err(ERROR_INVALID_ADDRESS)
+			};
+		}
+		// Commit pages inside an existing reservation
+		if !reserve {
+			if address == 0 {
+				return Self::allocate_new(&mut pages, 0, len, true, protect, mem_type);
+			}
+			let start = page_floor(address);
+			let end = page_ceil(address.saturating_add(len));
+			let allocation_base = match pages.get(&start) {
+				Some(page) => page.allocation_base,
+				None => return Err(ERROR_INVALID_ADDRESS),
+			};
+			if !(start..end).step_by(PAGE_SIZE).all(|page| pages.get(&page).is_some_and(|page| page.allocation_base == allocation_base)) {
+				return Err(ERROR_INVALID_ADDRESS);
+			}
+			for page in pages.range_mut(start..end).map(|(_, page)| page) {
+				if page.bytes.is_none() {
+					page.bytes = Some(vec![0u8; PAGE_SIZE].into_boxed_slice());
+				}
+				page.protect = protect;
+			}
+			return Ok(start);
+		}
+		Self::allocate_new(&mut pages, address, len, commit, protect, mem_type)
+	}
+	fn allocate_new(pages: &mut BTreeMap<usize, Page>, address: usize, len: usize, commit: bool, protect: Protect, mem_type: MemoryType) -> Result<usize> {
+		let (start, end) = if address == 0 {
+			let size = page_ceil(len);
+			let start = Self::find_free(pages, size)?;
+			(start, start + size)
+		}
+		else {
+			let start = address & !(ALLOCATION_GRANULARITY - 1);
+			let end = page_ceil(address.saturating_add(len));
+			if end > MAX_ADDRESS || pages.range(start..end).next().is_some() {
+				return Err(ERROR_INVALID_ADDRESS);
+			}
+			(start, end)
+		};
+		for address in (start..end).step_by(PAGE_SIZE) {
+			pages.insert(address, Page {
+				allocation_base: start,
+				allocation_protect: protect,
+				mem_type,
+				protect,
+				bytes: if commit { Some(vec![0u8; PAGE_SIZE].into_boxed_slice()) } else { None },
+			});
+		}
+		Ok(start)
+	}
+}
+impl Default for MockProcess {
+	fn default() -> MockProcess {
+		MockProcess::new()
+	}
+}
+impl fmt::Debug for MockProcess {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut list = f.debug_list();
+		let mut address = 0;
+		while let Ok(mi) = self.vm_query(address) {
+			if mi.state != MemoryState::FREE {
+				list.entry(&mi);
+			}
+			address = mi.base_address + mi.region_size;
+		}
+		list.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+impl VirtualMemory for MockProcess {
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		if self.copy_from(address, bytes) == bytes.len() {
+			Ok(bytes)
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let bytes_read = self.copy_from(address, dest);
+		Ok(&mut dest[..bytes_read])
+	}
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		if self.copy_to(address, bytes, Page::is_writable) == bytes.len() {
+			Ok(())
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]> {
+		let bytes_written = self.copy_to(address, bytes, Page::is_writable);
+		Ok(&bytes[..bytes_written])
+	}
+	fn vm_alloc(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect) -> Result<usize> {
+		self.allocate(address, len, alloc_type, protect, MemoryType::PRIVATE)
+	}
+	fn vm_free(&self, address: usize, len: usize, free_type: FreeType) -> Result<()> {
+		let mut pages = self.pages.write().unwrap();
+		let allocation_base = match pages.get(&page_floor(address)) {
+			Some(page) => page.allocation_base,
+			None => return Err(ERROR_INVALID_ADDRESS),
+		};
+		if free_type == FreeType::RELEASE {
+			if len != 0 {
+				return Err(ERROR_INVALID_PARAMETER);
+			}
+			if address != allocation_base {
+				return Err(ERROR_INVALID_ADDRESS);
+			}
+			let end = pages.range(allocation_base..)
+				.take_while(|&(_, page)| page.allocation_base == allocation_base)
+				.last()
+				.map_or(allocation_base, |(&address, _)| address + PAGE_SIZE);
+			let tail = pages.split_off(&end);
+			pages.split_off(&allocation_base);
+			pages.extend(tail);
+			Ok(())
+		}
+		else if free_type == FreeType::DECOMMIT {
+			let start = page_floor(address);
+			let end = if len == 0 {
+				pages.range(start..)
+					.take_while(|&(_, page)| page.allocation_base == allocation_base)
+					.last()
+					.map_or(start, |(&address, _)| address + PAGE_SIZE)
+			}
+			else {
+				page_ceil(address.saturating_add(len))
+			};
+			if !(start..end).step_by(PAGE_SIZE).all(|page| pages.get(&page).is_some_and(|page| page.allocation_base == allocation_base)) {
+				return Err(ERROR_INVALID_ADDRESS);
+			}
+			for page in pages.range_mut(start..end).map(|(_, page)| page) {
+				page.bytes = None;
+			}
+			Ok(())
+		}
+		else {
+			Err(ERROR_INVALID_PARAMETER)
+		}
+	}
+	fn vm_protect(&self, address: usize, len: usize, protect: Protect) -> Result<Protect> {
+		if len == 0 {
+			return Err(ERROR_INVALID_PARAMETER);
+		}
+		let mut pages = self.pages.write().unwrap();
+		let start = page_floor(address);
+		let end = page_ceil(address.saturating_add(len));
+		if !(start..end).step_by(PAGE_SIZE).all(|page| pages.get(&page).is_some_and(Page::is_committed)) {
+			return Err(ERROR_INVALID_ADDRESS);
+		}
+		let mut old = None;
+		for page in pages.range_mut(start..end).map(|(_, page)| page) {
+			old.get_or_insert(page.protect);
+			page.protect = protect;
+		}
+		Ok(old.unwrap_or(protect))
+	}
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		if address >= MAX_ADDRESS {
+			return Err(ERROR_INVALID_PARAMETER);
+		}
+		let pages = self.pages.read().unwrap();
+		let base_address = page_floor(address);
+		match pages.get(&base_address) {
+			Some(first) => {
+				let mut region_size = 0;
+				for (&address, page) in pages.range(base_address..) {
+					if address != base_address + region_size
+						|| page.allocation_base != first.allocation_base
+						|| page.is_committed() != first.is_committed()
+						|| page.is_committed() && page.protect != first.protect
+					{
+						break;
+					}
+					region_size += PAGE_SIZE;
+				}
+				Ok(MemoryInformation {
+					base_address,
+					allocation_base: first.allocation_base,
+					allocation_protect: first.allocation_protect,
+					region_size,
+					state: if first.is_committed() { MemoryState::COMMIT } else { MemoryState::RESERVE },
+					protect: if first.is_committed() { first.protect } else { Protect::default() },
+					mem_type: first.mem_type,
+				})
+			},
+			None => {
+				let end = pages.range(base_address..).next().map_or(MAX_ADDRESS, |(&address, _)| address);
+				Ok(MemoryInformation {
+					base_address,
+					allocation_base: 0,
+					allocation_protect: Protect::default(),
+					region_size: end - base_address,
+					state: MemoryState::FREE,
+					protect: Protect::NO_ACCESS,
+					mem_type: MemoryType::default(),
+				})
+			},
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use std::num::NonZeroU16;
+	use crate::ptr::{FromRemoteBytes, Ptr, Ptr64};
+	use crate::error::{ERROR_INVALID_DATA, ERROR_PARTIAL_COPY};
+	use super::*;
+
+	#[test]
+	fn read_write() {
+		let process = MockProcess::new();
+		let base = process.map(0x20000, &[1, 2, 3, 4, 5, 6, 7, 8], Protect::READ_WRITE).unwrap();
+		assert_eq!(base, 0x20000);
+		assert_eq!(process.vm_read(Ptr::<u32>::from(0x20004)), Ok(0x08070605));
+		process.vm_write(Ptr::<u16>::from(0x20002), &0xAABB).unwrap();
+		let mut bytes = [0u8; 4];
+		assert_eq!(process.vm_read_bytes(0x20000, &mut bytes), Ok(&mut [1, 2, 0xBB, 0xAA][..]));
+		let mut vec = vec![0u8];
+		assert_eq!(process.vm_read_append(Ptr::<[u8]>::from(0x20005), &mut vec, 3), Ok(&mut [6, 7, 8][..]));
+		assert_eq!(vec, [0, 6, 7, 8]);
+	}
+
+	#[derive(Copy, Clone, Debug, Eq, PartialEq, FromRemoteBytes)]
+	#[repr(i16)]
+	enum Kind {
+		Small = -1,
+		Large = 0x100,
+	}
+
+	#[derive(Copy, Clone, Debug, Eq, PartialEq, FromRemoteBytes)]
+	#[repr(C)]
+	struct Validated {
+		flags: [[bool; 2]; 2],
+		kind: Kind,
+		id: NonZeroU16,
+	}
+
+	#[test]
+	fn validated_read() {
+		let process = MockProcess::new();
+		let base = process.map(0x40000, &[0; 8], Protect::READ_WRITE).unwrap();
+		let ptr = Ptr64::<Validated>::from(base as u64);
+		assert_eq!(process.vm_read(ptr), Err(ERROR_INVALID_DATA));
+		process.poke(base, &[1, 0, 0, 1]).unwrap();
+		process.poke(base + 4, &0x100i16.to_ne_bytes()).unwrap();
+		process.poke(base + 6, &7u16.to_ne_bytes()).unwrap();
+		let expected = Validated { flags: [[true, false], [false, true]], kind: Kind::Large, id: NonZeroU16::new(7).unwrap() };
+		assert_eq!(process.vm_read(ptr), Ok(expected));
+		process.poke(base + 4, &(-1i16).to_ne_bytes()).unwrap();
+		assert_eq!(process.vm_read(ptr).map(|value| value.kind), Ok(Kind::Small));
+		process.poke(base + 4, &1i16.to_ne_bytes()).unwrap();
+		assert_eq!(process.vm_read(ptr), Err(ERROR_INVALID_DATA));
+		process.poke(base + 4, &0x100i16.to_ne_bytes()).unwrap();
+		process.poke(base + 3, &[2]).unwrap();
+		assert_eq!(process.vm_read(ptr), Err(ERROR_INVALID_DATA));
+		assert_eq!(process.vm_read(Ptr64::<bool>::from(base as u64)), Ok(true));
+	}
+
+	#[test]
+	fn partial_copy() {
+		let process = MockProcess::new();
+		process.map(0x30000, &[0xCC; PAGE_SIZE * 2], Protect::READ_WRITE).unwrap();
+		process.vm_protect(0x30000 + PAGE_SIZE, PAGE_SIZE, Protect::NO_ACCESS).unwrap();
+		let mut bytes = [0u8; 0x10];
+		let address = 0x30000 + PAGE_SIZE - 4;
+		assert_eq!(process.vm_read_bytes(address, &mut bytes), Err(ERROR_PARTIAL_COPY));
+		assert_eq!(process.vm_read_partial(address, &mut bytes).map(|bytes| bytes.len()), Ok(4));
+		assert_eq!(process.vm_write_partial(address, &[0u8; 0x10]).map(|bytes| bytes.len()), Ok(4));
+		assert_eq!(process.vm_read_partial(0x90000, &mut bytes).map(|bytes| bytes.len()), Ok(0));
+		process.vm_protect(0x30000, PAGE_SIZE, Protect::READ_WRITE.set_guard(true)).unwrap();
+		assert_eq!(process.vm_read_bytes(0x30000, &mut bytes), Err(ERROR_PARTIAL_COPY));
+	}
+
+	#[test]
+	fn alloc_query_free() {
+		let process = MockProcess::new();
+		let base = process.vm_reserve(0, PAGE_SIZE * 4, Protect::READ_WRITE).unwrap();
+		process.vm_commit(base + PAGE_SIZE, PAGE_SIZE, Protect::READ_ONLY).unwrap();
+		let mut regions = Vec::new();
+		process.vm_regions(base, PAGE_SIZE * 4, |mi| regions.push((mi.base_address - base, mi.region_size, mi.state))).unwrap();
+		assert_eq!(regions, [
+			(0, PAGE_SIZE, MemoryState::RESERVE),
+			(PAGE_SIZE, PAGE_SIZE, MemoryState::COMMIT),
+			(PAGE_SIZE * 2, PAGE_SIZE * 2, MemoryState::RESERVE),
+		]);
+		assert_eq!(process.vm_query(base + PAGE_SIZE).unwrap().protect, Protect::READ_ONLY);
+		assert!(process.vm_protect(base, PAGE_SIZE * 2, Protect::READ_WRITE).is_err());
+		process.vm_decommit(base + PAGE_SIZE, PAGE_SIZE).unwrap();
+		assert_eq!(process.vm_query(base).unwrap().region_size, PAGE_SIZE * 4);
+		assert!(process.vm_release(base + PAGE_SIZE).is_err());
+		process.vm_release(base).unwrap();
+		assert_eq!(process.vm_query(base).unwrap().state, MemoryState::FREE);
+	}
+}
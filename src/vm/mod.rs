@@ -0,0 +1,44 @@
+/*!
+Virtual memory interaction with a process.
+
+The [`VirtualMemory`](trait.VirtualMemory.html) trait abstracts over the backend providing the memory.
+It is implemented by [`Process`](../process/struct.Process.html) for live processes and by [`MockProcess`](struct.MockProcess.html) for a simulated address space.
+
+Code written against the trait works with either, this allows tooling to be tested without a live target.
+Wrappers such as [`CachedReader`](struct.CachedReader.html) implement the trait as well and compose with any backend.
+A [`MemorySnapshot`](struct.MemorySnapshot.html) captured from a process, or loaded from a file, is a read only backend.
+!*/
+
+mod protect;
+mod info;
+mod virtual_memory;
+mod mock;
+mod maps;
+mod cached_reader;
+mod batch;
+mod cursor;
+mod walk;
+mod path;
+mod range;
+mod address_space;
+mod snapshot;
+mod watcher;
+#[cfg(windows)]
+mod windows;
+#[cfg(target_os = "linux")]
+mod linux;
+
+pub use self::protect::*;
+pub use self::info::*;
+pub use self::virtual_memory::*;
+pub use self::mock::*;
+pub use self::maps::*;
+pub use self::cached_reader::*;
+pub use self::batch::ReadRequest;
+pub use self::cursor::*;
+pub use self::walk::{ListEntry, ListIter, ArrayIter};
+pub use self::path::{PointerPath, ParseError, ResolveError};
+pub use self::range::*;
+pub use self::address_space::*;
+pub use self::snapshot::*;
+pub use self::watcher::*;
@@ -0,0 +1,561 @@
+use std::{error, fmt, str};
+use crate::error::ErrorCode;
+use crate::ptr::{RemotePtr, TargetArch};
+use super::VirtualMemory;
+
+/// Pointer path expression.
+///
+/// Describes how to find an address starting from module bases, eg. `game.exe+0x1A2B30 -> 0x10 -> 0x48`.
+///
+/// * Numbers are decimal or hexadecimal with the `0x` prefix.
+/// * Names refer to the base address of a module, names which are not simple identifiers are quoted: `"my game.exe"`.
+/// * Arithmetic with `+`, `-` and `*`, grouping with parentheses.
+/// * `[expr]` reads the pointer at the address.
+/// * `expr -> offset` reads the pointer at the address and adds the offset, it is shorthand for `[expr]+offset`.
+///
+/// Arithmetic wraps around at the pointer width of the target architecture.
+///
+/// Paths are parsed with `FromStr` and formatted with `Display` in a canonical form, with the `serde` feature they serialize as strings.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::{Ptr, TargetArch};
+/// use external::vm::{MockProcess, PointerPath, Protect};
+///
+/// let process = MockProcess::new();
+/// let health = process.map(0x20000, &100f32.to_le_bytes(), Protect::READ_WRITE).unwrap() as u64;
+/// let player = process.map(0x30000, &(health - 0x10).to_le_bytes(), Protect::READ_WRITE).unwrap() as u64;
+/// let global = process.map(0x40000, &(player).to_le_bytes(), Protect::READ_WRITE).unwrap() as u64;
+///
+/// let path: PointerPath = "game.exe+0x100 -> 0 -> 0x10".parse().unwrap();
+/// let modules = |name: &str| if name == "game.exe" { Some(global - 0x100) } else { None };
+/// let ptr: Ptr<f32> = path.resolve(&process, TargetArch::X64, modules).unwrap();
+/// assert_eq!(ptr.into_raw(), health);
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PointerPath(Expr);
+
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+enum Expr {
+	Int(u64),
+	Module(String),
+	Neg(Box<Expr>),
+	Add(Box<Expr>, Box<Expr>),
+	Sub(Box<Expr>, Box<Expr>),
+	Mul(Box<Expr>, Box<Expr>),
+	Deref(Box<Expr>),
+	Arrow(Box<Expr>, Box<Expr>),
+}
+
+impl PointerPath {
+	/// Parses a pointer path.
+	pub fn parse(s: &str) -> Result<PointerPath, ParseError> {
+		let mut parser = Parser::new(s)?;
+		let expr = parser.arrow()?;
+		match parser.token {
+			Token::End => Ok(PointerPath(expr)),
+			_ => Err(parser.unexpected()),
+		}
+	}
+	/// Constructs the path `module+offset -> offsets[0] -> offsets[1] -> ...`.
+	///
+	/// The module offset is left out when it is zero.
+	pub fn from_chain(module: &str, offset: u64, offsets: &[u64]) -> PointerPath {
+		let mut expr = Expr::Module(module.to_string());
+		if offset != 0 {
+			expr = Expr::Add(Box::new(expr), Box::new(Expr::Int(offset)));
+		}
+		for &offset in offsets {
+			expr = Expr::Arrow(Box::new(expr), Box::new(Expr::Int(offset)));
+		}
+		PointerPath(expr)
+	}
+	/// The names of the modules referenced by the path, in order of appearance.
+	pub fn modules(&self) -> Vec<&str> {
+		let mut modules = Vec::new();
+		self.0.modules(&mut modules);
+		modules
+	}
+	/// Resolves the path to an address.
+	///
+	/// The module bases are looked up with the given closure, the pointers are read with the pointer size of the target architecture.
+	pub fn resolve_address<V, F>(&self, vm: &V, arch: TargetArch, modules: F) -> Result<u64, ResolveError>
+		where V: VirtualMemory + ?Sized, F: FnMut(&str) -> Option<u64>
+	{
+		let mask = match arch {
+			TargetArch::X86 => 0xFFFF_FFFF,
+			TargetArch::X64 => u64::MAX,
+		};
+		let mut resolver = Resolver { vm, arch, mask, modules, step: 0 };
+		resolver.eval(&self.0)
+	}
+	/// Resolves the path to a typed pointer.
+	///
+	/// See [`resolve_address`](#method.resolve_address) for more information.
+	pub fn resolve<P, V, F>(&self, vm: &V, arch: TargetArch, modules: F) -> Result<P, ResolveError>
+		where P: RemotePtr, V: VirtualMemory + ?Sized, F: FnMut(&str) -> Option<u64>
+	{
+		self.resolve_address(vm, arch, modules).map(P::from_address)
+	}
+}
+#[cfg(any(windows, target_os = "linux"))]
+impl PointerPath {
+	/// Resolves the path in a live process.
+	///
+	/// The target architecture is queried from the process and module names are looked up with [`EnumModules`](../module/struct.EnumModules.html), ignoring ASCII case.
+	pub fn resolve_process<P: RemotePtr>(&self, process: &crate::process::Process) -> Result<P, ResolveError> {
+		let arch = process.target_arch().map_err(ResolveError::Process)?;
+		let mut modules = Vec::new();
+		if !self.modules().is_empty() {
+			let pid = process.pid().map_err(ResolveError::Process)?;
+			for entry in crate::module::EnumModules::create(pid).map_err(ResolveError::Process)? {
+				modules.push((entry.name().to_string_lossy().into_owned(), entry.base() as u64));
+			}
+		}
+		self.resolve(process, arch, |name| {
+			modules.iter().find(|(module, _)| module.eq_ignore_ascii_case(name)).map(|&(_, base)| base)
+		})
+	}
+}
+impl str::FromStr for PointerPath {
+	type Err = ParseError;
+	fn from_str(s: &str) -> Result<PointerPath, ParseError> {
+		PointerPath::parse(s)
+	}
+}
+impl fmt::Display for PointerPath {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f, 0)
+	}
+}
+#[cfg(feature = "serde")]
+impl serde::Serialize for PointerPath {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.collect_str(self)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PointerPath {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<PointerPath, D::Error> {
+		let s = <std::borrow::Cow<str> as serde::Deserialize>::deserialize(deserializer)?;
+		PointerPath::parse(&s).map_err(serde::de::Error::custom)
+	}
+}
+
+//----------------------------------------------------------------
+
+// Operator precedence when formatting.
+const PREC_ARROW: u32 = 0;
+const PREC_ADD: u32 = 1;
+const PREC_MUL: u32 = 2;
+const PREC_UNARY: u32 = 3;
+
+fn is_name_start(chr: char) -> bool {
+	chr.is_alphabetic() || chr == '_' || chr == '$' || chr == '@'
+}
+fn is_name_char(chr: char) -> bool {
+	chr.is_alphanumeric() || chr == '_' || chr == '$' || chr == '@' || chr == '.'
+}
+
+impl Expr {
+	fn modules<'a>(&'a self, modules: &mut Vec<&'a str>) {
+		match self {
+			Expr::Int(_) => (),
+			Expr::Module(name) => modules.push(name),
+			Expr::Neg(expr) | Expr::Deref(expr) => expr.modules(modules),
+			Expr::Add(lhs, rhs) | Expr::Sub(lhs, rhs) | Expr::Mul(lhs, rhs) | Expr::Arrow(lhs, rhs) => {
+				lhs.modules(modules);
+				rhs.modules(modules);
+			},
+		}
+	}
+	fn prec(&self) -> u32 {
+		match self {
+			Expr::Arrow(..) => PREC_ARROW,
+			Expr::Add(..) | Expr::Sub(..) => PREC_ADD,
+			Expr::Mul(..) => PREC_MUL,
+			Expr::Neg(..) => PREC_UNARY,
+			Expr::Int(_) | Expr::Module(_) | Expr::Deref(_) => PREC_UNARY + 1,
+		}
+	}
+	fn fmt(&self, f: &mut fmt::Formatter, prec: u32) -> fmt::Result {
+		if self.prec() < prec {
+			f.write_str("(")?;
+			self.fmt(f, 0)?;
+			return f.write_str(")");
+		}
+		match self {
+			Expr::Int(value) => write!(f, "{:#X}", value),
+			Expr::Module(name) => {
+				if name.starts_with(is_name_start) && name.chars().all(is_name_char) {
+					f.write_str(name)
+				}
+				else {
+					write!(f, "\"{}\"", name)
+				}
+			},
+			Expr::Neg(expr) => {
+				f.write_str("-")?;
+				expr.fmt(f, PREC_UNARY)
+			},
+			Expr::Add(lhs, rhs) => {
+				lhs.fmt(f, PREC_ADD)?;
+				f.write_str("+")?;
+				rhs.fmt(f, PREC_MUL)
+			},
+			Expr::Sub(lhs, rhs) => {
+				lhs.fmt(f, PREC_ADD)?;
+				f.write_str("-")?;
+				rhs.fmt(f, PREC_MUL)
+			},
+			Expr::Mul(lhs, rhs) => {
+				lhs.fmt(f, PREC_MUL)?;
+				f.write_str("*")?;
+				rhs.fmt(f, PREC_UNARY)
+			},
+			Expr::Deref(expr) => {
+				f.write_str("[")?;
+				expr.fmt(f, 0)?;
+				f.write_str("]")
+			},
+			Expr::Arrow(lhs, rhs) => {
+				lhs.fmt(f, PREC_ARROW)?;
+				f.write_str(" -> ")?;
+				rhs.fmt(f, PREC_ADD)
+			},
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Pointer path syntax error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+	position: usize,
+	message: &'static str,
+}
+impl ParseError {
+	/// The byte offset into the input where the error was found.
+	pub fn position(&self) -> usize {
+		self.position
+	}
+	/// Description of the error.
+	pub fn message(&self) -> &'static str {
+		self.message
+	}
+}
+impl fmt::Display for ParseError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} at position {}", self.message, self.position)
+	}
+}
+impl error::Error for ParseError {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Token {
+	Int(u64),
+	Name(String),
+	Plus,
+	Minus,
+	Star,
+	Arrow,
+	LBracket,
+	RBracket,
+	LParen,
+	RParen,
+	End,
+}
+
+struct Parser<'a> {
+	s: &'a str,
+	// The current token and its position.
+	token: Token,
+	start: usize,
+	// Position after the current token.
+	pos: usize,
+}
+impl<'a> Parser<'a> {
+	fn new(s: &'a str) -> Result<Parser<'a>, ParseError> {
+		let mut parser = Parser { s, token: Token::End, start: 0, pos: 0 };
+		parser.bump()?;
+		Ok(parser)
+	}
+	fn error(&self, position: usize, message: &'static str) -> ParseError {
+		ParseError { position, message }
+	}
+	fn unexpected(&self) -> ParseError {
+		match self.token {
+			Token::End => self.error(self.start, "unexpected end of input"),
+			_ => self.error(self.start, "unexpected token"),
+		}
+	}
+	// Advances to the next token.
+	fn bump(&mut self) -> Result<(), ParseError> {
+		let rest = &self.s[self.pos..];
+		let trimmed = rest.trim_start();
+		self.start = self.pos + (rest.len() - trimmed.len());
+		let mut chars = trimmed.chars();
+		let (token, len) = match chars.next() {
+			None => (Token::End, 0),
+			Some('+') => (Token::Plus, 1),
+			Some('-') if chars.next() == Some('>') => (Token::Arrow, 2),
+			Some('-') => (Token::Minus, 1),
+			Some('*') => (Token::Star, 1),
+			Some('[') => (Token::LBracket, 1),
+			Some(']') => (Token::RBracket, 1),
+			Some('(') => (Token::LParen, 1),
+			Some(')') => (Token::RParen, 1),
+			Some('"') => {
+				let end = trimmed[1..].find('"').ok_or_else(|| self.error(self.start, "unterminated module name"))?;
+				if end == 0 {
+					return Err(self.error(self.start, "empty module name"));
+				}
+				(Token::Name(trimmed[1..end + 1].to_string()), end + 2)
+			},
+			Some(chr) if chr.is_ascii_digit() => {
+				let len = trimmed.find(|chr: char| !is_name_char(chr)).unwrap_or(trimmed.len());
+				let literal = &trimmed[..len];
+				let value = match literal.strip_prefix("0x").or_else(|| literal.strip_prefix("0X")) {
+					Some(hex) => u64::from_str_radix(hex, 16),
+					None => literal.parse(),
+				};
+				(Token::Int(value.map_err(|_| self.error(self.start, "invalid number"))?), len)
+			},
+			Some(chr) if is_name_start(chr) => {
+				let len = trimmed.find(|chr: char| !is_name_char(chr)).unwrap_or(trimmed.len());
+				(Token::Name(trimmed[..len].to_string()), len)
+			},
+			Some(_) => return Err(self.error(self.start, "unexpected character")),
+		};
+		self.token = token;
+		self.pos = self.start + len;
+		Ok(())
+	}
+	fn expect(&mut self, token: Token) -> Result<(), ParseError> {
+		if self.token != token {
+			return Err(self.unexpected());
+		}
+		self.bump()
+	}
+	fn arrow(&mut self) -> Result<Expr, ParseError> {
+		let mut lhs = self.additive()?;
+		while self.token == Token::Arrow {
+			self.bump()?;
+			let rhs = self.additive()?;
+			lhs = Expr::Arrow(Box::new(lhs), Box::new(rhs));
+		}
+		Ok(lhs)
+	}
+	fn additive(&mut self) -> Result<Expr, ParseError> {
+		let mut lhs = self.multiplicative()?;
+		loop {
+			let op: fn(Box<Expr>, Box<Expr>) -> Expr = match self.token {
+				Token::Plus => Expr::Add,
+				Token::Minus => Expr::Sub,
+				_ => return Ok(lhs),
+			};
+			self.bump()?;
+			let rhs = self.multiplicative()?;
+			lhs = op(Box::new(lhs), Box::new(rhs));
+		}
+	}
+	fn multiplicative(&mut self) -> Result<Expr, ParseError> {
+		let mut lhs = self.unary()?;
+		while self.token == Token::Star {
+			self.bump()?;
+			let rhs = self.unary()?;
+			lhs = Expr::Mul(Box::new(lhs), Box::new(rhs));
+		}
+		Ok(lhs)
+	}
+	fn unary(&mut self) -> Result<Expr, ParseError> {
+		if self.token == Token::Minus {
+			self.bump()?;
+			return Ok(Expr::Neg(Box::new(self.unary()?)));
+		}
+		self.primary()
+	}
+	fn primary(&mut self) -> Result<Expr, ParseError> {
+		let expr = match self.token {
+			Token::Int(value) => Expr::Int(value),
+			Token::Name(ref name) => Expr::Module(name.clone()),
+			Token::LBracket => {
+				self.bump()?;
+				let expr = self.arrow()?;
+				self.expect(Token::RBracket)?;
+				return Ok(Expr::Deref(Box::new(expr)));
+			},
+			Token::LParen => {
+				self.bump()?;
+				let expr = self.arrow()?;
+				self.expect(Token::RParen)?;
+				return Ok(expr);
+			},
+			_ => return Err(self.unexpected()),
+		};
+		self.bump()?;
+		Ok(expr)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Pointer path resolution error.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ResolveError {
+	/// The named module was not found in the process.
+	ModuleNotFound(String),
+	/// Reading a pointer failed.
+	Deref {
+		/// The dereference which failed, counting from one in the order they are evaluated.
+		step: usize,
+		/// The sub-expression whose value was dereferenced.
+		expr: String,
+		/// The address of the pointer.
+		address: u64,
+		/// The read error.
+		error: ErrorCode,
+	},
+	/// Querying the process failed.
+	Process(ErrorCode),
+}
+impl fmt::Display for ResolveError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ResolveError::ModuleNotFound(name) => write!(f, "module not found: {}", name),
+			ResolveError::Deref { step, expr, address, error } => write!(f, "step {} dereferencing `{}`: cannot read pointer at {:#x}: {}", step, expr, address, error),
+			ResolveError::Process(error) => write!(f, "cannot query process: {}", error),
+		}
+	}
+}
+impl error::Error for ResolveError {}
+
+struct Resolver<'a, V: ?Sized, F> {
+	vm: &'a V,
+	arch: TargetArch,
+	mask: u64,
+	modules: F,
+	step: usize,
+}
+impl<V: VirtualMemory + ?Sized, F: FnMut(&str) -> Option<u64>> Resolver<'_, V, F> {
+	fn eval(&mut self, expr: &Expr) -> Result<u64, ResolveError> {
+		let value = match expr {
+			Expr::Int(value) => *value,
+			Expr::Module(name) => (self.modules)(name).ok_or_else(|| ResolveError::ModuleNotFound(name.clone()))?,
+			Expr::Neg(expr) => self.eval(expr)?.wrapping_neg(),
+			Expr::Add(lhs, rhs) => self.eval(lhs)?.wrapping_add(self.eval(rhs)?),
+			Expr::Sub(lhs, rhs) => self.eval(lhs)?.wrapping_sub(self.eval(rhs)?),
+			Expr::Mul(lhs, rhs) => self.eval(lhs)?.wrapping_mul(self.eval(rhs)?),
+			Expr::Deref(expr) => self.deref(expr)?,
+			Expr::Arrow(lhs, rhs) => self.deref(lhs)?.wrapping_add(self.eval(rhs)?),
+		};
+		Ok(value & self.mask)
+	}
+	fn deref(&mut self, expr: &Expr) -> Result<u64, ResolveError> {
+		let address = self.eval(expr)?;
+		self.step += 1;
+		self.vm.vm_read_ptr(self.arch, address as usize).map_err(|error| ResolveError::Deref {
+			step: self.step,
+			expr: PointerPath(expr.clone()).to_string(),
+			address,
+			error,
+		})
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::error::ERROR_PARTIAL_COPY;
+	use crate::ptr::{Ptr, Ptr32};
+	use super::super::*;
+	use super::*;
+
+	#[test]
+	fn parse() {
+		let cases = [
+			("game.exe+0x1A2B30 -> 0x10 -> 0x48", "game.exe+0x1A2B30 -> 0x10 -> 0x48"),
+			("[[client.dll + 0x10] + 0x20]", "[[client.dll+0x10]+0x20]"),
+			("\"my game.exe\" + 16 * 4 -> -0x8", "\"my game.exe\"+0x10*0x4 -> -0x8"),
+			("(a - (b - 1)) * (2 + 3)", "(a-(b-0x1))*(0x2+0x3)"),
+			("a -> (b -> 1)", "a -> (b -> 0x1)"),
+		];
+		for &(input, canonical) in &cases {
+			let path: PointerPath = input.parse().unwrap();
+			assert_eq!(path.to_string(), canonical);
+			assert_eq!(canonical.parse::<PointerPath>(), Ok(path));
+		}
+		let errors = [
+			("", 0, "unexpected end of input"),
+			("game.exe+", 9, "unexpected end of input"),
+			("[a+1", 4, "unexpected end of input"),
+			("a+1]", 3, "unexpected token"),
+			("1A2B", 0, "invalid number"),
+			("0x10000000000000000", 0, "invalid number"),
+			("a + \"b", 4, "unterminated module name"),
+			("a ? b", 2, "unexpected character"),
+		];
+		for &(input, position, message) in &errors {
+			let err = PointerPath::parse(input).unwrap_err();
+			assert_eq!((err.position(), err.message()), (position, message), "{:?}", input);
+		}
+		let path = PointerPath::parse("[a+b] -> \"c d\"").unwrap();
+		assert_eq!(path.modules(), ["a", "b", "c d"]);
+		assert_eq!(PointerPath::from_chain("game.exe", 0x1A2B30, &[0x10, 0x48]), cases[0].1.parse().unwrap());
+		assert_eq!(PointerPath::from_chain("my game.exe", 0, &[0]).to_string(), "\"my game.exe\" -> 0x0");
+	}
+
+	#[test]
+	fn resolve() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap() as u64;
+		process.vm_write(Ptr::<u64>::from(base + 0x100), &(base + 0x200)).unwrap();
+		process.vm_write(Ptr::<u64>::from(base + 0x210), &(base + 0x300)).unwrap();
+		process.vm_write(Ptr32::<u32>::from(base as u32 + 0x400), &(base as u32 + 0x500)).unwrap();
+		let modules = |name: &str| if name == "game.exe" { Some(base) } else { None };
+
+		let path = PointerPath::parse("game.exe+0x100 -> 0x10 -> 0x48").unwrap();
+		assert_eq!(path.resolve_address(&process, TargetArch::X64, modules), Ok(base + 0x348));
+		let path = PointerPath::parse("[[game.exe+0x100]+0x10]+0x48").unwrap();
+		assert_eq!(path.resolve::<Ptr<u8>, _, _>(&process, TargetArch::X64, modules), Ok(Ptr::from(base + 0x348)));
+		let path = PointerPath::parse("game.exe+0x400 -> -4").unwrap();
+		assert_eq!(path.resolve_address(&process, TargetArch::X86, modules), Ok(base + 0x4FC));
+
+		let path = PointerPath::parse("game.exe+0x100 -> 0x10 -> 0 -> 0x20 -> 8").unwrap();
+		assert_eq!(path.resolve_address(&process, TargetArch::X64, modules), Err(ResolveError::Deref {
+			step: 4,
+			expr: "game.exe+0x100 -> 0x10 -> 0x0 -> 0x20".to_string(),
+			address: 0x20,
+			error: ERROR_PARTIAL_COPY,
+		}));
+		let path = PointerPath::parse("client.dll -> 8").unwrap();
+		assert_eq!(path.resolve_address(&process, TargetArch::X64, modules), Err(ResolveError::ModuleNotFound("client.dll".to_string())));
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn serde() {
+		use serde::de::{Deserialize, IntoDeserializer, value::Error};
+		let path = PointerPath::deserialize(IntoDeserializer::<Error>::into_deserializer("[a+8] -> 0x10")).unwrap();
+		assert_eq!(path.to_string(), "[a+0x8] -> 0x10");
+		assert!(PointerPath::deserialize(IntoDeserializer::<Error>::into_deserializer("a ->")).is_err());
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn resolve_process() {
+		static VALUE: u32 = 42;
+		static POINTER: &u32 = &VALUE;
+		let exe = std::env::current_exe().unwrap();
+		let name = exe.file_name().unwrap().to_str().unwrap();
+		let pid = crate::process::Process::current().pid().unwrap();
+		let base = crate::module::EnumModules::create(pid).unwrap()
+			.find(|module| module.name() == name).unwrap().base();
+		let path = PointerPath::parse(&format!("\"{}\"+{:#x} -> 0", name, &POINTER as *const _ as usize - base)).unwrap();
+		let ptr: Ptr<u32> = path.resolve_process(&crate::process::Process::current()).unwrap();
+		assert_eq!(ptr.into_raw() as usize, &VALUE as *const u32 as usize);
+	}
+}
@@ -0,0 +1,124 @@
+use std::fmt;
+
+// Protection, allocation and region flags with their Windows SDK values, the mock and Linux backends use the same encoding.
+const PAGE_NOACCESS: u32 = 0x01;
+const PAGE_READONLY: u32 = 0x02;
+const PAGE_READWRITE: u32 = 0x04;
+const PAGE_WRITECOPY: u32 = 0x08;
+const PAGE_EXECUTE: u32 = 0x10;
+const PAGE_EXECUTE_READ: u32 = 0x20;
+const PAGE_EXECUTE_READWRITE: u32 = 0x40;
+const PAGE_EXECUTE_WRITECOPY: u32 = 0x80;
+const PAGE_GUARD: u32 = 0x100;
+
+const MEM_COMMIT: u32 = 0x1000;
+const MEM_RESERVE: u32 = 0x2000;
+const MEM_DECOMMIT: u32 = 0x4000;
+const MEM_RELEASE: u32 = 0x8000;
+const MEM_FREE: u32 = 0x10000;
+const MEM_PRIVATE: u32 = 0x20000;
+const MEM_MAPPED: u32 = 0x40000;
+const MEM_RESET: u32 = 0x80000;
+const MEM_IMAGE: u32 = 0x1000000;
+const MEM_RESET_UNDO: u32 = 0x1000000;
+
+//----------------------------------------------------------------
+
+/// Memory protection type.
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Protect(u32);
+impl_inner!(Protect: u32);
+impl Protect {
+	pub const EXECUTE: Protect = Protect(PAGE_EXECUTE);
+	pub const EXECUTE_READ: Protect = Protect(PAGE_EXECUTE_READ);
+	pub const EXECUTE_READ_WRITE: Protect = Protect(PAGE_EXECUTE_READWRITE);
+	pub const EXECUTE_WRITE_COPY: Protect = Protect(PAGE_EXECUTE_WRITECOPY);
+	pub const NO_ACCESS: Protect = Protect(PAGE_NOACCESS);
+	pub const READ_ONLY: Protect = Protect(PAGE_READONLY);
+	pub const READ_WRITE: Protect = Protect(PAGE_READWRITE);
+	pub const WRITE_COPY: Protect = Protect(PAGE_WRITECOPY);
+	pub fn is_executable(self) -> bool {
+		self.0 & (PAGE_EXECUTE | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY) != 0
+	}
+	pub fn is_readable(self) -> bool {
+		self.0 & (PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY | PAGE_READONLY | PAGE_READWRITE | PAGE_WRITECOPY) != 0
+	}
+	pub fn is_writable(self) -> bool {
+		self.0 & (PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY | PAGE_READWRITE | PAGE_WRITECOPY) != 0
+	}
+	pub fn has_guard(self) -> bool {
+		self.0 & (PAGE_GUARD) != 0
+	}
+	pub fn set_guard(self, value: bool) -> Protect {
+		if value {
+			Protect(self.0 | PAGE_GUARD)
+		}
+		else {
+			Protect(self.0 & !PAGE_GUARD)
+		}
+	}
+}
+impl fmt::Debug for Protect {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Protect")
+			.field("flags", &format_args!("{:#x}", self.0))
+			.field("is_executable", &self.is_executable())
+			.field("is_readable", &self.is_readable())
+			.field("is_writable", &self.is_writable())
+			.field("has_guard", &self.has_guard())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Free type for virtual memory.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct FreeType(u32);
+impl_inner!(FreeType: u32);
+impl FreeType {
+	pub const DECOMMIT: FreeType = FreeType(MEM_DECOMMIT);
+	pub const RELEASE: FreeType = FreeType(MEM_RELEASE);
+}
+
+/// Allocation type for virtual memory.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct AllocType(u32);
+impl_inner!(AllocType: u32);
+impl AllocType {
+	pub const COMMIT: AllocType = AllocType(MEM_COMMIT);
+	pub const RESERVE: AllocType = AllocType(MEM_RESERVE);
+	pub const RESET: AllocType = AllocType(MEM_RESET);
+	pub const RESET_UNDO: AllocType = AllocType(MEM_RESET_UNDO);
+	/// Combines the allocation types.
+	pub fn with(self, other: AllocType) -> AllocType {
+		AllocType(self.0 | other.0)
+	}
+	/// Returns if all the flags in `other` are set.
+	pub fn contains(self, other: AllocType) -> bool {
+		self.0 & other.0 == other.0
+	}
+}
+
+/// State of the pages in a region of virtual memory.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryState(u32);
+impl_inner!(MemoryState: u32);
+impl MemoryState {
+	pub const COMMIT: MemoryState = MemoryState(MEM_COMMIT);
+	pub const FREE: MemoryState = MemoryState(MEM_FREE);
+	pub const RESERVE: MemoryState = MemoryState(MEM_RESERVE);
+}
+
+/// Type of the pages in a region of virtual memory.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MemoryType(u32);
+impl_inner!(MemoryType: u32);
+impl MemoryType {
+	pub const IMAGE: MemoryType = MemoryType(MEM_IMAGE);
+	pub const MAPPED: MemoryType = MemoryType(MEM_MAPPED);
+	pub const PRIVATE: MemoryType = MemoryType(MEM_PRIVATE);
+}
@@ -0,0 +1,153 @@
+use std::{cmp, fmt};
+use crate::ptr::Ptr64;
+use super::MemoryInformation;
+
+/// Half-open range of addresses `start..end`.
+///
+/// Works with 64bit addresses regardless of the host's pointer width.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::vm::AddressRange;
+///
+/// let range = AddressRange::from_len(Ptr64::from(0x1800), 0x1000);
+/// assert!(range.contains(Ptr64::from(0x2000)));
+/// assert_eq!(range.align_out(0x1000), AddressRange::new(Ptr64::from(0x1000), Ptr64::from(0x3000)));
+/// assert!(range.align_in(0x1000).is_empty());
+/// ```
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AddressRange {
+	start: Ptr64,
+	end: Ptr64,
+}
+impl AddressRange {
+	/// Constructs a range from its start and end address.
+	///
+	/// Panics if the end is before the start.
+	pub fn new(start: Ptr64, end: Ptr64) -> AddressRange {
+		assert!(start <= end, "range end before its start");
+		AddressRange { start, end }
+	}
+	/// The range covering the whole address space.
+	pub fn all() -> AddressRange {
+		AddressRange { start: Ptr64::null(), end: Ptr64::from(u64::MAX) }
+	}
+	/// Constructs a range from its start address and length, the end saturates at the end of the address space.
+	pub fn from_len(start: Ptr64, len: u64) -> AddressRange {
+		let end = Ptr64::from(start.into_raw().saturating_add(len));
+		AddressRange { start, end }
+	}
+	/// The first address in the range.
+	pub fn start(self) -> Ptr64 {
+		self.start
+	}
+	/// The first address past the range.
+	pub fn end(self) -> Ptr64 {
+		self.end
+	}
+	/// The length of the range, in bytes.
+	pub fn len(self) -> u64 {
+		self.end.into_raw() - self.start.into_raw()
+	}
+	/// Returns if the range is empty.
+	pub fn is_empty(self) -> bool {
+		self.start == self.end
+	}
+	/// Returns if the address is in the range.
+	pub fn contains(self, address: Ptr64) -> bool {
+		self.start <= address && address < self.end
+	}
+	/// Returns if the other range lies completely within this range.
+	pub fn contains_range(self, other: AddressRange) -> bool {
+		self.start <= other.start && other.end <= self.end
+	}
+	/// Returns if the ranges have any addresses in common.
+	pub fn overlaps(self, other: AddressRange) -> bool {
+		self.start < other.end && other.start < self.end
+	}
+	/// Returns the addresses the ranges have in common, `None` if they do not overlap.
+	pub fn intersect(self, other: AddressRange) -> Option<AddressRange> {
+		if self.overlaps(other) {
+			Some(AddressRange { start: cmp::max(self.start, other.start), end: cmp::min(self.end, other.end) })
+		}
+		else {
+			None
+		}
+	}
+	/// Splits the range in two at the address, which is clamped to the range.
+	pub fn split_at(self, address: Ptr64) -> (AddressRange, AddressRange) {
+		let mid = cmp::min(cmp::max(address, self.start), self.end);
+		(AddressRange { start: self.start, end: mid }, AddressRange { start: mid, end: self.end })
+	}
+	/// Grows the range to the smallest range of whole pages containing it.
+	///
+	/// Panics if the page size is not a power of two.
+	pub fn align_out(self, page_size: u64) -> AddressRange {
+		assert!(page_size.is_power_of_two(), "page size must be a power of two");
+		let mask = page_size - 1;
+		let start = self.start.into_raw() & !mask;
+		let end = self.end.into_raw().checked_add(mask).map_or(!mask, |end| end & !mask);
+		AddressRange { start: Ptr64::from(start), end: Ptr64::from(cmp::max(start, end)) }
+	}
+	/// Shrinks the range to the largest range of whole pages it contains, which may be empty.
+	///
+	/// Panics if the page size is not a power of two.
+	pub fn align_in(self, page_size: u64) -> AddressRange {
+		assert!(page_size.is_power_of_two(), "page size must be a power of two");
+		let mask = page_size - 1;
+		let start = self.start.into_raw().checked_add(mask).map_or(!mask, |start| start & !mask);
+		let end = self.end.into_raw() & !mask;
+		AddressRange { start: Ptr64::from(start), end: Ptr64::from(cmp::max(start, end)) }
+	}
+}
+impl From<&MemoryInformation> for AddressRange {
+	fn from(mi: &MemoryInformation) -> AddressRange {
+		AddressRange::from_len(Ptr64::from(mi.base_address as u64), mi.region_size as u64)
+	}
+}
+#[cfg(any(windows, target_os = "linux"))]
+impl From<&crate::module::ModuleEntry> for AddressRange {
+	fn from(entry: &crate::module::ModuleEntry) -> AddressRange {
+		AddressRange::from_len(Ptr64::from(entry.base() as u64), entry.size() as u64)
+	}
+}
+impl fmt::Display for AddressRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{:#x}..{:#x}", self.start.into_raw(), self.end.into_raw())
+	}
+}
+impl fmt::Debug for AddressRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "AddressRange({:#x}..{:#x})", self.start.into_raw(), self.end.into_raw())
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn range(start: u64, end: u64) -> AddressRange {
+		AddressRange::new(Ptr64::from(start), Ptr64::from(end))
+	}
+
+	#[test]
+	fn algebra() {
+		let a = range(0x1000, 0x3000);
+		let b = range(0x2000, 0x5000);
+		assert_eq!(a.len(), 0x2000);
+		assert!(a.contains(Ptr64::from(0x1000)) && !a.contains(Ptr64::from(0x3000)));
+		assert!(a.contains_range(range(0x1800, 0x3000)) && !a.contains_range(b));
+		assert_eq!(a.intersect(b), Some(range(0x2000, 0x3000)));
+		assert_eq!(a.intersect(range(0x3000, 0x4000)), None);
+		assert_eq!(a.split_at(Ptr64::from(0x1800)), (range(0x1000, 0x1800), range(0x1800, 0x3000)));
+		assert_eq!(a.split_at(Ptr64::from(0x8000)), (a, range(0x3000, 0x3000)));
+		assert_eq!(range(0x1001, 0x2fff).align_out(0x1000), a);
+		assert_eq!(range(0x1001, 0x2fff).align_in(0x1000), range(0x2000, 0x2000));
+		assert_eq!(a.to_string(), "0x1000..0x3000");
+	}
+}
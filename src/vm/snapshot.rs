@@ -0,0 +1,448 @@
+use std::{cmp, fs, io, path};
+use std::io::{Read, Write};
+use crate::error::{ERROR_ACCESS_DENIED, ERROR_INVALID_DATA, ERROR_NOT_SUPPORTED, ERROR_PARTIAL_COPY};
+use crate::ptr::Ptr64;
+use crate::{FromInner, IntoInner, Result};
+use super::{AddressRange, AllocType, FreeType, MemoryInformation, MemoryState, MemoryType, Protect, VirtualMemory};
+
+const MAGIC: &[u8; 8] = b"EXTSNAP\0";
+const VERSION: u32 = 1;
+
+/// Region of a [`MemorySnapshot`](struct.MemorySnapshot.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SnapshotRegion {
+	/// The region at the time of the capture, clipped to the captured range.
+	pub info: MemoryInformation,
+	/// The contents of the region, shorter than the region if reading stopped early.
+	pub bytes: Vec<u8>,
+}
+impl SnapshotRegion {
+	/// The address range of the region.
+	pub fn range(&self) -> AddressRange {
+		AddressRange::from(&self.info)
+	}
+	/// The address range of the captured bytes.
+	pub fn bytes_range(&self) -> AddressRange {
+		AddressRange::from_len(Ptr64::from(self.info.base_address as u64), self.bytes.len() as u64)
+	}
+}
+
+/// Contents of the readable memory of a process at a point in time.
+///
+/// Snapshots are captured from any [`VirtualMemory`](trait.VirtualMemory.html), saved to and loaded from files and compared with [`diff`](#method.diff).
+/// A snapshot implements `VirtualMemory` itself as a read only address space, code written against the trait works on saved snapshots.
+///
+/// # Examples
+///
+/// ```
+/// use external::ptr::Ptr64;
+/// use external::vm::{AddressRange, MemorySnapshot, MockProcess, Protect};
+///
+/// let process = MockProcess::new();
+/// let base = process.map(0x10000, &[0; 0x100], Protect::READ_WRITE).unwrap();
+/// let before = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+/// process.poke(base + 0x10, &[1, 2]).unwrap();
+/// let after = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+///
+/// let diff = before.diff(&after);
+/// let change = &diff.regions[0].changes[0];
+/// assert_eq!(change.address, Ptr64::from(base as u64 + 0x10));
+/// assert_eq!((&*change.old, &*change.new), (&[0, 0][..], &[1, 2][..]));
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MemorySnapshot {
+	regions: Vec<SnapshotRegion>,
+}
+impl MemorySnapshot {
+	/// Creates an empty snapshot.
+	pub fn new() -> MemorySnapshot {
+		MemorySnapshot::default()
+	}
+	/// Captures the readable regions in the range.
+	pub fn capture<V: VirtualMemory + ?Sized>(vm: &V, range: AddressRange) -> Result<MemorySnapshot> {
+		MemorySnapshot::capture_with(vm, range, |_| true)
+	}
+	/// Captures the readable regions in the range accepted by the filter.
+	pub fn capture_with<V, F>(vm: &V, range: AddressRange, mut filter: F) -> Result<MemorySnapshot>
+		where V: VirtualMemory + ?Sized, F: FnMut(&MemoryInformation) -> bool
+	{
+		let mut infos = Vec::new();
+		super::regions_in(vm, range, |mi| {
+			if mi.is_readable() && filter(mi) {
+				infos.push(*mi);
+			}
+		})?;
+		let mut regions = Vec::with_capacity(infos.len());
+		for info in infos {
+			let mut bytes = vec![0u8; info.region_size];
+			let len = vm.vm_read_partial(info.base_address, &mut bytes)?.len();
+			bytes.truncate(len);
+			regions.push(SnapshotRegion { info, bytes });
+		}
+		Ok(MemorySnapshot { regions })
+	}
+	/// The regions sorted by address.
+	pub fn regions(&self) -> &[SnapshotRegion] {
+		&self.regions
+	}
+	/// Finds the region containing the address.
+	pub fn region(&self, address: Ptr64) -> Option<&SnapshotRegion> {
+		let index = self.position(address.into_raw())?;
+		Some(&self.regions[index])
+	}
+	fn position(&self, address: u64) -> Option<usize> {
+		let index = self.regions.partition_point(|region| region.info.base_address as u64 <= address).checked_sub(1)?;
+		if self.regions[index].range().contains(Ptr64::from(address)) { Some(index) } else { None }
+	}
+
+	/// Compares the snapshot with a later snapshot.
+	///
+	/// Changes are grouped by the regions of this snapshot.
+	/// Bytes are compared where both snapshots captured them, regions without any overlap are reported as removed or added.
+	pub fn diff(&self, newer: &MemorySnapshot) -> SnapshotDiff {
+		let mut diff = SnapshotDiff::default();
+		let mut overlapped = vec![false; newer.regions.len()];
+		for old in &self.regions {
+			let range = old.range();
+			let first = newer.regions.partition_point(|new| new.range().end() <= range.start());
+			let mut changes = Vec::new();
+			let mut found = false;
+			for (index, new) in newer.regions.iter().enumerate().skip(first).take_while(|(_, new)| new.range().start() < range.end()) {
+				found = true;
+				overlapped[index] = true;
+				if let Some(common) = old.bytes_range().intersect(new.bytes_range()) {
+					let old_offset = (common.start().into_raw() - old.info.base_address as u64) as usize;
+					let new_offset = (common.start().into_raw() - new.info.base_address as u64) as usize;
+					let len = common.len() as usize;
+					diff_bytes(common.start().into_raw(), &old.bytes[old_offset..old_offset + len], &new.bytes[new_offset..new_offset + len], &mut changes);
+				}
+			}
+			if !found {
+				diff.removed.push(old.info);
+			}
+			else if !changes.is_empty() {
+				diff.regions.push(RegionDiff { region: old.info, changes });
+			}
+		}
+		diff.added = newer.regions.iter().zip(overlapped).filter(|&(_, overlapped)| !overlapped).map(|(new, _)| new.info).collect();
+		diff
+	}
+
+	/// Saves the snapshot to a file.
+	pub fn save<P: AsRef<path::Path>>(&self, path: P) -> Result<()> {
+		let mut file = io::BufWriter::new(fs::File::create(path)?);
+		self.write_to(&mut file)?;
+		file.flush()?;
+		Ok(())
+	}
+	/// Loads a snapshot saved with [`save`](#method.save).
+	pub fn load<P: AsRef<path::Path>>(path: P) -> Result<MemorySnapshot> {
+		MemorySnapshot::read_from(io::BufReader::new(fs::File::open(path)?))
+	}
+	/// Writes the snapshot in its binary format.
+	pub fn write_to<W: io::Write>(&self, mut writer: W) -> Result<()> {
+		writer.write_all(MAGIC)?;
+		writer.write_all(&VERSION.to_le_bytes())?;
+		writer.write_all(&(self.regions.len() as u64).to_le_bytes())?;
+		for region in &self.regions {
+			let info = &region.info;
+			writer.write_all(&(info.base_address as u64).to_le_bytes())?;
+			writer.write_all(&(info.allocation_base as u64).to_le_bytes())?;
+			writer.write_all(&(info.region_size as u64).to_le_bytes())?;
+			writer.write_all(&info.allocation_protect.into_inner().to_le_bytes())?;
+			writer.write_all(&info.state.into_inner().to_le_bytes())?;
+			writer.write_all(&info.protect.into_inner().to_le_bytes())?;
+			writer.write_all(&info.mem_type.into_inner().to_le_bytes())?;
+			writer.write_all(&(region.bytes.len() as u64).to_le_bytes())?;
+			writer.write_all(&region.bytes)?;
+		}
+		Ok(())
+	}
+	/// Reads a snapshot written by [`write_to`](#method.write_to).
+	///
+	/// Fails with `ERROR_INVALID_DATA` if the data is not a valid snapshot, including data which ends early, or with the error of the reader.
+	pub fn read_from<R: io::Read>(mut reader: R) -> Result<MemorySnapshot> {
+		let mut magic = [0u8; 8];
+		read_exact(&mut reader, &mut magic)?;
+		if &magic != MAGIC || read_u32(&mut reader)? != VERSION {
+			return Err(ERROR_INVALID_DATA);
+		}
+		let count = read_u64(&mut reader)?;
+		let mut regions: Vec<SnapshotRegion> = Vec::new();
+		for _ in 0..count {
+			let base_address = read_usize(&mut reader)?;
+			let allocation_base = read_usize(&mut reader)?;
+			let region_size = read_usize(&mut reader)?;
+			let info = unsafe {
+				MemoryInformation {
+					base_address,
+					allocation_base,
+					allocation_protect: Protect::from_inner(read_u32(&mut reader)?),
+					region_size,
+					state: MemoryState::from_inner(read_u32(&mut reader)?),
+					protect: Protect::from_inner(read_u32(&mut reader)?),
+					mem_type: MemoryType::from_inner(read_u32(&mut reader)?),
+				}
+			};
+			let len = read_u64(&mut reader)?;
+			if len > region_size as u64 || base_address.checked_add(region_size).is_none()
+				|| regions.last().is_some_and(|last| last.range().end() > Ptr64::from(base_address as u64))
+			{
+				return Err(ERROR_INVALID_DATA);
+			}
+			// Read through `take` so a corrupt length cannot allocate more than the file holds
+			let mut bytes = Vec::new();
+			if (&mut reader).take(len).read_to_end(&mut bytes)? as u64 != len {
+				return Err(ERROR_INVALID_DATA);
+			}
+			regions.push(SnapshotRegion { info, bytes });
+		}
+		Ok(MemorySnapshot { regions })
+	}
+}
+
+// Appends the runs of differing bytes.
+fn diff_bytes(address: u64, old: &[u8], new: &[u8], changes: &mut Vec<ByteChange>) {
+	let mut index = 0;
+	while index < old.len() {
+		if old[index] == new[index] {
+			index += 1;
+			continue;
+		}
+		let start = index;
+		while index < old.len() && old[index] != new[index] {
+			index += 1;
+		}
+		changes.push(ByteChange {
+			address: Ptr64::from(address + start as u64),
+			old: old[start..index].to_vec(),
+			new: new[start..index].to_vec(),
+		});
+	}
+}
+
+// A file which ends early is not a valid snapshot, other io errors are passed on.
+fn read_exact<R: io::Read>(reader: &mut R, bytes: &mut [u8]) -> Result<()> {
+	match reader.read_exact(bytes) {
+		Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Err(ERROR_INVALID_DATA),
+		result => Ok(result?),
+	}
+}
+fn read_u32<R: io::Read>(reader: &mut R) -> Result<u32> {
+	let mut bytes = [0u8; 4];
+	read_exact(reader, &mut bytes)?;
+	Ok(u32::from_le_bytes(bytes))
+}
+fn read_u64<R: io::Read>(reader: &mut R) -> Result<u64> {
+	let mut bytes = [0u8; 8];
+	read_exact(reader, &mut bytes)?;
+	Ok(u64::from_le_bytes(bytes))
+}
+fn read_usize<R: io::Read>(reader: &mut R) -> Result<usize> {
+	let value = read_u64(reader)?;
+	if value > usize::MAX as u64 {
+		return Err(ERROR_INVALID_DATA);
+	}
+	Ok(value as usize)
+}
+
+/// Run of changed bytes in a [`SnapshotDiff`](struct.SnapshotDiff.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ByteChange {
+	pub address: Ptr64,
+	pub old: Vec<u8>,
+	pub new: Vec<u8>,
+}
+
+/// Changed bytes of a region in a [`SnapshotDiff`](struct.SnapshotDiff.html).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RegionDiff {
+	/// The region in the older snapshot.
+	pub region: MemoryInformation,
+	/// The runs of changed bytes sorted by address.
+	pub changes: Vec<ByteChange>,
+}
+
+/// Differences between two memory snapshots.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotDiff {
+	/// The regions with changed bytes sorted by address.
+	pub regions: Vec<RegionDiff>,
+	/// Regions of the older snapshot not in the newer snapshot.
+	pub removed: Vec<MemoryInformation>,
+	/// Regions of the newer snapshot not in the older snapshot.
+	pub added: Vec<MemoryInformation>,
+}
+impl SnapshotDiff {
+	/// Returns if the snapshots are the same.
+	pub fn is_empty(&self) -> bool {
+		self.regions.is_empty() && self.removed.is_empty() && self.added.is_empty()
+	}
+	/// The changes of all regions sorted by address.
+	pub fn changes(&self) -> impl Iterator<Item = &ByteChange> {
+		self.regions.iter().flat_map(|region| &region.changes)
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Read only address space of the captured bytes.
+///
+/// Writing fails with `ERROR_ACCESS_DENIED`, allocating, freeing and protecting with `ERROR_NOT_SUPPORTED`.
+impl VirtualMemory for MemorySnapshot {
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let len = bytes.len();
+		if self.vm_read_partial(address, bytes)?.len() == len {
+			Ok(bytes)
+		}
+		else {
+			Err(ERROR_PARTIAL_COPY)
+		}
+	}
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let mut bytes_read = 0;
+		if let Some(mut index) = self.position(address as u64) {
+			while bytes_read < dest.len() {
+				let region = match self.regions.get(index) {
+					Some(region) => region,
+					None => break,
+				};
+				let current = address + bytes_read;
+				let offset = match current.checked_sub(region.info.base_address) {
+					Some(offset) if offset < region.bytes.len() => offset,
+					_ => break,
+				};
+				let len = cmp::min(region.bytes.len() - offset, dest.len() - bytes_read);
+				dest[bytes_read..bytes_read + len].copy_from_slice(&region.bytes[offset..offset + len]);
+				bytes_read += len;
+				index += 1;
+			}
+		}
+		Ok(&mut dest[..bytes_read])
+	}
+	fn vm_write_bytes(&self, _address: usize, _bytes: &[u8]) -> Result<()> {
+		Err(ERROR_ACCESS_DENIED)
+	}
+	fn vm_write_partial<'a>(&self, _address: usize, _bytes: &'a [u8]) -> Result<&'a [u8]> {
+		Err(ERROR_ACCESS_DENIED)
+	}
+	fn vm_alloc(&self, _address: usize, _len: usize, _alloc_type: AllocType, _protect: Protect) -> Result<usize> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	fn vm_free(&self, _address: usize, _len: usize, _free_type: FreeType) -> Result<()> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	fn vm_protect(&self, _address: usize, _len: usize, _protect: Protect) -> Result<Protect> {
+		Err(ERROR_NOT_SUPPORTED)
+	}
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		if let Some(index) = self.position(address as u64) {
+			return Ok(self.regions[index].info);
+		}
+		// Memory between the captured regions is free
+		let index = self.regions.partition_point(|region| region.info.base_address <= address);
+		let base_address = self.regions.get(index.wrapping_sub(1)).map_or(0, |region| region.info.base_address + region.info.region_size);
+		let end = self.regions.get(index).map_or(usize::MAX, |region| region.info.base_address);
+		Ok(MemoryInformation {
+			base_address,
+			allocation_base: 0,
+			allocation_protect: Protect::default(),
+			region_size: end - base_address,
+			state: MemoryState::FREE,
+			protect: Protect::NO_ACCESS,
+			mem_type: MemoryType::default(),
+		})
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::ptr::Ptr;
+	use super::super::MockProcess;
+	use super::*;
+
+	fn process() -> (MockProcess, usize, usize) {
+		let process = MockProcess::new();
+		let data = process.map(0x10000, &[0; 0x2000], Protect::READ_WRITE).unwrap();
+		process.vm_protect(data + 0x1000, 0x1000, Protect::READ_ONLY).unwrap();
+		let heap = process.map(0x20000, &[0; 0x1000], Protect::READ_WRITE).unwrap();
+		process.map(0x30000, &[0; 0x1000], Protect::NO_ACCESS).unwrap();
+		(process, data, heap)
+	}
+
+	#[test]
+	fn capture() {
+		let (process, data, heap) = process();
+		process.poke(data + 0xFFE, &[1, 2, 3, 4]).unwrap();
+		let snapshot = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+		let bases: Vec<usize> = snapshot.regions().iter().map(|region| region.info.base_address).collect();
+		assert_eq!(bases, [data, data + 0x1000, heap]);
+		assert_eq!(snapshot.region(Ptr64::from(heap as u64 + 0x10)).map(|region| region.info.protect), Some(Protect::READ_WRITE));
+		assert_eq!(snapshot.region(Ptr64::from(0x30000)), None);
+
+		// Reads continue across adjacent regions
+		assert_eq!(snapshot.vm_read(Ptr::<u32>::from(data as u64 + 0xFFE)), Ok(0x04030201));
+		assert!(snapshot.vm_read(Ptr::<u32>::from(heap as u64 + 0xFFE)).is_err());
+		assert_eq!(snapshot.vm_write_bytes(heap, &[1]), Err(ERROR_ACCESS_DENIED));
+		assert_eq!(snapshot.vm_query(0x30000).map(|mi| (mi.base_address, mi.state)), Ok((heap + 0x1000, MemoryState::FREE)));
+
+		let range = AddressRange::from_len(Ptr64::from(data as u64 + 0x800), 0x1000);
+		let clipped = MemorySnapshot::capture(&process, range).unwrap();
+		let ranges: Vec<AddressRange> = clipped.regions().iter().map(|region| region.range()).collect();
+		assert_eq!(ranges, [AddressRange::from_len(range.start(), 0x800), AddressRange::from_len(Ptr64::from(data as u64 + 0x1000), 0x800)]);
+		let writable = MemorySnapshot::capture_with(&process, AddressRange::all(), |mi| mi.protect.is_writable()).unwrap();
+		assert_eq!(writable.regions().len(), 2);
+	}
+
+	#[test]
+	fn diff() {
+		let (process, data, heap) = process();
+		let before = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+		process.poke(data + 0x10, &[1, 1, 0, 1]).unwrap();
+		process.poke(heap + 0xFFF, &[5]).unwrap();
+		process.vm_release(data).unwrap();
+		process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap();
+		process.poke(0x10010, &[1, 1, 0, 1]).unwrap();
+		process.map(0x40000, &[0; 0x1000], Protect::READ_ONLY).unwrap();
+		let after = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+
+		let diff = before.diff(&after);
+		let changes: Vec<(u64, &[u8], &[u8])> = diff.changes().map(|change| (change.address.into_raw(), &*change.old, &*change.new)).collect();
+		assert_eq!(changes, [
+			(data as u64 + 0x10, &[0, 0][..], &[1, 1][..]),
+			(data as u64 + 0x13, &[0][..], &[1][..]),
+			(heap as u64 + 0xFFF, &[0][..], &[5][..]),
+		]);
+		assert_eq!(diff.regions.iter().map(|region| region.region.base_address).collect::<Vec<_>>(), [data, heap]);
+		assert_eq!(diff.removed.iter().map(|mi| mi.base_address).collect::<Vec<_>>(), [data + 0x1000]);
+		assert_eq!(diff.added.iter().map(|mi| mi.base_address).collect::<Vec<_>>(), [0x40000]);
+		assert!(after.diff(&after).is_empty());
+	}
+
+	#[test]
+	fn save_load() {
+		let (process, data, _) = process();
+		process.poke(data + 0x20, b"snapshot").unwrap();
+		let snapshot = MemorySnapshot::capture(&process, AddressRange::all()).unwrap();
+
+		let mut file = Vec::new();
+		snapshot.write_to(&mut file).unwrap();
+		assert_eq!(MemorySnapshot::read_from(&file[..]), Ok(snapshot.clone()));
+		assert_eq!(MemorySnapshot::read_from(&file[..file.len() - 1]), Err(ERROR_INVALID_DATA));
+		assert_eq!(MemorySnapshot::read_from(&b"EXTSNAP\0\x02\0\0\0"[..]), Err(ERROR_INVALID_DATA));
+		// Cut off inside the magic, the header and the first region record
+		assert_eq!(MemorySnapshot::read_from(&file[..4]), Err(ERROR_INVALID_DATA));
+		assert_eq!(MemorySnapshot::read_from(&b"EXTSNAP\0"[..]), Err(ERROR_INVALID_DATA));
+		assert_eq!(MemorySnapshot::read_from(&file[..30]), Err(ERROR_INVALID_DATA));
+
+		let path = std::env::temp_dir().join(format!("external-snapshot-{}.bin", std::process::id()));
+		snapshot.save(&path).unwrap();
+		let loaded = MemorySnapshot::load(&path);
+		fs::remove_file(&path).unwrap();
+		let loaded = loaded.unwrap();
+		assert_eq!(loaded.vm_read(Ptr::<[u8; 8]>::from(data as u64 + 0x20)), Ok(*b"snapshot"));
+		assert!(loaded.diff(&snapshot).is_empty());
+	}
+}
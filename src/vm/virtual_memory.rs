@@ -0,0 +1,269 @@
+use std::{cmp, mem, ops, slice};
+use std::mem::MaybeUninit;
+use crate::error::{ERROR_INVALID_DATA, ERROR_INVALID_PARAMETER};
+use crate::ptr::{Arch, FromRemoteBytes, Pod, RemotePtr, TargetArch};
+use crate::Result;
+use super::{batch, walk, AddressRange, AllocType, FreeType, Protect, MemoryInformation, ReadRequest, ListEntry, ListIter, ArrayIter};
+
+/// Virtual memory API.
+///
+/// The required methods are the primitives a backend must provide, the typed helpers are built on top of them.
+pub trait VirtualMemory {
+	/// Reads bytes into the destination buffer.
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]>;
+	/// Reads as many bytes as are available.
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]>;
+	/// Writes bytes.
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()>;
+	/// Writes as many bytes as it can.
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]>;
+	/// Allocates memomry in the process.
+	fn vm_alloc(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect) -> Result<usize>;
+	/// Frees memory in the process.
+	fn vm_free(&self, address: usize, len: usize, free_type: FreeType) -> Result<()>;
+	/// Changes memory protection in the process.
+	fn vm_protect(&self, address: usize, len: usize, protect: Protect) -> Result<Protect>;
+	/// Queries the state of virtual memory in the process.
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation>;
+
+	/// Reads a `T` from the process.
+	///
+	/// Types which are not valid for every bit pattern are validated after the read, see [`FromRemoteBytes`](../ptr/trait.FromRemoteBytes.html).
+	/// Fails with `ERROR_INVALID_DATA` if the bytes read are not a valid `T`.
+	#[inline]
+	fn vm_read<T: FromRemoteBytes, P: RemotePtr<Target = T>>(&self, ptr: P) -> Result<T> {
+		let address = ptr.into_address() as usize;
+		let mut dest = MaybeUninit::<T>::zeroed();
+		// The zeroed bytes are initialized, they only become a `T` once validated.
+		let bytes = unsafe { slice::from_raw_parts_mut(dest.as_mut_ptr() as *mut u8, mem::size_of::<T>()) };
+		self.vm_read_bytes(address, bytes)?;
+		if !T::validate(bytes) {
+			return Err(ERROR_INVALID_DATA);
+		}
+		Ok(unsafe { dest.assume_init() })
+	}
+	/// Reads a slice of Pod `T` from the process.
+	#[inline]
+	fn vm_read_into<'a, T: Pod + ?Sized, P: RemotePtr<Target = T>>(&self, ptr: P, dest: &'a mut T) -> Result<&'a mut T> {
+		let address = ptr.into_address() as usize;
+		match self.vm_read_bytes(address, dest.as_bytes_mut()) {
+			Ok(_) => Ok(dest),
+			Err(err) => Err(err),
+		}
+	}
+	/// Reads a number of Pod `T` and appends the read elements to the given Vec.
+	#[inline]
+	fn vm_read_append<'a, T: Pod, P: RemotePtr<Target = [T]>>(&self, ptr: P, dest: &'a mut Vec<T>, len: usize) -> Result<&'a mut [T]> {
+		let old_len = dest.len();
+		let new_len = usize::checked_add(old_len, len).expect("overflow");
+		if dest.capacity() < new_len {
+			let additional = new_len - dest.capacity();
+			dest.reserve(additional);
+		}
+		// Zero the spare capacity so it can be read into as bytes, any bit pattern is a valid Pod.
+		let spare = &mut dest.spare_capacity_mut()[..len];
+		let bytes = unsafe {
+			spare.as_mut_ptr().write_bytes(0, len);
+			slice::from_raw_parts_mut(spare.as_mut_ptr() as *mut u8, mem::size_of_val(spare))
+		};
+		self.vm_read_bytes(ptr.into_address() as usize, bytes)?;
+		unsafe { dest.set_len(new_len) };
+		Ok(&mut dest[old_len..])
+	}
+	/// Reads a pointer of the target architecture chosen at runtime.
+	#[inline]
+	fn vm_read_ptr(&self, arch: TargetArch, address: usize) -> Result<u64> {
+		let mut bytes = [0u8; 8];
+		let bytes = self.vm_read_bytes(address, &mut bytes[..arch.pointer_size()])?;
+		Ok(bytes.iter().rev().fold(0, |value, &byte| (value << 8) | byte as u64))
+	}
+	/// Reads multiple ranges in order, stopping at the first range which cannot be read completely.
+	///
+	/// Returns the total number of bytes read, the ranges before the stopping range are read completely.
+	/// Backends override this to read all ranges with a single call where the OS supports it.
+	#[inline]
+	fn vm_read_scatter(&self, iov: &mut [(usize, &mut [u8])]) -> Result<usize> {
+		batch::read_scatter(self, iov)
+	}
+	/// Reads a batch of requests with as few reads as possible.
+	///
+	/// Requests close to each other are coalesced into a single read, the coalesced reads are passed to `vm_read_scatter`.
+	/// The outcome of every request is reported as if it were read with `vm_read_partial`, see [`ReadRequest`](struct.ReadRequest.html).
+	#[inline]
+	fn vm_read_batch(&self, requests: &mut [ReadRequest]) {
+		batch::read_batch(self, requests)
+	}
+	/// Iterates over a circular doubly linked list, yielding pointers to the records containing the links.
+	///
+	/// The `head` is the list head which is not part of a record, the link field is at `link_offset` bytes into the records.
+	/// Fails with `ERROR_INVALID_DATA` if the list is broken or loops without returning to the head and with `ERROR_INSUFFICIENT_BUFFER` after `max_len` records.
+	#[inline]
+	fn vm_list<A: Arch, T: ?Sized, P: RemotePtr<Target = ListEntry<A>>>(&self, head: P, link_offset: usize, max_len: usize) -> ListIter<'_, Self, A, T> {
+		ListIter::new(self, head, link_offset, max_len)
+	}
+	/// Iterates over the elements of an array, the elements are read in chunks.
+	#[inline]
+	fn vm_array<T: Pod, P: RemotePtr<Target = [T]>>(&self, ptr: P, len: usize) -> ArrayIter<'_, Self, T> {
+		ArrayIter::new(self, ptr.into_address(), len)
+	}
+	/// Reads a null terminated string, the terminator is not included.
+	///
+	/// Fails with `ERROR_INSUFFICIENT_BUFFER` if no terminator is found within `max_len` characters.
+	#[inline]
+	fn vm_read_cstr<P: RemotePtr<Target = [u8]>>(&self, ptr: P, max_len: usize) -> Result<Vec<u8>> {
+		walk::read_str(self, ptr.into_address(), max_len)
+	}
+	/// Reads a null terminated wide string, the terminator is not included.
+	///
+	/// Fails with `ERROR_INSUFFICIENT_BUFFER` if no terminator is found within `max_len` characters.
+	#[inline]
+	fn vm_read_wstr<P: RemotePtr<Target = [u16]>>(&self, ptr: P, max_len: usize) -> Result<Vec<u16>> {
+		walk::read_str(self, ptr.into_address(), max_len)
+	}
+	/// Writes the Pod `T` to the process.
+	#[inline]
+	fn vm_write<T: ?Sized + Pod, P: RemotePtr<Target = T>>(&self, ptr: P, val: &T) -> Result<()> {
+		let address = ptr.into_address() as usize;
+		self.vm_write_bytes(address, val.as_bytes())
+	}
+	/// Writes a sub range of the Pod `T` to the process.
+	/// Panics if the range falls outside the bytes of the given value.
+	#[inline]
+	fn vm_write_range<T: Pod, P: RemotePtr<Target = T>>(&self, ptr: P, val: &T, range: ops::Range<usize>) -> Result<()> {
+		let address = ptr.into_address() as usize + range.start;
+		let val = &val.as_bytes()[range];
+		self.vm_write_bytes(address, val)
+	}
+	/// Commits memory in the process.
+	#[inline]
+	fn vm_commit(&self, address: usize, len: usize, protect: Protect) -> Result<usize> {
+		self.vm_alloc(address, len, AllocType::COMMIT, protect)
+	}
+	/// Reserves memory in the process.
+	#[inline]
+	fn vm_reserve(&self, address: usize, len: usize, protect: Protect) -> Result<usize> {
+		self.vm_alloc(address, len, AllocType::RESERVE, protect)
+	}
+	/// Decommits memory in the process.
+	#[inline]
+	fn vm_decommit(&self, address: usize, len: usize) -> Result<()> {
+		self.vm_free(address, len, FreeType::DECOMMIT)
+	}
+	/// Releases memory in the process.
+	#[inline]
+	fn vm_release(&self, address: usize) -> Result<()> {
+		self.vm_free(address, 0, FreeType::RELEASE)
+	}
+	/// Foreach virtual memory region in the specified address range.
+	#[inline]
+	fn vm_regions<F: FnMut(&MemoryInformation)>(&self, mut base_address: usize, size: usize, mut f: F) -> Result<()> {
+		let end_address = base_address + size;
+		while base_address < end_address {
+			let mi = self.vm_query(base_address)?;
+			f(&mi);
+			base_address = mi.base_address + mi.region_size;
+		}
+		Ok(())
+	}
+}
+
+// Foreach virtual memory region in the range, clipped to the range.
+//
+// The walk ends with `ERROR_INVALID_PARAMETER` when it queries past the end of the address space.
+// This ends the walk once a region was reported, but is an error if the range starts past the end.
+pub(crate) fn regions_in<V, F>(vm: &V, range: AddressRange, mut f: F) -> Result<()>
+	where V: VirtualMemory + ?Sized, F: FnMut(&MemoryInformation)
+{
+	let start = cmp::min(range.start().into_raw(), usize::MAX as u64) as usize;
+	let end = cmp::min(range.end().into_raw(), usize::MAX as u64) as usize;
+	let mut reported = false;
+	let result = vm.vm_regions(start, end - start, |mi| {
+		reported = true;
+		if let Some(clipped) = AddressRange::from(mi).intersect(range) {
+			let mut mi = *mi;
+			mi.base_address = clipped.start().into_raw() as usize;
+			mi.region_size = clipped.len() as usize;
+			f(&mi);
+		}
+	});
+	match result {
+		Err(ERROR_INVALID_PARAMETER) if reported => Ok(()),
+		result => result,
+	}
+}
+
+/// Virtual memory API through a shared reference, lets wrappers borrow their backend.
+impl<V: VirtualMemory + ?Sized> VirtualMemory for &V {
+	#[inline]
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		(**self).vm_read_bytes(address, bytes)
+	}
+	#[inline]
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		(**self).vm_read_partial(address, dest)
+	}
+	#[inline]
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		(**self).vm_write_bytes(address, bytes)
+	}
+	#[inline]
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]> {
+		(**self).vm_write_partial(address, bytes)
+	}
+	#[inline]
+	fn vm_alloc(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect) -> Result<usize> {
+		(**self).vm_alloc(address, len, alloc_type, protect)
+	}
+	#[inline]
+	fn vm_free(&self, address: usize, len: usize, free_type: FreeType) -> Result<()> {
+		(**self).vm_free(address, len, free_type)
+	}
+	#[inline]
+	fn vm_protect(&self, address: usize, len: usize, protect: Protect) -> Result<Protect> {
+		(**self).vm_protect(address, len, protect)
+	}
+	#[inline]
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		(**self).vm_query(address)
+	}
+	#[inline]
+	fn vm_read_scatter(&self, iov: &mut [(usize, &mut [u8])]) -> Result<usize> {
+		(**self).vm_read_scatter(iov)
+	}
+	#[inline]
+	fn vm_regions<F: FnMut(&MemoryInformation)>(&self, base_address: usize, size: usize, f: F) -> Result<()> {
+		(**self).vm_regions(base_address, size, f)
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::ptr::Ptr64;
+	use super::super::{MemoryState, MockProcess};
+	use super::*;
+
+	#[test]
+	fn regions() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x3000], Protect::READ_WRITE).unwrap() as u64;
+		let mut committed = Vec::new();
+		regions_in(&process, AddressRange::all(), |mi| {
+			if mi.state == MemoryState::COMMIT {
+				committed.push(AddressRange::from(mi));
+			}
+		}).unwrap();
+		assert_eq!(committed, [AddressRange::from_len(Ptr64::from(base), 0x3000)]);
+
+		// Clipped to the range
+		let range = AddressRange::from_len(Ptr64::from(base + 0x800), 0x1000);
+		let mut clipped = Vec::new();
+		regions_in(&process, range, |mi| clipped.push(AddressRange::from(mi))).unwrap();
+		assert_eq!(clipped, [range]);
+
+		// A range past the end of the address space is an error
+		let past = AddressRange::from_len(Ptr64::from(u64::MAX - 0x1000), 0x1000);
+		assert_eq!(regions_in(&process, past, |_| ()), Err(ERROR_INVALID_PARAMETER));
+	}
+}
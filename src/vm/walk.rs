@@ -0,0 +1,260 @@
+use std::{cmp, fmt, mem};
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use crate::ptr::{Arch, HostArch, Pod, RemotePtr};
+use crate::error::{ErrorCode, ERROR_INSUFFICIENT_BUFFER, ERROR_INVALID_DATA, ERROR_PARTIAL_COPY};
+use crate::Result;
+use super::VirtualMemory;
+
+// Strings and arrays are read in chunks of this many bytes.
+const CHUNK_SIZE: usize = 0x1000;
+
+//----------------------------------------------------------------
+
+/// Doubly linked list entry.
+///
+/// See [LIST_ENTRY](https://msdn.microsoft.com/en-us/library/windows/desktop/aa489548.aspx) for more information.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Pod)]
+#[repr(C)]
+pub struct ListEntry<A: Arch = HostArch> {
+	pub flink: A::Ptr<ListEntry<A>>,
+	pub blink: A::Ptr<ListEntry<A>>,
+}
+
+/// Iterator over a circular doubly linked list in another process.
+///
+/// See [`vm_list`](trait.VirtualMemory.html#method.vm_list) for more information.
+pub struct ListIter<'a, V: ?Sized, A: Arch, T: ?Sized> {
+	vm: &'a V,
+	head: A::Ptr<ListEntry<A>>,
+	next: A::Ptr<ListEntry<A>>,
+	link_offset: usize,
+	remaining: usize,
+	visited: HashSet<u64>,
+	done: bool,
+	_phantom: PhantomData<fn() -> T>,
+}
+impl<'a, V: VirtualMemory + ?Sized, A: Arch, T: ?Sized> ListIter<'a, V, A, T> {
+	pub(crate) fn new<P: RemotePtr<Target = ListEntry<A>>>(vm: &'a V, head: P, link_offset: usize, max_len: usize) -> ListIter<'a, V, A, T> {
+		let head = A::Ptr::from_address(head.into_address());
+		ListIter { vm, head, next: head, link_offset, remaining: max_len, visited: HashSet::new(), done: false, _phantom: PhantomData }
+	}
+	fn fail(&mut self, err: ErrorCode) -> Option<Result<A::Ptr<T>>> {
+		self.done = true;
+		Some(Err(err))
+	}
+}
+impl<'a, V: VirtualMemory + ?Sized, A: Arch, T: ?Sized> Iterator for ListIter<'a, V, A, T> {
+	type Item = Result<A::Ptr<T>>;
+	fn next(&mut self) -> Option<Result<A::Ptr<T>>> {
+		if self.done {
+			return None;
+		}
+		let entry: ListEntry<A> = match self.vm.vm_read(self.next) {
+			Ok(entry) => entry,
+			Err(err) => return self.fail(err),
+		};
+		if entry.flink == self.head {
+			self.done = true;
+			return None;
+		}
+		// Corrupted lists may be broken or loop without returning to the head
+		let flink = entry.flink.into_address();
+		if flink == 0 || !self.visited.insert(flink) {
+			return self.fail(ERROR_INVALID_DATA);
+		}
+		if self.remaining == 0 {
+			return self.fail(ERROR_INSUFFICIENT_BUFFER);
+		}
+		self.remaining -= 1;
+		self.next = entry.flink;
+		Some(Ok(A::Ptr::from_address(flink.wrapping_sub(self.link_offset as u64))))
+	}
+}
+impl<'a, V: ?Sized, A: Arch, T: ?Sized> fmt::Debug for ListIter<'a, V, A, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ListIter")
+			.field("head", &self.head)
+			.field("next", &self.next)
+			.field("link_offset", &self.link_offset)
+			.field("remaining", &self.remaining)
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+/// Iterator over an array in another process.
+///
+/// See [`vm_array`](trait.VirtualMemory.html#method.vm_array) for more information.
+pub struct ArrayIter<'a, V: ?Sized, T> {
+	vm: &'a V,
+	address: u64,
+	len: usize,
+	index: usize,
+	chunk: Vec<u8>,
+	chunk_index: usize,
+	done: bool,
+	_phantom: PhantomData<fn() -> T>,
+}
+impl<'a, V: VirtualMemory + ?Sized, T: Pod> ArrayIter<'a, V, T> {
+	pub(crate) fn new(vm: &'a V, address: u64, len: usize) -> ArrayIter<'a, V, T> {
+		ArrayIter { vm, address, len, index: 0, chunk: Vec::new(), chunk_index: 0, done: false, _phantom: PhantomData }
+	}
+	fn fetch(&mut self) -> Result<()> {
+		let size = cmp::max(mem::size_of::<T>(), 1);
+		let count = cmp::min(cmp::max(CHUNK_SIZE / size, 1), self.len - self.index);
+		self.chunk.resize(count * mem::size_of::<T>(), 0);
+		let address = self.address.wrapping_add((self.index * mem::size_of::<T>()) as u64);
+		self.vm.vm_read_bytes(address as usize, &mut self.chunk)?;
+		self.chunk_index = self.index;
+		Ok(())
+	}
+}
+impl<'a, V: VirtualMemory + ?Sized, T: Pod> Iterator for ArrayIter<'a, V, T> {
+	type Item = Result<T>;
+	fn next(&mut self) -> Option<Result<T>> {
+		if self.done || self.index >= self.len {
+			return None;
+		}
+		let size = mem::size_of::<T>();
+		let offset = (self.index - self.chunk_index) * size;
+		if self.chunk.is_empty() || offset + size > self.chunk.len() {
+			if let Err(err) = self.fetch() {
+				self.done = true;
+				return Some(Err(err));
+			}
+		}
+		let offset = (self.index - self.chunk_index) * size;
+		// Any bit pattern is a valid Pod, the zeroes are overwritten.
+		let mut value: T = unsafe { mem::zeroed() };
+		value.as_bytes_mut().copy_from_slice(&self.chunk[offset..offset + size]);
+		self.index += 1;
+		Some(Ok(value))
+	}
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let remaining = if self.done { 0 } else { self.len - self.index };
+		(0, Some(remaining))
+	}
+}
+impl<'a, V: ?Sized, T> fmt::Debug for ArrayIter<'a, V, T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("ArrayIter")
+			.field("address", &format_args!("{:#x}", self.address))
+			.field("len", &self.len)
+			.field("index", &self.index)
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+// Reads a null terminated string of Pod `T`, the terminator is not included.
+pub(crate) fn read_str<V: VirtualMemory + ?Sized, T: Pod + Copy + Default + Eq>(vm: &V, address: u64, max_len: usize) -> Result<Vec<T>> {
+	let size = mem::size_of::<T>();
+	let mut string = Vec::new();
+	let mut chunk = Vec::new();
+	loop {
+		// Read up to the next chunk boundary to avoid spanning into unmapped pages unnecessarily
+		let address = address.wrapping_add((string.len() * size) as u64) as usize;
+		let count = cmp::max((CHUNK_SIZE - address % CHUNK_SIZE) / size, 1);
+		let count = cmp::min(count, max_len.saturating_add(1) - string.len());
+		chunk.clear();
+		chunk.resize(count, T::default());
+		let read = vm.vm_read_partial(address, chunk[..].as_bytes_mut())?.len() / size;
+		if read == 0 {
+			return Err(ERROR_PARTIAL_COPY);
+		}
+		if let Some(end) = chunk[..read].iter().position(|&c| c == T::default()) {
+			string.extend_from_slice(&chunk[..end]);
+			return Ok(string);
+		}
+		string.extend_from_slice(&chunk[..read]);
+		if string.len() > max_len {
+			return Err(ERROR_INSUFFICIENT_BUFFER);
+		}
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::ptr::{Ptr, Ptr32, X86};
+	use super::super::*;
+	use super::*;
+
+	#[repr(C)]
+	struct Record {
+		value: u64,
+		links: ListEntry,
+	}
+
+	fn entry(flink: usize, blink: usize) -> ListEntry {
+		ListEntry { flink: Ptr::from(flink as u64), blink: Ptr::from(blink as u64) }
+	}
+
+	#[test]
+	fn list() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap();
+		// Head followed by three records linked through the field at offset 8
+		let head = base;
+		let records = [base + 0x100, base + 0x200, base + 0x300];
+		process.vm_write(Ptr::from(head as u64), &entry(records[0] + 8, records[2] + 8)).unwrap();
+		process.vm_write(Ptr::from(records[0] as u64 + 8), &entry(records[1] + 8, head)).unwrap();
+		process.vm_write(Ptr::from(records[1] as u64 + 8), &entry(records[2] + 8, records[0] + 8)).unwrap();
+		process.vm_write(Ptr::from(records[2] as u64 + 8), &entry(head, records[1] + 8)).unwrap();
+
+		let head = Ptr::<ListEntry>::from(head as u64);
+		let walked: Vec<Ptr<Record>> = process.vm_list(head, 8, 16).collect::<Result<_>>().unwrap();
+		assert_eq!(walked, records.iter().map(|&record| Ptr::from(record as u64)).collect::<Vec<_>>());
+		assert_eq!(process.vm_list::<_, Record, _>(head, 8, 2).last(), Some(Err(ERROR_INSUFFICIENT_BUFFER)));
+
+		// Loop back to the second record instead of the head
+		process.vm_write(Ptr::from(records[2] as u64 + 8), &entry(records[1] + 8, records[1] + 8)).unwrap();
+		assert_eq!(process.vm_list::<_, Record, _>(head, 8, 16).last(), Some(Err(ERROR_INVALID_DATA)));
+	}
+
+	#[test]
+	fn list32() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0; 0x1000], Protect::READ_WRITE).unwrap() as u32;
+		let entry = |flink: u32, blink: u32| ListEntry::<X86> { flink: Ptr32::from(flink), blink: Ptr32::from(blink) };
+		process.vm_write(Ptr32::from(base), &entry(base + 0x104, base + 0x104)).unwrap();
+		process.vm_write(Ptr32::from(base + 0x104), &entry(base, base)).unwrap();
+		let walked: Vec<Ptr32<u32>> = process.vm_list(Ptr32::<ListEntry<X86>>::from(base), 4, 16).collect::<Result<_>>().unwrap();
+		assert_eq!(walked, [Ptr32::from(base + 0x100)]);
+	}
+
+	#[test]
+	fn array() {
+		let process = MockProcess::new();
+		let values: Vec<u32> = (0..0x500).collect();
+		let base = process.map(0x10000, values[..].as_bytes(), Protect::READ_ONLY).unwrap();
+		let ptr = Ptr::<[u32]>::from(base as u64);
+		assert!(process.vm_array(ptr, 0x500).map(Result::unwrap).eq(values.iter().cloned()));
+		let mut iter = process.vm_array(ptr, 0xC00);
+		assert_eq!(iter.by_ref().take(0x800).count(), 0x800);
+		assert_eq!(iter.next(), Some(Err(ERROR_PARTIAL_COPY)));
+		assert_eq!(iter.next(), None);
+	}
+
+	#[test]
+	fn strings() {
+		let process = MockProcess::new();
+		let base = process.map(0x10000, &[0x41; 0x1000], Protect::READ_WRITE).unwrap();
+		process.vm_write_bytes(base + 0x20, b"hello\0").unwrap();
+		let wide: Vec<u16> = "wide\0".encode_utf16().collect();
+		process.vm_write(Ptr::<[u16]>::from(base as u64 + 0x40), &wide[..]).unwrap();
+		assert_eq!(process.vm_read_cstr(Ptr::from(base as u64 + 0x20), 16), Ok(b"hello".to_vec()));
+		assert_eq!(process.vm_read_cstr(Ptr::from(base as u64 + 0x20), 5), Ok(b"hello".to_vec()));
+		assert_eq!(process.vm_read_cstr(Ptr::from(base as u64 + 0x20), 4), Err(ERROR_INSUFFICIENT_BUFFER));
+		assert_eq!(process.vm_read_wstr(Ptr::from(base as u64 + 0x40), 16), Ok(wide[..4].to_vec()));
+		// No limit on the length
+		assert_eq!(process.vm_read_cstr(Ptr::from(base as u64 + 0x20), usize::MAX), Ok(b"hello".to_vec()));
+		assert_eq!(process.vm_read_wstr(Ptr::from(base as u64 + 0x40), usize::MAX), Ok(wide[..4].to_vec()));
+		// Runs into the end of the allocation without a terminator
+		assert_eq!(process.vm_read_cstr(Ptr::from(base as u64 + 0x100), 0x10000), Err(ERROR_PARTIAL_COPY));
+	}
+}
@@ -0,0 +1,276 @@
+use std::{fmt, thread};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::error::{ErrorCode, ERROR_TIMEOUT};
+use crate::ptr::{Pod, Ptr64, RemotePtr};
+use crate::Result;
+use super::VirtualMemory;
+
+/// Identifies a watch of a [`MemoryWatcher`](struct.MemoryWatcher.html).
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct WatchId(u64);
+
+/// Event of a watched value.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum WatchEvent<T> {
+	/// The value changed since the previous sample.
+	Changed { old: T, new: T, time: Instant },
+	/// Reading the value failed, reported once until reading succeeds again.
+	ReadFailed { error: ErrorCode, time: Instant },
+	/// Reading the value succeeded again after failing.
+	Recovered { value: T, time: Instant },
+}
+
+enum Sample<T> {
+	// The first read failed and has not been reported yet.
+	Unread,
+	Value(T),
+	Failed,
+}
+
+type Sampler<V> = Box<dyn FnMut(&V, Instant) + Send>;
+
+struct Watches<V: ?Sized> {
+	next_id: u64,
+	list: Vec<(WatchId, Sampler<V>)>,
+}
+
+struct Shared<V: ?Sized> {
+	watches: Mutex<Watches<V>>,
+	interval: Mutex<Duration>,
+}
+
+/// Samples typed values on a background thread and reports their changes.
+///
+/// Every interval each watch reads its value and compares it with the previous sample.
+/// Changes are delivered to a callback or a channel, a watch whose reads start failing, e.g. because the process exited, reports [`ReadFailed`](enum.WatchEvent.html#variant.ReadFailed).
+///
+/// Callbacks run on the background thread while the watches are locked, they must not add or remove watches.
+/// Dropping the watcher stops the thread.
+///
+/// # Examples
+///
+/// ```
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use external::ptr::Ptr64;
+/// use external::vm::{MemoryWatcher, MockProcess, Protect, WatchEvent};
+///
+/// let process = Arc::new(MockProcess::new());
+/// let base = process.map(0x10000, &[0; 4], Protect::READ_WRITE).unwrap();
+/// let health = Ptr64::<u32>::from(base as u64);
+///
+/// let watcher = MemoryWatcher::new(process.clone(), Duration::from_millis(1));
+/// let (_, events) = watcher.watch_channel(health);
+/// process.poke(base, &100u32.to_le_bytes()).unwrap();
+/// match events.recv().unwrap() {
+///     WatchEvent::Changed { old, new, .. } => assert_eq!((old, new), (0, 100)),
+///     event => panic!("unexpected {:?}", event),
+/// }
+/// ```
+pub struct MemoryWatcher<V: ?Sized> {
+	vm: Arc<V>,
+	shared: Arc<Shared<V>>,
+	stop: Option<mpsc::Sender<()>>,
+	thread: Option<thread::JoinHandle<()>>,
+}
+impl<V: VirtualMemory + Send + Sync + ?Sized + 'static> MemoryWatcher<V> {
+	/// Starts the background thread sampling the watches at the interval.
+	pub fn new(vm: Arc<V>, interval: Duration) -> MemoryWatcher<V> {
+		let shared = Arc::new(Shared {
+			watches: Mutex::new(Watches { next_id: 0, list: Vec::new() }),
+			interval: Mutex::new(interval),
+		});
+		let (stop, stopped) = mpsc::channel();
+		let thread = {
+			let vm = vm.clone();
+			let shared = shared.clone();
+			thread::spawn(move || loop {
+				let time = Instant::now();
+				for (_, sample) in &mut shared.watches.lock().unwrap().list {
+					sample(&*vm, time);
+				}
+				let interval = *shared.interval.lock().unwrap();
+				// Sending is never done, the sender is dropped to stop the thread
+				if stopped.recv_timeout(interval.saturating_sub(time.elapsed())) != Err(mpsc::RecvTimeoutError::Timeout) {
+					break;
+				}
+			})
+		};
+		MemoryWatcher { vm, shared, stop: Some(stop), thread: Some(thread) }
+	}
+	/// The memory being watched.
+	pub fn vm(&self) -> &V {
+		&self.vm
+	}
+	/// The time between samples.
+	pub fn interval(&self) -> Duration {
+		*self.shared.interval.lock().unwrap()
+	}
+	/// Sets the time between samples, takes effect after the next sample.
+	pub fn set_interval(&self, interval: Duration) {
+		*self.shared.interval.lock().unwrap() = interval;
+	}
+	/// Watches the value pointed to, calling the callback for every event.
+	///
+	/// The value is read right away, changes are relative to this first sample.
+	/// The callback is not called for the first sample, a first read which fails is reported by the background thread.
+	pub fn watch<T, P, F>(&self, ptr: P, mut callback: F) -> WatchId
+		where T: Pod + Copy + PartialEq + Send + 'static, P: RemotePtr<Target = T>, F: FnMut(WatchEvent<T>) + Send + 'static
+	{
+		let ptr = Ptr64::<T>::from(ptr.into_address());
+		let mut last = match self.vm.vm_read(ptr) {
+			Ok(value) => Sample::Value(value),
+			Err(_) => Sample::Unread,
+		};
+		let sample = move |vm: &V, time: Instant| {
+			let result = vm.vm_read(ptr);
+			match (&last, result) {
+				(&Sample::Value(old), Ok(new)) if old != new => callback(WatchEvent::Changed { old, new, time }),
+				(&Sample::Failed, Ok(value)) => callback(WatchEvent::Recovered { value, time }),
+				(&Sample::Unread, Err(error)) | (&Sample::Value(_), Err(error)) => callback(WatchEvent::ReadFailed { error, time }),
+				_ => (),
+			}
+			last = match result {
+				Ok(value) => Sample::Value(value),
+				Err(_) => Sample::Failed,
+			};
+		};
+		let mut watches = self.shared.watches.lock().unwrap();
+		let id = WatchId(watches.next_id);
+		watches.next_id += 1;
+		watches.list.push((id, Box::new(sample)));
+		id
+	}
+	/// Watches the value pointed to, sending the events to the returned channel.
+	pub fn watch_channel<T, P>(&self, ptr: P) -> (WatchId, mpsc::Receiver<WatchEvent<T>>)
+		where T: Pod + Copy + PartialEq + Send + 'static, P: RemotePtr<Target = T>
+	{
+		let (tx, rx) = mpsc::channel();
+		let id = self.watch(ptr, move |event| {
+			// The receiver may have been dropped, the watch stays until removed
+			let _ = tx.send(event);
+		});
+		(id, rx)
+	}
+	/// Removes a watch, returns false if it was already removed.
+	pub fn unwatch(&self, id: WatchId) -> bool {
+		let mut watches = self.shared.watches.lock().unwrap();
+		let len = watches.list.len();
+		watches.list.retain(|&(watch_id, _)| watch_id != id);
+		watches.list.len() != len
+	}
+	/// Blocks until the value pointed to satisfies the predicate.
+	///
+	/// The value is sampled at the interval of the watcher on the calling thread.
+	/// Fails with the error of a failed read, or `ERROR_TIMEOUT` if the predicate is not satisfied in time.
+	pub fn wait_until<T, P, F>(&self, ptr: P, mut predicate: F, timeout: Duration) -> Result<T>
+		where T: Pod + Copy, P: RemotePtr<Target = T>, F: FnMut(&T) -> bool
+	{
+		let ptr = Ptr64::<T>::from(ptr.into_address());
+		let start = Instant::now();
+		loop {
+			let value = self.vm.vm_read(ptr)?;
+			if predicate(&value) {
+				return Ok(value);
+			}
+			let elapsed = start.elapsed();
+			if elapsed >= timeout {
+				return Err(ERROR_TIMEOUT);
+			}
+			thread::sleep(Ord::min(self.interval(), timeout - elapsed));
+		}
+	}
+}
+impl<V: ?Sized> Drop for MemoryWatcher<V> {
+	fn drop(&mut self) {
+		drop(self.stop.take());
+		if let Some(thread) = self.thread.take() {
+			let _ = thread.join();
+		}
+	}
+}
+impl<V: ?Sized> fmt::Debug for MemoryWatcher<V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("MemoryWatcher")
+			.field("interval", &*self.shared.interval.lock().unwrap())
+			.field("watches", &self.shared.watches.lock().unwrap().list.len())
+			.finish()
+	}
+}
+
+//----------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+	use crate::error::ERROR_PARTIAL_COPY;
+	use super::super::{MockProcess, Protect};
+	use super::*;
+
+	const TIMEOUT: Duration = Duration::from_secs(5);
+
+	#[test]
+	fn events() {
+		let process = Arc::new(MockProcess::new());
+		let base = process.map(0x10000, &[0; 8], Protect::READ_WRITE).unwrap();
+		let value = Ptr64::<u32>::from(base as u64);
+		let watcher = MemoryWatcher::new(process.clone(), Duration::from_millis(1));
+		let (id, events) = watcher.watch_channel(value);
+		let (tx, calls) = mpsc::channel();
+		watcher.watch(Ptr64::<u16>::from(base as u64 + 4), move |event| { let _ = tx.send(event); });
+
+		process.poke(base, &7u32.to_le_bytes()).unwrap();
+		match events.recv_timeout(TIMEOUT).unwrap() {
+			WatchEvent::Changed { old, new, .. } => assert_eq!((old, new), (0, 7)),
+			event => panic!("unexpected {:?}", event),
+		}
+		process.poke(base + 4, &3u16.to_le_bytes()).unwrap();
+		assert!(matches!(calls.recv_timeout(TIMEOUT), Ok(WatchEvent::Changed { old: 0, new: 3, .. })));
+
+		// The target goes away and comes back
+		process.vm_release(base).unwrap();
+		assert!(matches!(events.recv_timeout(TIMEOUT), Ok(WatchEvent::ReadFailed { error: ERROR_PARTIAL_COPY, .. })));
+		process.map(0x10000, &9u32.to_le_bytes(), Protect::READ_WRITE).unwrap();
+		assert!(matches!(events.recv_timeout(TIMEOUT), Ok(WatchEvent::Recovered { value: 9, .. })));
+
+		assert!(watcher.unwatch(id));
+		assert!(!watcher.unwatch(id));
+		process.poke(base, &1u32.to_le_bytes()).unwrap();
+		assert!(events.recv_timeout(Duration::from_millis(50)).is_err());
+	}
+
+	#[test]
+	fn first_read() {
+		let process = Arc::new(MockProcess::new());
+		let watcher = MemoryWatcher::new(process, Duration::from_millis(1));
+		let (tx, calls) = mpsc::channel();
+		watcher.watch(Ptr64::<u32>::from(0x10000), move |event| { let _ = tx.send((thread::current().id(), event)); });
+		// The failed first read is reported from the background thread
+		let (id, event) = calls.recv_timeout(TIMEOUT).unwrap();
+		assert_ne!(id, thread::current().id());
+		assert!(matches!(event, WatchEvent::ReadFailed { error: ERROR_PARTIAL_COPY, .. }));
+	}
+
+	#[test]
+	fn wait_until() {
+		let process = Arc::new(MockProcess::new());
+		let base = process.map(0x10000, &[0; 4], Protect::READ_WRITE).unwrap();
+		let value = Ptr64::<u32>::from(base as u64);
+		let watcher = MemoryWatcher::new(process.clone(), Duration::from_millis(1));
+
+		let writer = {
+			let process = process.clone();
+			thread::spawn(move || {
+				for i in 1..=10u32 {
+					process.poke(base, &i.to_le_bytes()).unwrap();
+					thread::sleep(Duration::from_millis(1));
+				}
+			})
+		};
+		assert_eq!(watcher.wait_until(value, |&value| value >= 10, TIMEOUT), Ok(10));
+		writer.join().unwrap();
+		assert_eq!(watcher.wait_until(value, |&value| value == 0, Duration::from_millis(10)), Err(ERROR_TIMEOUT));
+		process.vm_release(base).unwrap();
+		assert_eq!(watcher.wait_until(value, |_| true, TIMEOUT), Err(ERROR_PARTIAL_COPY));
+	}
+}
@@ -0,0 +1,227 @@
+use std::{ptr, mem};
+use crate::winapi::*;
+use crate::process::Process;
+use crate::error::{ErrorCode, ERROR_PARTIAL_COPY};
+use crate::{Result, AsInner, IntoInner, FromInner};
+use super::*;
+
+//----------------------------------------------------------------
+
+/// Virtual memory API.
+impl VirtualMemory for Process {
+	#[inline]
+	fn vm_read_bytes<'a>(&self, address: usize, bytes: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let num_bytes = mem::size_of_val(bytes);
+		let success = unsafe {
+			ReadProcessMemory(
+				*self.as_inner(),
+				address as LPCVOID,
+				bytes.as_mut_ptr() as LPVOID,
+				num_bytes as SIZE_T,
+				ptr::null_mut(),
+			) != FALSE
+		};
+		if success {
+			Ok(bytes)
+		}
+		else {
+			Err(ErrorCode::last())
+		}
+	}
+	#[inline]
+	fn vm_read_partial<'a>(&self, address: usize, dest: &'a mut [u8]) -> Result<&'a mut [u8]> {
+		let mut bytes_read = 0;
+		let num_bytes = mem::size_of_val(dest);
+		let success = unsafe {
+			ReadProcessMemory(
+				*self.as_inner(),
+				address as LPCVOID,
+				dest.as_mut_ptr() as LPVOID,
+				num_bytes as SIZE_T,
+				&mut bytes_read,
+			) != FALSE
+		};
+		if success {
+			Ok(dest)
+		}
+		else {
+			let err = ErrorCode::last();
+			if err != ERROR_PARTIAL_COPY {
+				Err(err)
+			}
+			else {
+				Ok(unsafe { dest.get_unchecked_mut(..bytes_read as usize) })
+			}
+		}
+	}
+	#[inline]
+	fn vm_write_bytes(&self, address: usize, bytes: &[u8]) -> Result<()> {
+		let mut bytes_written = 0;
+		let num_bytes = mem::size_of_val(bytes);
+		let success = unsafe {
+			WriteProcessMemory(
+				*self.as_inner(),
+				address as LPVOID,
+				bytes.as_ptr() as LPCVOID,
+				num_bytes as SIZE_T,
+				&mut bytes_written,
+			) != FALSE
+		};
+		if success {
+			Ok(())
+		}
+		else {
+			Err(ErrorCode::last())
+		}
+	}
+	#[inline]
+	fn vm_write_partial<'a>(&self, address: usize, bytes: &'a [u8]) -> Result<&'a [u8]> {
+		let mut bytes_written = 0;
+		let num_bytes = mem::size_of_val(bytes);
+		let success = unsafe {
+			WriteProcessMemory(
+				*self.as_inner(),
+				address as LPVOID,
+				bytes.as_ptr() as LPCVOID,
+				num_bytes as SIZE_T,
+				&mut bytes_written,
+			) != FALSE
+		};
+		if success {
+			Ok(bytes)
+		}
+		else {
+			let err = ErrorCode::last();
+			if err != ERROR_PARTIAL_COPY {
+				Err(err)
+			}
+			else {
+				Ok(unsafe { bytes.get_unchecked(..bytes_written as usize) })
+			}
+		}
+	}
+	#[inline]
+	fn vm_alloc(&self, address: usize, len: usize, alloc_type: AllocType, protect: Protect) -> Result<usize> {
+		let result = unsafe {
+			VirtualAllocEx(
+				*self.as_inner(),
+				address as LPVOID,
+				len as SIZE_T,
+				alloc_type.into_inner(),
+				protect.into_inner(),
+			)
+		};
+		if !result.is_null() {
+			Ok(result as usize)
+		}
+		else {
+			Err(ErrorCode::last())
+		}
+	}
+	#[inline]
+	fn vm_free(&self, address: usize, len: usize, free_type: FreeType) -> Result<()> {
+		let success = unsafe {
+			VirtualFreeEx(
+				*self.as_inner(),
+				address as LPVOID,
+				len as SIZE_T,
+				free_type.into_inner(),
+			) != FALSE
+		};
+		if success {
+			Ok(())
+		}
+		else {
+			Err(ErrorCode::last())
+		}
+	}
+	#[inline]
+	fn vm_protect(&self, address: usize, len: usize, protect: Protect) -> Result<Protect> {
+		let mut old = 0;
+		let success = unsafe {
+			VirtualProtectEx(
+				*self.as_inner(),
+				address as LPVOID,
+				len as SIZE_T,
+				protect.into_inner(),
+				&mut old,
+			) != FALSE
+		};
+		if success {
+			Ok(unsafe { Protect::from_inner(old) })
+		}
+		else {
+			Err(ErrorCode::last())
+		}
+	}
+	#[inline]
+	fn vm_query(&self, address: usize) -> Result<MemoryInformation> {
+		let size = mem::size_of::<MEMORY_BASIC_INFORMATION>() as SIZE_T;
+		unsafe {
+			let mut mem_basic_info: MEMORY_BASIC_INFORMATION = mem::zeroed();
+			if VirtualQueryEx(*self.as_inner(), address as LPCVOID, &mut mem_basic_info, size) == size {
+				Ok(MemoryInformation::from(mem_basic_info))
+			}
+			else {
+				Err(ErrorCode::last())
+			}
+		}
+	}
+}
+
+/// Windows specific virtual memory API.
+impl Process {
+	/// Queries the working set ex of virtual memory in the process.
+	#[inline]
+	pub fn vm_query_ws_ex(&self, address: usize) -> Result<WorkingSetExBlock> {
+		let size = mem::size_of::<PSAPI_WORKING_SET_EX_INFORMATION>() as DWORD;
+		unsafe {
+			let mut buffer: PSAPI_WORKING_SET_EX_INFORMATION = mem::zeroed();
+			buffer.VirtualAddress = address as PVOID;
+			if K32QueryWorkingSetEx(*self.as_inner(), &mut buffer as *mut _ as PVOID, size) != 0 {
+				Ok(WorkingSetExBlock::from(buffer.VirtualAttributes))
+			}
+			else {
+				Err(ErrorCode::last())
+			}
+		}
+	}
+	/// Foreach virtual memory allocation and associated mapped filename.
+	#[inline]
+	pub fn vm_allocations<F: FnMut(&ForEachAllocation, Option<&[u16]>)>(&self, mut f: F) -> Result<()> {
+		let mut base_address = 0;
+		let mut allocation_base = 0;
+		let mut allocation_size = 0;
+		let mut allocation_protect = 0;
+		let mut regions_state = 0;
+		let mut regions_protect = 0;
+		let mut regions_type = 0;
+		let mut mapped_file_name = vec![0u16; 0x200];
+		loop {
+			let mi = self.vm_query(base_address)?;
+			if mi.allocation_base != allocation_base {
+				let path = self.get_mapped_file_name_wide(allocation_base, &mut mapped_file_name)
+					.ok().map(|path| &*path);
+				f(&ForEachAllocation {
+					allocation_base,
+					allocation_size,
+					allocation_protect,
+					regions_state,
+					regions_protect,
+					regions_type,
+				}, path);
+				allocation_base = mi.allocation_base;
+				allocation_size = 0;
+				allocation_protect = mi.allocation_protect.into_inner();
+				regions_state = 0;
+				regions_protect = 0;
+				regions_type = 0;
+			}
+			allocation_size += mi.region_size;
+			regions_state |= mi.state.into_inner();
+			regions_protect |= mi.protect.into_inner();
+			regions_type |= mi.mem_type.into_inner();
+			base_address += mi.region_size;
+		}
+	}
+}
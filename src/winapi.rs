@@ -14,12 +14,12 @@ pub use winapi::um::wincon::*;
 pub use winapi::um::wingdi::*;
 pub use winapi::um::winnt::*;
 pub use winapi::um::winuser::*;
+pub use winapi::um::wow64apiset::*;
 pub use winapi::shared::basetsd::*;
 pub use winapi::shared::minwindef::*;
 // pub use winapi::shared::ntdef::*;
 pub use winapi::shared::ntdef::UNICODE_STRING;
 pub use winapi::shared::windef::*;
-pub use winapi::shared::winerror::*;
 pub use winapi::ctypes::*;
 
 pub use ntapi::ntexapi::*;
@@ -1,4 +1,5 @@
-use std::{mem, ptr};
+use std::ptr;
+use std::mem::MaybeUninit;
 use std::ffi::OsString;
 use std::os::windows::ffi::OsStringExt;
 use crate::winapi::*;
@@ -45,7 +46,7 @@ impl Window {
 	pub fn class(self) -> Result<OsString> {
 		unsafe {
 			// 260 ought to be enough for everyone.
-			let mut buf: [WCHAR; 260] = mem::uninitialized();
+			let mut buf: [WCHAR; 260] = [0; 260];
 			let len = RealGetWindowClassW(self.into_inner(), buf.as_mut_ptr(), 260);
 			if len == 0 {
 				Err(ErrorCode::last())
@@ -98,7 +99,7 @@ impl Window {
 	pub fn title(self) -> Result<OsString> {
 		unsafe {
 			// 260 ought to be enough for everyone.
-			let mut buf: [WCHAR; 260] = mem::uninitialized();
+			let mut buf: [WCHAR; 260] = [0; 260];
 			let len = GetWindowTextW(self.into_inner(), buf.as_mut_ptr(), 260);
 			if len <= 0 {
 				Err(ErrorCode::last())
@@ -111,7 +112,7 @@ impl Window {
 	/// Returns the thread and process id associated with this window.
 	pub fn thread_process_id(self) -> (ThreadId, ProcessId) {
 		unsafe {
-			let mut process_id: DWORD = mem::uninitialized();
+			let mut process_id: DWORD = 0;
 			let thread_id = GetWindowThreadProcessId(self.into_inner(), &mut process_id);
 			(ThreadId::from_inner(thread_id), ProcessId::from_inner(process_id))
 		}
@@ -119,11 +120,12 @@ impl Window {
 	/// Retrieves the coordinates of a window's client area.
 	pub fn client_area(self) -> Result<(i32, i32)> {
 		unsafe {
-			let mut rc = mem::uninitialized();
-			if GetClientRect(self.into_inner(), &mut rc) == FALSE {
+			let mut rc = MaybeUninit::<RECT>::uninit();
+			if GetClientRect(self.into_inner(), rc.as_mut_ptr()) == FALSE {
 				Err(ErrorCode::last())
 			}
 			else {
+				let rc = rc.assume_init();
 				Ok((rc.right, rc.bottom))
 			}
 		}